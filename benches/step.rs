@@ -0,0 +1,60 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use robot_dsl::{
+    channel::Transcript, interpreter::Interpreter, parser::Parser, scanner::Scanner, syntax::Stmt,
+};
+
+const RECURSION: &str = r#"
+var calls = 0;
+step down(depth) {
+    calls = calls + 1;
+    var more = depth != 0;
+    branch (more) down(depth - 1);
+}
+loop {
+    down(100);
+    branch (calls == 10100) exit;
+}
+"#;
+
+const PASSING: &str = r#"
+var rounds = 0;
+step chatty(times) {
+    var a = times; var b = a; var c = b; var d = c;
+    var e = d; var f = e; var g = f; var h = g;
+    speak "never";
+}
+step apply(action, other) {
+    var held = action;
+    held = other;
+}
+loop {
+    apply(chatty, chatty);
+    var copy = chatty;
+    rounds = rounds + 1;
+    branch (rounds == 10000) exit;
+}
+"#;
+
+fn parse(source: &str) -> Vec<Stmt> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    Parser::new(tokens).parse().expect("Failed to parse")
+}
+
+fn run(c: &mut Criterion, name: &str, source: &str) {
+    let statements = parse(source);
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            let mut interpreter = Interpreter::builder().channel(Transcript::new(&[])).build();
+            interpreter.interpret(&statements).unwrap()
+        })
+    });
+}
+
+fn benchmark(c: &mut Criterion) {
+    run(c, "deep recursion", RECURSION);
+    run(c, "passing steps", PASSING);
+}
+
+criterion_group!(benches, benchmark);
+criterion_main!(benches);
@@ -23,12 +23,21 @@ fn main() {
         .entry("step", "TokenType::Step")
         .entry("true", "TokenType::True")
         .entry("var", "TokenType::Var")
+        .entry("const", "TokenType::Const")
         .entry("exit", "TokenType::Exit")
         .entry("input", "TokenType::Input")
         .entry("loop", "TokenType::Loop")
         .entry("inputn", "TokenType::Inputn")
         .entry("nil", "TokenType::Nil")
+        .entry("import", "TokenType::Import")
+        .entry("as", "TokenType::As")
+        .entry("match", "TokenType::Match")
+        .entry("intent", "TokenType::Intent")
+        .entry("menu", "TokenType::Menu")
+        .entry("state", "TokenType::State")
+        .entry("on", "TokenType::On")
+        .entry("goto", "TokenType::Goto")
         .build(&mut file)
         .unwrap();
-    write!(&mut file, ";\n").unwrap();
+    writeln!(&mut file, ";").unwrap();
 }
@@ -0,0 +1,22 @@
+use std::io::{self, BufReader};
+use std::process::exit;
+use std::thread;
+
+use robot_dsl::{dap, interpreter::STACK_SIZE};
+
+fn main() {
+    // 解释器递归地执行语句，在足够大的栈上运行才能达到默认的调用深度
+    let adapter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(|| {
+            // 标准输出是协议通道，诊断信息只能写到标准错误
+            if let Err(e) = dap::run(BufReader::new(io::stdin()), io::stdout()) {
+                eprintln!("{}", e);
+                exit(74)
+            }
+        })
+        .expect("failed to start the adapter thread");
+    if let Err(panic) = adapter.join() {
+        std::panic::resume_unwind(panic)
+    }
+}
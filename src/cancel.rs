@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+///
+/// 取消令牌，宿主通过它让正在运行的解释器尽快停下
+///
+/// 令牌可以克隆并发送到其他线程，所有克隆共享同一个状态。
+/// 解释器在每条语句、每次循环和 listen 等待期间检查令牌，
+/// 发现已取消时返回 `Error::Cancelled`。
+///
+/// # 使用示例
+/// let token = CancelToken::new();
+/// let mut interpreter = Interpreter::builder().cancel_token(token.clone()).build();
+/// thread::spawn(move || {
+///     thread::sleep(Duration::from_secs(5));
+///     token.cancel();
+/// });
+/// interpreter.interpret(&statements)?;
+///
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    lock: Mutex<()>,
+    wakeup: Condvar,
+}
+
+impl CancelToken {
+    ///
+    /// 创建一个未取消的令牌
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// 取消，同时唤醒正在 listen 中等待的解释器
+    ///
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        let _guard = self.inner.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.inner.wakeup.notify_all();
+    }
+
+    ///
+    /// 是否已经取消
+    ///
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+    }
+
+    ///
+    /// 等待一段时间，期间被取消时提前返回
+    ///
+    /// 时间长到无法表示截止时刻时一直等到被取消。
+    ///
+    /// # 返回值
+    /// * 是否等满了整段时间，被取消时为 false
+    ///
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now().checked_add(duration);
+        let mut guard = self.inner.lock.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if self.is_cancelled() {
+                return false;
+            }
+            let now = Instant::now();
+            guard = match deadline {
+                Some(deadline) if now >= deadline => return true,
+                Some(deadline) => match self.inner.wakeup.wait_timeout(guard, deadline - now) {
+                    Ok((guard, _)) => guard,
+                    Err(poisoned) => poisoned.into_inner().0,
+                },
+                None => self
+                    .inner
+                    .wakeup
+                    .wait(guard)
+                    .unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
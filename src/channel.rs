@@ -0,0 +1,166 @@
+use crate::error::Error;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+///
+/// 解释器与用户对话的通道，speak、input 和 menu 都经由它读写
+///
+pub trait Channel {
+    ///
+    /// 向用户输出一行
+    ///
+    /// # 参数列表
+    /// * text: 输出的内容
+    ///
+    fn speak(&mut self, text: &str) -> Result<(), Error>;
+
+    ///
+    /// 读取用户输入的一行
+    ///
+    /// # 返回值
+    /// * 去掉行尾换行符的输入，输入结束时为 None
+    ///
+    fn listen(&mut self) -> Result<Option<String>, Error>;
+}
+
+///
+/// 标准输入输出通道，命令行运行脚本时使用
+///
+#[derive(Default)]
+pub struct Console;
+
+impl Channel for Console {
+    fn speak(&mut self, text: &str) -> Result<(), Error> {
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", text)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    fn listen(&mut self) -> Result<Option<String>, Error> {
+        let mut input = String::new();
+        if io::stdin().lock().read_line(&mut input)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(input.trim_end_matches(['\r', '\n']).to_string()))
+    }
+}
+
+///
+/// 预先给定输入并记录输出的通道，用于测试和宿主程序
+///
+/// 克隆得到的通道共享同一份输入与输出，
+/// 因此可以把一个克隆交给解释器，用另一个查看对话记录。
+///
+/// # 使用示例
+/// let transcript = Transcript::new(&["b"]);
+/// let mut interpreter = Interpreter::builder().channel(transcript.clone()).build();
+/// ...
+/// assert_eq!(transcript.outputs(), vec!["1. Check balance (b)"]);
+///
+#[derive(Clone, Default)]
+pub struct Transcript {
+    inputs: Rc<RefCell<VecDeque<String>>>,
+    outputs: Rc<RefCell<Vec<String>>>,
+}
+
+impl Transcript {
+    ///
+    /// 创建通道
+    ///
+    /// # 参数列表
+    /// * inputs: 依次作为用户输入的各行
+    ///
+    pub fn new(inputs: &[&str]) -> Self {
+        Transcript {
+            inputs: Rc::new(RefCell::new(
+                inputs.iter().map(|line| line.to_string()).collect(),
+            )),
+            outputs: Rc::default(),
+        }
+    }
+
+    ///
+    /// 追加一行用户输入
+    ///
+    pub fn push_input(&self, line: &str) {
+        self.inputs.borrow_mut().push_back(line.to_string());
+    }
+
+    ///
+    /// 到目前为止的全部输出
+    ///
+    pub fn outputs(&self) -> Vec<String> {
+        self.outputs.borrow().clone()
+    }
+
+    ///
+    /// 取出到目前为止的全部输出并清空记录
+    ///
+    pub fn take_outputs(&self) -> Vec<String> {
+        self.outputs.borrow_mut().drain(..).collect()
+    }
+}
+
+impl Channel for Transcript {
+    fn speak(&mut self, text: &str) -> Result<(), Error> {
+        self.outputs.borrow_mut().push(text.to_string());
+        Ok(())
+    }
+
+    fn listen(&mut self) -> Result<Option<String>, Error> {
+        Ok(self.inputs.borrow_mut().pop_front())
+    }
+}
+
+///
+/// 可恢复执行的会话使用的通道
+///
+/// 用户消息由宿主放入收件箱，没有待处理的消息时 listen 返回 `Error::Suspend`，
+/// 解释器随即暂停，等宿主收到下一条消息后再恢复执行。
+///
+#[derive(Clone, Default)]
+pub struct Mailbox {
+    inbox: Rc<RefCell<VecDeque<String>>>,
+    outbox: Rc<RefCell<Vec<String>>>,
+}
+
+impl Mailbox {
+    ///
+    /// 创建空的通道
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// 放入一条用户消息
+    ///
+    pub fn post(&self, message: &str) {
+        self.inbox.borrow_mut().push_back(message.to_string());
+    }
+
+    ///
+    /// 取出到目前为止的全部输出
+    ///
+    pub fn take_outputs(&self) -> Vec<String> {
+        self.outbox.borrow_mut().drain(..).collect()
+    }
+}
+
+impl Channel for Mailbox {
+    fn speak(&mut self, text: &str) -> Result<(), Error> {
+        self.outbox.borrow_mut().push(text.to_string());
+        Ok(())
+    }
+
+    fn listen(&mut self) -> Result<Option<String>, Error> {
+        match self.inbox.borrow_mut().pop_front() {
+            Some(message) => Ok(Some(message)),
+            None => Err(Error::Suspend),
+        }
+    }
+}
@@ -0,0 +1,719 @@
+use crate::channel::Channel;
+use crate::debug::{self, describe, Breakpoints, Context, Debugger, Reason, Resume};
+use crate::error::Error;
+use crate::interpreter::Interpreter;
+use crate::object::Object;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::syntax::Stmt;
+
+use serde_json::{json, Value};
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// 消息的最大长度
+const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+/// 脚本只在一个线程中运行
+const THREAD_ID: i64 = 1;
+
+///
+/// 读取一条调试适配器协议消息，消息由 Content-Length 头和 JSON 正文组成
+///
+/// # 返回值
+/// * 消息，输入在消息开始前结束时为 None
+/// * 消息格式错误时返回 InvalidData 错误
+///
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    let mut started = false;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return match started {
+                false => Ok(None),
+                true => Err(invalid("input closed inside the headers")),
+            };
+        }
+        started = true;
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        match line.split_once(':') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case("content-length") => {
+                let value = value.trim().parse::<usize>();
+                length = Some(value.map_err(|_| invalid("malformed Content-Length"))?);
+            }
+            Some(_) => (),
+            None => return Err(invalid("malformed header")),
+        }
+    }
+    let length = length.ok_or_else(|| invalid("missing Content-Length"))?;
+    if length > MAX_MESSAGE {
+        return Err(invalid("message too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| invalid(&e.to_string()))
+}
+
+///
+/// 写出一条调试适配器协议消息
+///
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+///
+/// 在标准输入输出上运行调试适配器，直到客户端断开
+///
+/// 客户端的请求由单独的线程读取，脚本运行期间也能收到暂停、修改断点等请求。
+/// 脚本的 speak 输出作为 output 事件发给客户端，
+/// 脚本读取输入时暂停，用户在调试控制台中输入的内容作为脚本的输入。
+///
+/// # 参数列表
+/// * input: 客户端发来的消息
+/// * output: 发给客户端的消息
+///
+/// # 使用示例
+/// dap::run(BufReader::new(io::stdin()), io::stdout())?;
+///
+pub fn run<R, W>(input: R, output: W) -> Result<(), Error>
+where
+    R: BufRead + Send + 'static,
+    W: Write + 'static,
+{
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || {
+        let mut input = input;
+        while let Ok(Some(message)) = read_message(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    let connection = Rc::new(RefCell::new(Connection::new(requests, Box::new(output))));
+
+    let launch = match connection.borrow_mut().configure()? {
+        Some(launch) => launch,
+        None => return Ok(()),
+    };
+    let program = connection.borrow().program.clone();
+    let frontend = Adapter {
+        connection: Rc::clone(&connection),
+    };
+    let mut interpreter = Interpreter::builder()
+        .channel(Console {
+            connection: Rc::clone(&connection),
+        })
+        .debug_hook(
+            Debugger::new(frontend)
+                .breakpoints(launch.breakpoints)
+                .stop_on_entry(launch.stop_on_entry),
+        )
+        .build();
+    interpreter.set_script_path(&program)?;
+    let result = interpreter.interpret(&launch.statements);
+
+    let mut connection = connection.borrow_mut();
+    if connection.disconnected {
+        return Ok(());
+    }
+    let code = match result {
+        Ok(()) | Err(Error::Exit) => 0,
+        Err(error) => {
+            let message = match error {
+                Error::Runtime { token, message } => format!("[line {}] {}", token.line, message),
+                other => other.to_string(),
+            };
+            connection.event(
+                "output",
+                json!({ "category": "stderr", "output": message + "\n" }),
+            )?;
+            70
+        }
+    };
+    connection.event("exited", json!({ "exitCode": code }))?;
+    connection.event("terminated", Value::Null)?;
+    while let Some(request) = connection.next() {
+        match command(&request) {
+            "disconnect" | "terminate" => return connection.respond(&request, Value::Null),
+            "threads" => connection.threads(&request)?,
+            _ => connection.fail(&request, "The script has ended.")?,
+        }
+    }
+    Ok(())
+}
+
+///
+/// launch 请求给出的程序与配置阶段设置的断点
+///
+struct Launch {
+    statements: Vec<Stmt>,
+    stop_on_entry: bool,
+    breakpoints: Breakpoints,
+}
+
+///
+/// 与客户端的连接，由调试前端与对话通道共享
+///
+struct Connection {
+    requests: Receiver<Value>,
+    output: Box<dyn Write>,
+    seq: i64,
+    /// 运行期间收到的、只能在暂停时处理的请求
+    deferred: VecDeque<Value>,
+    /// 用户在调试控制台中输入、尚未被脚本读取的内容
+    inputs: VecDeque<String>,
+    /// 客户端的行号是否从 1 开始
+    lines_start_at1: bool,
+    program: PathBuf,
+    /// 程序中有语句的行，断点落在这些行上
+    lines: BTreeSet<i32>,
+    disconnected: bool,
+}
+
+impl Connection {
+    fn new(requests: Receiver<Value>, output: Box<dyn Write>) -> Self {
+        Connection {
+            requests,
+            output,
+            seq: 0,
+            deferred: VecDeque::new(),
+            inputs: VecDeque::new(),
+            lines_start_at1: true,
+            program: PathBuf::new(),
+            lines: BTreeSet::new(),
+            disconnected: false,
+        }
+    }
+
+    ///
+    /// 下一个请求，客户端断开时为 None
+    ///
+    fn next(&mut self) -> Option<Value> {
+        self.deferred
+            .pop_front()
+            .or_else(|| self.requests.recv().ok())
+    }
+
+    ///
+    /// 处理配置阶段的请求，直到收到 launch 与 configurationDone
+    ///
+    /// # 返回值
+    /// * 要运行的程序，客户端在此之前断开时为 None
+    ///
+    fn configure(&mut self) -> Result<Option<Launch>, Error> {
+        let mut breakpoints = Breakpoints::default();
+        let mut launched: Option<(Vec<Stmt>, bool)> = None;
+        let mut configured = false;
+        // launch 之前收到的断点，等读入程序后再校验
+        let mut early = Vec::new();
+        while !configured || launched.is_none() {
+            let request = match self.next() {
+                Some(request) => request,
+                None => return Ok(None),
+            };
+            match command(&request) {
+                "initialize" => {
+                    let arguments = &request["arguments"];
+                    self.lines_start_at1 = arguments["linesStartAt1"].as_bool().unwrap_or(true);
+                    self.respond(
+                        &request,
+                        json!({
+                            "supportsConfigurationDoneRequest": true,
+                            "supportsFunctionBreakpoints": true,
+                        }),
+                    )?;
+                    self.event("initialized", Value::Null)?;
+                }
+                "launch" => match self.load(&request) {
+                    Ok(statements) => {
+                        let stop_on_entry = request["arguments"]["stopOnEntry"]
+                            .as_bool()
+                            .unwrap_or(false);
+                        launched = Some((statements, stop_on_entry));
+                        self.respond(&request, Value::Null)?;
+                        for request in early.drain(..) {
+                            self.set_breakpoints(&request, &mut breakpoints)?;
+                        }
+                    }
+                    Err(message) => self.fail(&request, &message)?,
+                },
+                "setBreakpoints" if launched.is_none() => early.push(request),
+                "setBreakpoints" => self.set_breakpoints(&request, &mut breakpoints)?,
+                "setFunctionBreakpoints" => {
+                    self.set_function_breakpoints(&request, &mut breakpoints)?
+                }
+                "setExceptionBreakpoints" => self.respond(&request, Value::Null)?,
+                "configurationDone" => {
+                    configured = true;
+                    self.respond(&request, Value::Null)?;
+                }
+                "threads" => self.threads(&request)?,
+                "disconnect" | "terminate" => {
+                    self.respond(&request, Value::Null)?;
+                    return Ok(None);
+                }
+                _ => self.fail(&request, "Unsupported request.")?,
+            }
+        }
+        Ok(launched.map(|(statements, stop_on_entry)| Launch {
+            statements,
+            stop_on_entry,
+            breakpoints,
+        }))
+    }
+
+    ///
+    /// 读入并解析 launch 请求中的程序
+    ///
+    fn load(&mut self, request: &Value) -> Result<Vec<Stmt>, String> {
+        let program = match request["arguments"]["program"].as_str() {
+            Some(program) => PathBuf::from(program),
+            None => return Err("Missing 'program' in the launch arguments.".to_string()),
+        };
+        let source = fs::read_to_string(&program)
+            .map_err(|e| format!("Cannot read '{}': {}", program.display(), e))?;
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+        let statements = Parser::new(tokens)
+            .parse()
+            .map_err(|_| format!("'{}' has syntax errors.", program.display()))?;
+        self.lines.clear();
+        for statement in &statements {
+            collect_lines(statement, &mut self.lines);
+        }
+        self.program = program;
+        Ok(statements)
+    }
+
+    fn line_to_client(&self, line: i32) -> i64 {
+        i64::from(line) - i64::from(!self.lines_start_at1)
+    }
+
+    fn line_from_client(&self, line: i64) -> i32 {
+        (line + i64::from(!self.lines_start_at1)) as i32
+    }
+
+    fn is_program(&self, path: &str) -> bool {
+        let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.into());
+        canonical(Path::new(path)) == canonical(&self.program)
+    }
+
+    ///
+    /// 设置程序中的行号断点，断点移到所在行或其后第一条语句的行上
+    ///
+    fn set_breakpoints(
+        &mut self,
+        request: &Value,
+        breakpoints: &mut Breakpoints,
+    ) -> Result<(), Error> {
+        let arguments = &request["arguments"];
+        let requested = arguments["breakpoints"].as_array().cloned();
+        let path = arguments["source"]["path"].as_str().unwrap_or_default();
+        let own = self.is_program(path);
+        if own {
+            breakpoints.clear_lines();
+        }
+        let mut verified = Vec::new();
+        for breakpoint in requested.unwrap_or_default() {
+            let wanted = breakpoint["line"].as_i64().unwrap_or_default();
+            let line = self.line_from_client(wanted);
+            match self.lines.range(line..).next().copied() {
+                Some(actual) if own => {
+                    breakpoints.add_line(actual);
+                    verified.push(json!({ "verified": true, "line": self.line_to_client(actual) }));
+                }
+                _ => verified.push(json!({
+                    "verified": false,
+                    "line": wanted,
+                    "message": match own {
+                        true => "No statement at or after this line.",
+                        false => "Breakpoints are only supported in the launched program.",
+                    },
+                })),
+            }
+        }
+        self.respond(request, json!({ "breakpoints": verified }))
+    }
+
+    ///
+    /// 设置 step 断点，进入同名 step 时暂停
+    ///
+    fn set_function_breakpoints(
+        &mut self,
+        request: &Value,
+        breakpoints: &mut Breakpoints,
+    ) -> Result<(), Error> {
+        breakpoints.clear_steps();
+        let requested = request["arguments"]["breakpoints"].as_array().cloned();
+        let mut verified = Vec::new();
+        for breakpoint in requested.unwrap_or_default() {
+            if let Some(name) = breakpoint["name"].as_str() {
+                breakpoints.add_step(name);
+                verified.push(json!({ "verified": true }));
+            }
+        }
+        self.respond(request, json!({ "breakpoints": verified }))
+    }
+
+    fn threads(&mut self, request: &Value) -> Result<(), Error> {
+        self.respond(
+            request,
+            json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+        )
+    }
+
+    fn stack_trace(&mut self, request: &Value, context: &Context<'_>) -> Result<(), Error> {
+        let source = json!({
+            "name": self.program.file_name().map(|name| name.to_string_lossy()),
+            "path": self.program.to_string_lossy(),
+        });
+        let frames: Vec<Value> = context
+            .stack()
+            .iter()
+            .enumerate()
+            .map(|(id, frame)| {
+                json!({
+                    "id": id,
+                    "name": frame.name,
+                    "line": self.line_to_client(frame.line),
+                    "column": 1,
+                    "source": source,
+                })
+            })
+            .collect();
+        let total = frames.len();
+        self.respond(
+            request,
+            json!({ "stackFrames": frames, "totalFrames": total }),
+        )
+    }
+
+    ///
+    /// 作用域的 variablesReference 是它在 `Context::scopes` 中的下标加一，
+    /// 只有最内层的调用帧能看到局部作用域
+    ///
+    fn scopes(&mut self, request: &Value, scopes: &[Vec<(String, Object)>]) -> Result<(), Error> {
+        let innermost = request["arguments"]["frameId"].as_i64() == Some(0);
+        let body: Vec<Value> = scopes
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| innermost || index + 1 == scopes.len())
+            .map(|(index, _)| {
+                let name = match index {
+                    _ if index + 1 == scopes.len() => "Globals",
+                    0 => "Locals",
+                    _ => "Enclosing",
+                };
+                json!({ "name": name, "variablesReference": index + 1, "expensive": false })
+            })
+            .collect();
+        self.respond(request, json!({ "scopes": body }))
+    }
+
+    fn variables(
+        &mut self,
+        request: &Value,
+        scopes: &[Vec<(String, Object)>],
+    ) -> Result<(), Error> {
+        let reference = request["arguments"]["variablesReference"]
+            .as_u64()
+            .unwrap_or_default() as usize;
+        let variables: Vec<Value> = reference
+            .checked_sub(1)
+            .and_then(|index| scopes.get(index))
+            .map(|scope| {
+                scope
+                    .iter()
+                    .map(|(name, value)| {
+                        json!({
+                            "name": name,
+                            "value": describe(value),
+                            "type": value.type_name(),
+                            "variablesReference": 0,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.respond(request, json!({ "variables": variables }))
+    }
+
+    ///
+    /// 求值请求：脚本等待输入时，调试控制台中的内容作为输入，否则查找同名变量
+    ///
+    fn evaluate(
+        &mut self,
+        request: &Value,
+        context: Option<&Context<'_>>,
+        waiting: bool,
+    ) -> Result<(), Error> {
+        let arguments = &request["arguments"];
+        let expression = arguments["expression"].as_str().unwrap_or_default();
+        if arguments["context"].as_str() == Some("repl") && (waiting || context.is_none()) {
+            self.inputs.push_back(expression.to_string());
+            return self.respond(
+                request,
+                json!({ "result": format!("input: {}", expression), "variablesReference": 0 }),
+            );
+        }
+        match context.and_then(|context| context.lookup(expression.trim())) {
+            Some(value) => self.respond(
+                request,
+                json!({
+                    "result": describe(&value),
+                    "type": value.type_name(),
+                    "variablesReference": 0,
+                }),
+            ),
+            None => self.fail(
+                request,
+                &format!("Undefined variable '{}'.", expression.trim()),
+            ),
+        }
+    }
+
+    fn disconnect<T>(&mut self, request: &Value) -> Result<T, Error> {
+        self.respond(request, Value::Null)?;
+        self.disconnected = true;
+        Err(Error::Exit)
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<(), Error> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.output, &message)?;
+        Ok(())
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> Result<(), Error> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Value, message: &str) -> Result<(), Error> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<(), Error> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+}
+
+fn command(request: &Value) -> &str {
+    request["command"].as_str().unwrap_or_default()
+}
+
+fn collect_lines(statement: &Stmt, lines: &mut BTreeSet<i32>) {
+    lines.extend(statement.line());
+    for child in statement.children() {
+        collect_lines(child, lines);
+    }
+}
+
+///
+/// 即将执行的语句是否读取用户输入
+///
+fn reads_input(statement: &Stmt) -> bool {
+    matches!(statement, Stmt::Input { .. } | Stmt::Inputn { .. })
+}
+
+///
+/// 调试适配器前端，把暂停转换为 stopped 事件，并在暂停期间回应查询请求
+///
+struct Adapter {
+    connection: Rc<RefCell<Connection>>,
+}
+
+impl debug::Frontend for Adapter {
+    fn stopped(
+        &mut self,
+        reason: Reason,
+        context: &Context<'_>,
+        breakpoints: &mut Breakpoints,
+    ) -> Result<Resume, Error> {
+        let (reason, description) = match reason {
+            Reason::Entry => ("entry", "Paused on entry".to_string()),
+            Reason::Breakpoint => ("breakpoint", "Paused on breakpoint".to_string()),
+            Reason::Step(name) => ("function breakpoint", format!("Entered step {}", name)),
+            Reason::Stepped => ("step", "Paused".to_string()),
+            Reason::Pause => ("pause", "Paused".to_string()),
+            Reason::Input => ("pause", "Waiting for input".to_string()),
+        };
+        let mut connection = self.connection.borrow_mut();
+        connection.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": description,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        )?;
+        let scopes = context.scopes();
+        loop {
+            let request = match connection.next() {
+                Some(request) => request,
+                None => return Err(Error::Exit),
+            };
+            let resume = match command(&request) {
+                "continue" => Resume::Continue,
+                "next" => Resume::StepOver,
+                "stepIn" => Resume::StepIn,
+                "stepOut" => Resume::StepOut,
+                "threads" => {
+                    connection.threads(&request)?;
+                    continue;
+                }
+                "stackTrace" => {
+                    connection.stack_trace(&request, context)?;
+                    continue;
+                }
+                "scopes" => {
+                    connection.scopes(&request, &scopes)?;
+                    continue;
+                }
+                "variables" => {
+                    connection.variables(&request, &scopes)?;
+                    continue;
+                }
+                "evaluate" => {
+                    let waiting = reads_input(context.statement) && connection.inputs.is_empty();
+                    connection.evaluate(&request, Some(context), waiting)?;
+                    continue;
+                }
+                "setBreakpoints" => {
+                    connection.set_breakpoints(&request, breakpoints)?;
+                    continue;
+                }
+                "setFunctionBreakpoints" => {
+                    connection.set_function_breakpoints(&request, breakpoints)?;
+                    continue;
+                }
+                "pause" => {
+                    connection.respond(&request, Value::Null)?;
+                    continue;
+                }
+                "disconnect" | "terminate" => return connection.disconnect(&request),
+                _ => {
+                    connection.fail(&request, "Unsupported request.")?;
+                    continue;
+                }
+            };
+            let body = match resume {
+                Resume::Continue => json!({ "allThreadsContinued": true }),
+                _ => Value::Null,
+            };
+            connection.respond(&request, body)?;
+            return Ok(resume);
+        }
+    }
+
+    fn poll(
+        &mut self,
+        context: &Context<'_>,
+        breakpoints: &mut Breakpoints,
+    ) -> Result<Option<Reason>, Error> {
+        let mut connection = self.connection.borrow_mut();
+        let mut pause = false;
+        // 只能在暂停时处理的请求留到下次暂停，之后的请求也随之推迟，保持请求的顺序
+        while connection.deferred.is_empty() {
+            let request = match connection.requests.try_recv() {
+                Ok(request) => request,
+                Err(_) => break,
+            };
+            match command(&request) {
+                "setBreakpoints" => connection.set_breakpoints(&request, breakpoints)?,
+                "setFunctionBreakpoints" => {
+                    connection.set_function_breakpoints(&request, breakpoints)?
+                }
+                "threads" => connection.threads(&request)?,
+                "evaluate" => connection.evaluate(&request, None, true)?,
+                "pause" => {
+                    connection.respond(&request, Value::Null)?;
+                    pause = true;
+                }
+                "disconnect" | "terminate" => return connection.disconnect(&request),
+                _ => connection.deferred.push_back(request),
+            }
+        }
+        if pause {
+            Ok(Some(Reason::Pause))
+        } else if reads_input(context.statement) && connection.inputs.is_empty() {
+            Ok(Some(Reason::Input))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+///
+/// 调试适配器的对话通道，输出作为 output 事件，输入来自调试控制台
+///
+struct Console {
+    connection: Rc<RefCell<Connection>>,
+}
+
+impl Channel for Console {
+    fn speak(&mut self, text: &str) -> Result<(), Error> {
+        self.connection.borrow_mut().event(
+            "output",
+            json!({ "category": "stdout", "output": format!("{}\n", text) }),
+        )
+    }
+
+    fn listen(&mut self) -> Result<Option<String>, Error> {
+        let mut connection = self.connection.borrow_mut();
+        if let Some(input) = connection.inputs.pop_front() {
+            return Ok(Some(input));
+        }
+        // menu 等语句读取输入时不经过调试钩子，在这里等待调试控制台的输入
+        connection.event(
+            "output",
+            json!({
+                "category": "console",
+                "output": "Waiting for input, type it in the debug console.\n",
+            }),
+        )?;
+        while let Some(request) = connection.next() {
+            match command(&request) {
+                "evaluate" => connection.evaluate(&request, None, true)?,
+                "threads" => connection.threads(&request)?,
+                "disconnect" | "terminate" => return connection.disconnect(&request),
+                _ => connection.fail(&request, "The script is waiting for input.")?,
+            }
+            if let Some(input) = connection.inputs.pop_front() {
+                return Ok(Some(input));
+            }
+        }
+        Ok(None)
+    }
+}
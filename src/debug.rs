@@ -0,0 +1,595 @@
+use crate::channel::Channel;
+use crate::env::Environment;
+use crate::error::Error;
+use crate::function::Function;
+use crate::object::Object;
+use crate::syntax::Stmt;
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+///
+/// 解释器的调试钩子，在执行每条带行号的语句之前被调用
+///
+/// 钩子返回错误时解释器停止执行并返回该错误，例如返回 `Error::Exit` 结束脚本。
+///
+/// # 使用示例
+/// let mut interpreter = Interpreter::builder()
+///     .debug_hook(Debugger::new(CommandLine::new(Console)))
+///     .build();
+///
+pub trait Hook {
+    ///
+    /// 即将执行一条语句
+    ///
+    /// # 参数列表
+    /// * context: 语句所在行、当前环境与调用栈
+    ///
+    fn statement(&mut self, context: &Context<'_>) -> Result<(), Error>;
+}
+
+///
+/// 钩子被调用时解释器的状态
+///
+pub struct Context<'a> {
+    /// 即将执行的语句所在行
+    pub line: i32,
+    /// 即将执行的语句
+    pub statement: &'a Stmt,
+    environment: &'a Rc<RefCell<Environment>>,
+    calls: &'a [(String, i32)],
+    entered: bool,
+}
+
+///
+/// 调用栈中的一层
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    /// step 名，脚本顶层为 `<script>`
+    pub name: String,
+    /// 这一层正在执行的行
+    pub line: i32,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(
+        line: i32,
+        statement: &'a Stmt,
+        environment: &'a Rc<RefCell<Environment>>,
+        calls: &'a [(String, i32)],
+        entered: bool,
+    ) -> Self {
+        Context {
+            line,
+            statement,
+            environment,
+            calls,
+            entered,
+        }
+    }
+
+    ///
+    /// 是否为刚进入的 step 函数体中的第一条语句
+    ///
+    /// 尾调用不加深调用栈，只能通过这里识别。
+    ///
+    pub fn entered(&self) -> bool {
+        self.entered
+    }
+
+    ///
+    /// 正在执行的 step 调用层数，脚本顶层为 0
+    ///
+    pub fn depth(&self) -> usize {
+        self.calls.len()
+    }
+
+    ///
+    /// 调用栈，最内层在前
+    ///
+    pub fn stack(&self) -> Vec<StackFrame> {
+        let mut line = self.line;
+        let mut frames = Vec::with_capacity(self.calls.len() + 1);
+        for (name, call_line) in self.calls.iter().rev() {
+            frames.push(StackFrame {
+                name: name.clone(),
+                line,
+            });
+            line = *call_line;
+        }
+        frames.push(StackFrame {
+            name: "<script>".to_string(),
+            line,
+        });
+        frames
+    }
+
+    ///
+    /// 当前环境链中的变量，按作用域从内到外排列，最后一个是全局作用域
+    ///
+    /// 每个作用域内按变量名排序，原生函数不列出。
+    ///
+    pub fn scopes(&self) -> Vec<Vec<(String, Object)>> {
+        let mut scopes = Vec::new();
+        let mut environment = Some(Rc::clone(self.environment));
+        while let Some(current) = environment {
+            let scope = current.borrow();
+            let mut variables: Vec<(String, Object)> = scope
+                .entries()
+                .filter(|(_, value)| !matches!(value, Object::Callable(Function::Native { .. })))
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect();
+            variables.sort_by(|a, b| a.0.cmp(&b.0));
+            scopes.push(variables);
+            environment = scope.enclosing().cloned();
+        }
+        scopes
+    }
+
+    ///
+    /// 在当前环境链中查找变量
+    ///
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        self.environment.borrow().lookup(name)
+    }
+}
+
+///
+/// 断点，按行号或 step 名设置
+///
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    lines: BTreeSet<i32>,
+    steps: BTreeSet<String>,
+}
+
+impl Breakpoints {
+    ///
+    /// 在某一行设置断点
+    ///
+    pub fn add_line(&mut self, line: i32) {
+        self.lines.insert(line);
+    }
+
+    ///
+    /// 在进入某个 step 时暂停
+    ///
+    pub fn add_step(&mut self, name: &str) {
+        self.steps.insert(name.to_string());
+    }
+
+    ///
+    /// 删除某一行的断点
+    ///
+    /// # 返回值
+    /// * 该行原来是否有断点
+    ///
+    pub fn remove_line(&mut self, line: i32) -> bool {
+        self.lines.remove(&line)
+    }
+
+    ///
+    /// 删除某个 step 的断点
+    ///
+    /// # 返回值
+    /// * 该 step 原来是否有断点
+    ///
+    pub fn remove_step(&mut self, name: &str) -> bool {
+        self.steps.remove(name)
+    }
+
+    ///
+    /// 删除全部行号断点
+    ///
+    pub fn clear_lines(&mut self) {
+        self.lines.clear();
+    }
+
+    ///
+    /// 删除全部 step 断点
+    ///
+    pub fn clear_steps(&mut self) {
+        self.steps.clear();
+    }
+
+    ///
+    /// 设置了断点的行，从小到大排列
+    ///
+    pub fn lines(&self) -> impl Iterator<Item = i32> + '_ {
+        self.lines.iter().copied()
+    }
+
+    ///
+    /// 设置了断点的 step，按名字排序
+    ///
+    pub fn steps(&self) -> impl Iterator<Item = &str> {
+        self.steps.iter().map(String::as_str)
+    }
+}
+
+///
+/// 暂停的原因
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason {
+    /// 在第一条语句前暂停
+    Entry,
+    /// 到达行号断点
+    Breakpoint,
+    /// 进入设置了断点的 step
+    Step(String),
+    /// 单步执行结束
+    Stepped,
+    /// 前端要求暂停
+    Pause,
+    /// 即将读取用户输入，由前端决定
+    Input,
+}
+
+///
+/// 暂停后继续执行的方式
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    /// 运行到下一个断点
+    Continue,
+    /// 执行到下一行，遇到 step 调用时进入
+    StepIn,
+    /// 执行到当前 step 中的下一行，不进入调用
+    StepOver,
+    /// 执行到返回调用者为止
+    StepOut,
+}
+
+///
+/// 调试器前端，负责与用户交互
+///
+/// 命令行、调试适配器等不同的前端只需实现这个特质，
+/// 何时暂停由 `Debugger` 决定。
+///
+pub trait Frontend {
+    ///
+    /// 解释器暂停，前端在返回之前可以查看变量、修改断点
+    ///
+    /// # 参数列表
+    /// * reason: 暂停的原因
+    /// * context: 暂停位置的解释器状态
+    /// * breakpoints: 断点，前端可以修改
+    ///
+    /// # 返回值
+    /// * 继续执行的方式
+    /// * 错误，例如用户结束调试时返回 `Error::Exit`
+    ///
+    fn stopped(
+        &mut self,
+        reason: Reason,
+        context: &Context<'_>,
+        breakpoints: &mut Breakpoints,
+    ) -> Result<Resume, Error>;
+
+    ///
+    /// 运行期间在每条语句前调用，前端可以借此处理异步到达的请求
+    ///
+    /// # 参数列表
+    /// * context: 即将执行的语句
+    /// * breakpoints: 断点，前端可以修改
+    ///
+    /// # 返回值
+    /// * 要求暂停时返回暂停的原因
+    ///
+    fn poll(
+        &mut self,
+        _context: &Context<'_>,
+        _breakpoints: &mut Breakpoints,
+    ) -> Result<Option<Reason>, Error> {
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Run,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+///
+/// 断点与单步执行的调试器，作为钩子装入解释器
+///
+/// 同一行上的多条语句只暂停一次。
+///
+pub struct Debugger<F> {
+    frontend: F,
+    breakpoints: Breakpoints,
+    mode: Mode,
+    /// 上一条语句的行号与调用深度
+    last: Option<(i32, usize)>,
+}
+
+impl<F: Frontend> Debugger<F> {
+    ///
+    /// 创建调试器，在第一条语句前暂停
+    ///
+    pub fn new(frontend: F) -> Self {
+        Debugger {
+            frontend,
+            breakpoints: Breakpoints::default(),
+            mode: Mode::StepIn,
+            last: None,
+        }
+    }
+
+    ///
+    /// 设置初始断点
+    ///
+    pub fn breakpoints(mut self, breakpoints: Breakpoints) -> Self {
+        self.breakpoints = breakpoints;
+        self
+    }
+
+    ///
+    /// 是否在第一条语句前暂停，默认暂停
+    ///
+    pub fn stop_on_entry(mut self, stop: bool) -> Self {
+        self.mode = if stop { Mode::StepIn } else { Mode::Run };
+        self
+    }
+
+    fn reason(&mut self, context: &Context<'_>) -> Result<Option<Reason>, Error> {
+        let depth = context.depth();
+        let last = self.last.replace((context.line, depth));
+        if let (true, Some((name, _))) = (context.entered(), context.calls.last()) {
+            if self.breakpoints.steps.contains(name) {
+                return Ok(Some(Reason::Step(name.clone())));
+            }
+        }
+        if last != Some((context.line, depth)) && self.breakpoints.lines.contains(&context.line) {
+            return Ok(Some(Reason::Breakpoint));
+        }
+        if let (Mode::StepOver(from), true) = (self.mode, context.entered()) {
+            // 尾调用替换了正在越过的调用，越过它就是等到被调用的 step 返回
+            if depth == from {
+                self.mode = Mode::StepOut(from);
+            }
+        }
+        let reason = match self.mode {
+            _ if last == Some((context.line, depth)) && !context.entered() => None,
+            Mode::StepIn if last.is_none() => Some(Reason::Entry),
+            Mode::StepIn => Some(Reason::Stepped),
+            Mode::StepOver(from) if depth <= from => Some(Reason::Stepped),
+            Mode::StepOut(from) if depth < from => Some(Reason::Stepped),
+            _ => None,
+        };
+        match reason {
+            Some(reason) => Ok(Some(reason)),
+            None => self.frontend.poll(context, &mut self.breakpoints),
+        }
+    }
+}
+
+impl<F: Frontend> Hook for Debugger<F> {
+    fn statement(&mut self, context: &Context<'_>) -> Result<(), Error> {
+        let reason = match self.reason(context)? {
+            Some(reason) => reason,
+            None => return Ok(()),
+        };
+        let depth = context.depth();
+        self.mode = match self
+            .frontend
+            .stopped(reason, context, &mut self.breakpoints)?
+        {
+            Resume::Continue => Mode::Run,
+            Resume::StepIn => Mode::StepIn,
+            Resume::StepOver => Mode::StepOver(depth),
+            Resume::StepOut => Mode::StepOut(depth),
+        };
+        Ok(())
+    }
+}
+
+const HELP: &str = "\
+break <line|step>   set a breakpoint, without argument list them (b)
+delete <line|step>  remove a breakpoint (d)
+continue            run to the next breakpoint (c)
+step                step into calls (s)
+next                step over calls (n)
+finish              run until the current step returns (f)
+print <name>        show a variable (p)
+locals              show all visible variables
+backtrace           show the call stack (bt)
+list                show the source around the current line (l)
+quit                stop the script (q)";
+
+///
+/// 命令行调试前端，通过对话通道读取命令并显示结果
+///
+/// 命令行运行时与脚本共用标准输入输出，
+/// 每次暂停时读入命令，直到遇到继续执行的命令。
+///
+/// # 使用示例
+/// let frontend = CommandLine::new(Console).source(&source);
+/// let interpreter = Interpreter::builder().debug_hook(Debugger::new(frontend)).build();
+///
+pub struct CommandLine<C> {
+    channel: C,
+    /// 脚本源代码的各行，用于显示当前行
+    source: Vec<String>,
+}
+
+impl<C: Channel> CommandLine<C> {
+    ///
+    /// 创建命令行前端
+    ///
+    /// # 参数列表
+    /// * channel: 读取调试命令、显示结果的通道
+    ///
+    pub fn new(channel: C) -> Self {
+        CommandLine {
+            channel,
+            source: Vec::new(),
+        }
+    }
+
+    ///
+    /// 提供脚本源代码，暂停时显示当前行
+    ///
+    pub fn source(mut self, source: &str) -> Self {
+        self.source = source.lines().map(str::to_string).collect();
+        self
+    }
+
+    fn source_line(&self, line: i32) -> Option<String> {
+        usize::try_from(line - 1)
+            .ok()
+            .and_then(|index| self.source.get(index))
+            .map(|text| format!("{:>4}  {}", line, text.trim_end()))
+    }
+
+    fn show_location(&mut self, reason: &Reason, context: &Context<'_>) -> Result<(), Error> {
+        let why = match reason {
+            Reason::Entry => "Paused at entry".to_string(),
+            Reason::Breakpoint => "Breakpoint".to_string(),
+            Reason::Step(name) => format!("Entered step {}", name),
+            Reason::Stepped | Reason::Pause => "Paused".to_string(),
+            Reason::Input => "Waiting for input".to_string(),
+        };
+        let place = match (reason, context.calls.last()) {
+            (Reason::Step(_), _) | (_, None) => String::new(),
+            (_, Some((name, _))) => format!(" in {}", name),
+        };
+        self.channel
+            .speak(&format!("{}{} at line {}", why, place, context.line))?;
+        if let Some(text) = self.source_line(context.line) {
+            self.channel.speak(&text)?;
+        }
+        Ok(())
+    }
+
+    fn command(
+        &mut self,
+        command: &str,
+        argument: &str,
+        context: &Context<'_>,
+        breakpoints: &mut Breakpoints,
+    ) -> Result<Option<Resume>, Error> {
+        let mut lines = Vec::new();
+        match (command, argument) {
+            ("c" | "continue", _) => return Ok(Some(Resume::Continue)),
+            ("s" | "step", _) => return Ok(Some(Resume::StepIn)),
+            ("n" | "next", _) => return Ok(Some(Resume::StepOver)),
+            ("f" | "finish", _) => return Ok(Some(Resume::StepOut)),
+            ("q" | "quit", _) => return Err(Error::Exit),
+            ("b" | "break", "") => {
+                lines.extend(breakpoints.lines().map(|line| format!("line {}", line)));
+                lines.extend(breakpoints.steps().map(|step| format!("step {}", step)));
+            }
+            ("b" | "break", target) => match target.parse() {
+                Ok(line) => {
+                    breakpoints.add_line(line);
+                    lines.push(format!("Breakpoint at line {}", line));
+                }
+                Err(_) => {
+                    breakpoints.add_step(target);
+                    lines.push(format!("Breakpoint at step {}", target));
+                }
+            },
+            ("d" | "delete", target) => {
+                let removed = match target.parse() {
+                    Ok(line) => breakpoints.remove_line(line),
+                    Err(_) => breakpoints.remove_step(target),
+                };
+                if !removed {
+                    lines.push(format!("No breakpoint at {}", target));
+                }
+            }
+            ("p" | "print", "") => lines.push("Usage: print <name>".to_string()),
+            ("p" | "print", name) => lines.push(match context.lookup(name) {
+                Some(value) => format!("{} = {}", name, describe(&value)),
+                None => format!("Undefined variable '{}'.", name),
+            }),
+            ("locals", _) => {
+                let scopes = context.scopes();
+                for (depth, scope) in scopes.iter().enumerate() {
+                    let title = match depth {
+                        _ if depth + 1 == scopes.len() => "globals:",
+                        0 => "locals:",
+                        _ => "enclosing:",
+                    };
+                    lines.push(title.to_string());
+                    lines.extend(
+                        scope
+                            .iter()
+                            .map(|(name, value)| format!("  {} = {}", name, describe(value))),
+                    );
+                }
+            }
+            ("bt" | "backtrace", _) => {
+                for (number, frame) in context.stack().iter().enumerate() {
+                    lines.push(format!("#{} {} line {}", number, frame.name, frame.line));
+                }
+            }
+            ("l" | "list", _) => {
+                for line in (context.line - 3).max(1)..=context.line + 3 {
+                    if let Some(text) = self.source_line(line) {
+                        let marker = if line == context.line { ">" } else { " " };
+                        lines.push(format!("{}{}", marker, text));
+                    }
+                }
+            }
+            ("h" | "help", _) => lines.push(HELP.to_string()),
+            _ => lines.push(format!(
+                "Unknown command '{}'. Type help for help.",
+                command
+            )),
+        }
+        for line in lines {
+            self.channel.speak(&line)?;
+        }
+        Ok(None)
+    }
+}
+
+impl<C: Channel> Frontend for CommandLine<C> {
+    fn stopped(
+        &mut self,
+        reason: Reason,
+        context: &Context<'_>,
+        breakpoints: &mut Breakpoints,
+    ) -> Result<Resume, Error> {
+        self.show_location(&reason, context)?;
+        loop {
+            let line = match self.channel.listen()? {
+                Some(line) => line,
+                None => {
+                    // 命令输入结束后不再暂停
+                    *breakpoints = Breakpoints::default();
+                    return Ok(Resume::Continue);
+                }
+            };
+            let line = line.trim();
+            let (command, argument) = match line.split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (line, ""),
+            };
+            if command.is_empty() {
+                continue;
+            }
+            if let Some(resume) = self.command(command, argument, context, breakpoints)? {
+                return Ok(resume);
+            }
+        }
+    }
+}
+
+///
+/// 调试器显示变量值的格式，字符串带引号
+///
+pub fn describe(value: &Object) -> String {
+    match value {
+        Object::String(s) => format!("{:?}", s),
+        other => other.to_string(),
+    }
+}
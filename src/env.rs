@@ -1,5 +1,6 @@
 use crate::error::Error;
 use crate::object::Object;
+use crate::symbol::Symbol;
 use crate::token::Token;
 
 use std::cell::RefCell;
@@ -12,7 +13,8 @@ use std::rc::Rc;
 ///
 pub struct Environment {
     enclosing: Option<Rc<RefCell<Environment>>>, // Parent
-    values: HashMap<String, Object>,
+    values: HashMap<Symbol, Object>,
+    constants: HashMap<Symbol, i32>, // Declaration line, 0 for host constants
 }
 
 impl Environment {
@@ -23,6 +25,7 @@ impl Environment {
         Environment {
             enclosing: None,
             values: HashMap::new(),
+            constants: HashMap::new(),
         }
     }
 
@@ -39,6 +42,7 @@ impl Environment {
         Environment {
             enclosing: Some(Rc::clone(enclosing)),
             values: HashMap::new(),
+            constants: HashMap::new(),
         }
     }
 
@@ -52,10 +56,51 @@ impl Environment {
     /// # 返回值
     /// * 无
     ///
-    pub fn define(&mut self, name: String, value: Object) {
+    pub fn define(&mut self, name: impl Into<Symbol>, value: Object) {
+        self.values.insert(name.into(), value);
+    }
+
+    ///
+    /// 在当前环境中定义一个常量，之后对它的赋值会报错
+    ///
+    /// # 参数列表
+    /// * name: 常量名
+    /// * value: 常量值
+    /// * line: 声明所在行，宿主程序定义的常量为 0
+    ///
+    pub fn define_constant(&mut self, name: impl Into<Symbol>, value: Object, line: i32) {
+        let name = name.into();
+        self.constants.insert(name, line);
         self.values.insert(name, value);
     }
 
+    ///
+    /// 查询当前环境（不含父环境）中的常量
+    ///
+    /// # 参数列表
+    /// * name: 变量名
+    ///
+    /// # 返回值
+    /// * 若为常量，返回其声明所在行
+    ///
+    pub fn constant_line(&self, name: impl Into<Symbol>) -> Option<i32> {
+        self.constants.get(&name.into()).copied()
+    }
+
+    ///
+    /// 得到父环境，全局环境没有父环境
+    ///
+    pub fn enclosing(&self) -> Option<&Rc<RefCell<Environment>>> {
+        self.enclosing.as_ref()
+    }
+
+    ///
+    /// 遍历当前环境（不含父环境）中定义的变量
+    ///
+    pub fn entries(&self) -> impl Iterator<Item = (Symbol, &Object)> {
+        self.values.iter().map(|(name, value)| (*name, value))
+    }
+
     ///
     /// 在当前环境中得到该变量的相关信息
     ///
@@ -66,8 +111,7 @@ impl Environment {
     /// * 该变量的相关信息
     ///
     pub fn get(&self, name: &Token) -> Result<Object, Error> {
-        let key = &*name.lexeme;
-        if let Some(value) = self.values.get(key) {
+        if let Some(value) = self.values.get(&name.symbol) {
             Ok((*value).clone())
         } else {
             if let Some(ref enclosing) = self.enclosing {
@@ -75,12 +119,34 @@ impl Environment {
             } else {
                 Err(Error::Runtime {
                     token: name.clone(),
-                    message: format!("Undefined variable '{}'.", key),
+                    message: format!("Undefined variable '{}'.", name.lexeme),
                 })
             }
         }
     }
 
+    ///
+    /// 按名字查找变量，找不到时返回 None
+    ///
+    /// 查找不会驻留名字，调试器和宿主程序可以放心地查找任意输入。
+    ///
+    /// # 参数列表
+    /// * name: 变量名
+    ///
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        self.find(Symbol::get(name)?)
+    }
+
+    fn find(&self, name: Symbol) -> Option<Object> {
+        match self.values.get(&name) {
+            Some(value) => Some(value.clone()),
+            None => self
+                .enclosing
+                .as_ref()
+                .and_then(|enclosing| enclosing.borrow().find(name)),
+        }
+    }
+
     ///
     /// 改变变量的值
     ///
@@ -93,8 +159,13 @@ impl Environment {
     ///
     pub fn assign(&mut self, name: &Token, value: Object) -> Result<(), Error> {
         let key = &*name.lexeme;
-        if self.values.contains_key(key) {
-            self.values.insert(name.lexeme.clone(), value);
+        if let Some(line) = self.constant_line(name.symbol) {
+            Err(Error::Runtime {
+                token: name.clone(),
+                message: constant_message(key, line),
+            })
+        } else if let Some(slot) = self.values.get_mut(&name.symbol) {
+            *slot = value;
             Ok(())
         } else {
             if let Some(ref enclosing) = self.enclosing {
@@ -109,6 +180,26 @@ impl Environment {
     }
 }
 
+///
+/// 生成对常量重新赋值时的报错信息，指出常量的声明位置
+///
+pub fn constant_message(name: &str, line: i32) -> String {
+    if line > 0 {
+        format!(
+            "Cannot assign to constant '{}' declared on line {}.",
+            name, line
+        )
+    } else {
+        format!("Cannot assign to constant '{}' defined by the host.", name)
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl fmt::Display for Environment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "values: {:?}", self.values)
@@ -132,15 +223,15 @@ mod test {
 
         assert!(env
             .borrow()
-            .get(&Token::new(TokenType::Identifier, &"a".to_string(), 1))
+            .get(&Token::new(TokenType::Identifier, "a", 1))
             .is_ok());
         assert!(env
             .borrow()
-            .get(&Token::new(TokenType::Identifier, &"b".to_string(), 1))
+            .get(&Token::new(TokenType::Identifier, "b", 1))
             .is_ok());
         assert!(env
             .borrow()
-            .get(&Token::new(TokenType::Identifier, &"c".to_string(), 1))
+            .get(&Token::new(TokenType::Identifier, "c", 1))
             .is_ok());
     }
 
@@ -156,7 +247,7 @@ mod test {
 
         assert!(env
             .borrow()
-            .get(&Token::new(TokenType::Identifier, &"d".to_string(), 1))
+            .get(&Token::new(TokenType::Identifier, "d", 1))
             .is_err());
     }
 
@@ -173,26 +264,50 @@ mod test {
         assert!(env
             .borrow_mut()
             .assign(
-                &Token::new(TokenType::Identifier, &"a".to_string(), 1),
+                &Token::new(TokenType::Identifier, "a", 1),
                 Object::Number(4.0)
             )
             .is_ok());
         assert!(env
             .borrow_mut()
             .assign(
-                &Token::new(TokenType::Identifier, &"b".to_string(), 1),
+                &Token::new(TokenType::Identifier, "b", 1),
                 Object::Number(5.0)
             )
             .is_ok());
         assert!(env
             .borrow_mut()
             .assign(
-                &Token::new(TokenType::Identifier, &"c".to_string(), 1),
+                &Token::new(TokenType::Identifier, "c", 1),
                 Object::Number(6.0)
             )
             .is_ok());
     }
 
+    #[test]
+    fn test_assign_constant() {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        env.borrow_mut()
+            .define_constant("a".to_string(), Object::Number(1.0), 3);
+        let inner = Rc::new(RefCell::new(Environment::from(&env)));
+
+        match inner.borrow_mut().assign(
+            &Token::new(TokenType::Identifier, "a", 7),
+            Object::Number(2.0),
+        ) {
+            Err(Error::Runtime { token, message }) => {
+                assert_eq!(token.line, 7);
+                assert_eq!(message, "Cannot assign to constant 'a' declared on line 3.");
+            }
+            _ => panic!("expected a runtime error"),
+        }
+        assert!(env
+            .borrow()
+            .get(&Token::new(TokenType::Identifier, "a", 1))
+            .unwrap()
+            .equals(&Object::Number(1.0)));
+    }
+
     #[test]
     fn test_assign_undefined() {
         let env = Rc::new(RefCell::new(Environment::new()));
@@ -206,9 +321,27 @@ mod test {
         assert!(env
             .borrow_mut()
             .assign(
-                &Token::new(TokenType::Identifier, &"d".to_string(), 1),
+                &Token::new(TokenType::Identifier, "d", 1),
                 Object::Number(4.0)
             )
             .is_err());
     }
+
+    #[test]
+    fn test_symbol_keys() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer
+            .borrow_mut()
+            .define(Symbol::intern("balance"), Object::Number(5.0));
+        let inner = Environment::from(&outer);
+
+        let token = Token::new(TokenType::Identifier, "balance", 1);
+        assert_eq!(token.symbol, Symbol::intern("balance"));
+        assert!(inner.get(&token).unwrap().equals(&Object::Number(5.0)));
+        assert!(inner.lookup("balance").is_some());
+        assert_eq!(
+            outer.borrow().entries().next().unwrap().0.as_str(),
+            "balance"
+        );
+    }
 }
@@ -1,7 +1,10 @@
 use std::convert;
 use std::fmt;
 use std::io;
+use std::rc::Rc;
 
+use crate::limits::LimitExceeded;
+use crate::state::State;
 use crate::token::{Token, TokenType};
 
 ///
@@ -51,6 +54,18 @@ pub enum Error {
     Parse,
     /// 运行时错误
     Runtime { token: Token, message: String },
+    /// 原生函数错误，在调用处补上出错位置后转为运行时错误
+    Native(String),
+    /// goto 语句发出的状态转移，不是真正的错误，由状态机驱动器捕获
+    Transition(Rc<State>),
+    /// exit 语句发出的退出信号，由会话或命令行捕获后结束运行
+    Exit,
+    /// 通道中暂时没有输入，解释器暂停并记录执行位置，等待宿主恢复
+    Suspend,
+    /// 超出了宿主设置的资源限制，执行被中止
+    Limit(LimitExceeded),
+    /// 宿主通过取消令牌中止了执行
+    Cancelled,
 }
 
 impl fmt::Display for Error {
@@ -59,6 +74,12 @@ impl fmt::Display for Error {
             Error::Io(underlying) => write!(f, "IoError {}", underlying),
             Error::Parse => write!(f, "ParseError"),
             Error::Runtime { message, .. } => write!(f, "RuntimeError {}", message),
+            Error::Native(message) => write!(f, "RuntimeError {}", message),
+            Error::Transition(state) => write!(f, "Transition to {}", state),
+            Error::Exit => write!(f, "Exit"),
+            Error::Suspend => write!(f, "Suspended waiting for input"),
+            Error::Limit(exceeded) => write!(f, "LimitError {}", exceeded),
+            Error::Cancelled => write!(f, "Cancelled"),
         }
     }
 }
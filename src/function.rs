@@ -8,6 +8,15 @@ use crate::token::Token;
 use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
+use std::sync::Arc;
+
+///
+/// 原生函数的函数体类型
+///
+/// 原生函数可以是捕获了状态的闭包，调用时拿到解释器本身，
+/// 因此可以通过 `Interpreter::call` 回调 dsl 中的 step。
+///
+pub type NativeFn = dyn Fn(&mut Interpreter, &[Object]) -> Result<Object, Error>;
 
 #[derive(Clone)]
 ///
@@ -17,9 +26,11 @@ pub enum Function {
     /// 原生函数
     Native {
         /// 函数名
+        name: String,
+        /// 参数个数
         arity: usize,
-        /// 函数体
-        body: Box<fn(&Vec<Object>) -> Object>,
+        /// 函数体，出错时返回 Error::Native
+        body: Rc<NativeFn>,
     },
 
     /// 用户调用函数
@@ -27,15 +38,41 @@ pub enum Function {
         /// 函数名
         name: Token,
         /// 函数参数
-        params: Vec<Token>,
-        /// 函数体
-        body: Vec<Stmt>,
+        params: Arc<[Token]>,
+        /// 函数体，与声明语句共享，复制函数不会复制语法树
+        body: Arc<[Stmt]>,
         /// 函数环境
         closure: Rc<RefCell<Environment>>,
     },
 }
 
 impl Function {
+    ///
+    /// 创建一个原生函数
+    ///
+    /// # 参数列表
+    /// * name: 函数名
+    /// * arity: 参数个数
+    /// * body: 函数体，可以捕获状态
+    ///
+    /// # 使用示例
+    /// let counter = Rc::new(Cell::new(0.0));
+    /// let next = Function::native("next", 0, move |_, _| {
+    ///     counter.set(counter.get() + 1.0);
+    ///     Ok(Object::Number(counter.get()))
+    /// });
+    ///
+    pub fn native<F>(name: &str, arity: usize, body: F) -> Self
+    where
+        F: Fn(&mut Interpreter, &[Object]) -> Result<Object, Error> + 'static,
+    {
+        Function::Native {
+            name: name.to_string(),
+            arity,
+            body: Rc::new(body),
+        }
+    }
+
     ///
     /// 调用语句
     ///
@@ -50,22 +87,12 @@ impl Function {
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
-        arguments: &Vec<Object>,
+        arguments: &[Object],
     ) -> Result<Object, Error> {
         match self {
-            Function::Native { body, .. } => Ok(body(arguments)),
-            Function::User {
-                params,
-                body,
-                closure,
-                ..
-            } => {
-                let environment = Rc::new(RefCell::new(Environment::from(closure)));
-                for (param, argument) in params.iter().zip(arguments.iter()) {
-                    environment
-                        .borrow_mut()
-                        .define(param.lexeme.clone(), argument.clone());
-                }
+            Function::Native { body, .. } => body(interpreter, arguments),
+            Function::User { body, closure, .. } => {
+                let environment = self.bind(closure, arguments);
                 match interpreter.execute_block(body, environment) {
                     Err(other) => Err(other),
                     Ok(..) => Ok(Object::Null), // We don't have a return statement.
@@ -74,6 +101,39 @@ impl Function {
         }
     }
 
+    ///
+    /// 为用户函数的一次调用创建环境并绑定参数
+    ///
+    /// # 参数列表
+    /// * closure: 函数声明时的环境
+    /// * arguments: 参数列表
+    ///
+    pub fn bind(
+        &self,
+        closure: &Rc<RefCell<Environment>>,
+        arguments: &[Object],
+    ) -> Rc<RefCell<Environment>> {
+        let environment = Rc::new(RefCell::new(Environment::from(closure)));
+        if let Function::User { params, .. } = self {
+            for (param, argument) in params.iter().zip(arguments.iter()) {
+                environment
+                    .borrow_mut()
+                    .define(param.symbol, argument.clone());
+            }
+        }
+        environment
+    }
+
+    ///
+    /// 函数名
+    ///
+    pub fn name(&self) -> &str {
+        match self {
+            Function::Native { name, .. } => name,
+            Function::User { name, .. } => &name.lexeme,
+        }
+    }
+
     /// 元数检查
     /// # 返回值
     /// * 元数
@@ -88,7 +148,7 @@ impl Function {
 impl fmt::Debug for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Function::Native { .. } => write!(f, "<native func>"),
+            Function::Native { name, .. } => write!(f, "<native func {}>", name),
             Function::User { name, .. } => write!(f, "<fn {}>", name.lexeme),
         }
     }
@@ -97,7 +157,7 @@ impl fmt::Debug for Function {
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Function::Native { .. } => write!(f, "<native func>"),
+            Function::Native { name, .. } => write!(f, "<native func {}>", name),
             Function::User { name, .. } => write!(f, "<fn {}>", name.lexeme),
         }
     }
@@ -0,0 +1,389 @@
+use crate::cancel::CancelToken;
+use crate::channel::Channel;
+use crate::debug::Hook;
+use crate::error::Error;
+use crate::function::Function;
+use crate::interpreter::Interpreter;
+use crate::limits::Limits;
+use crate::object::Object;
+use crate::storage::Storage;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+///
+/// 从 dsl 对象转换为 Rust 类型
+///
+/// 转换失败时返回的错误信息只描述期望的类型，
+/// 由调用方补上函数名与参数位置。
+///
+pub trait FromObject: Sized {
+    ///
+    /// 转换对象
+    ///
+    /// # 参数列表
+    /// * object: dsl 对象
+    ///
+    /// # 返回值
+    /// * 转换结果
+    /// * 期望的类型名
+    ///
+    fn from_object(object: &Object) -> Result<Self, &'static str>;
+}
+
+///
+/// 从 Rust 类型转换为 dsl 对象
+///
+pub trait IntoObject {
+    ///
+    /// 转换为 dsl 对象
+    ///
+    fn into_object(self) -> Object;
+}
+
+///
+/// 宿主函数的返回值，既可以是普通值，也可以是 `Result`
+///
+pub trait IntoHostResult {
+    ///
+    /// 转换为原生函数的返回值
+    ///
+    fn into_host_result(self) -> Result<Object, Error>;
+}
+
+impl<T: IntoObject> IntoHostResult for T {
+    fn into_host_result(self) -> Result<Object, Error> {
+        Ok(self.into_object())
+    }
+}
+
+impl<T: IntoObject> IntoHostResult for Result<T, String> {
+    fn into_host_result(self) -> Result<Object, Error> {
+        self.map(IntoObject::into_object).map_err(Error::Native)
+    }
+}
+
+///
+/// 可以注册到解释器中的宿主函数
+///
+/// 为参数个数 0 到 4 的闭包实现，参数类型需实现 `FromObject`，
+/// 返回值类型需实现 `IntoHostResult`。
+///
+pub trait HostFunction<Args>: 'static {
+    /// 参数个数
+    fn arity(&self) -> usize;
+    /// 转换参数并调用
+    fn invoke(&self, name: &str, args: &[Object]) -> Result<Object, Error>;
+}
+
+fn argument<T: FromObject>(name: &str, args: &[Object], index: usize) -> Result<T, Error> {
+    T::from_object(&args[index]).map_err(|expected| {
+        Error::Native(format!(
+            "{}: argument {} expected {} but got {}.",
+            name,
+            index + 1,
+            expected,
+            args[index].type_name()
+        ))
+    })
+}
+
+macro_rules! host_function {
+    ( $arity:expr; $( $arg:ident $index:tt ),* ) => {
+        impl<F, R, $( $arg ),*> HostFunction<( $( $arg, )* )> for F
+        where
+            F: Fn( $( $arg ),* ) -> R + 'static,
+            R: IntoHostResult,
+            $( $arg: FromObject, )*
+        {
+            fn arity(&self) -> usize {
+                $arity
+            }
+
+            #[allow(unused_variables)]
+            fn invoke(&self, name: &str, args: &[Object]) -> Result<Object, Error> {
+                (self)( $( argument::<$arg>(name, args, $index)? ),* ).into_host_result()
+            }
+        }
+    };
+}
+
+host_function!(0;);
+host_function!(1; A 0);
+host_function!(2; A 0, B 1);
+host_function!(3; A 0, B 1, C 2);
+host_function!(4; A 0, B 1, C 2, D 3);
+
+///
+/// 解释器的构造器，用于宿主程序注册函数和全局常量
+///
+/// # 使用示例
+/// let mut interpreter = Interpreter::builder()
+///     .function("lookup_account", |id: String| format!("account {}", id))
+///     .constant("company", "BUPT Telecom")
+///     .build();
+///
+pub struct InterpreterBuilder {
+    interpreter: Interpreter,
+}
+
+impl InterpreterBuilder {
+    ///
+    /// 创建一个构造器，内含注册了标准库的解释器
+    ///
+    pub fn new() -> Self {
+        InterpreterBuilder {
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    ///
+    /// 注册一个宿主函数
+    ///
+    /// # 参数列表
+    /// * name: dsl 中的函数名
+    /// * function: Rust 闭包，参数与返回值自动转换
+    ///
+    pub fn function<Args, F: HostFunction<Args>>(self, name: &str, function: F) -> Self {
+        let function = Rc::new(function);
+        let arity = function.arity();
+        let owned = name.to_string();
+        let native = Function::native(name, arity, move |_, args| function.invoke(&owned, args));
+        self.define(name, Object::Callable(native))
+    }
+
+    ///
+    /// 注册一个全局常量，脚本中对它赋值会报错
+    ///
+    /// # 参数列表
+    /// * name: dsl 中的变量名
+    /// * value: 常量值
+    ///
+    pub fn constant<T: IntoObject>(self, name: &str, value: T) -> Self {
+        self.interpreter.globals.borrow_mut().define_constant(
+            name.to_string(),
+            value.into_object(),
+            0,
+        );
+        self
+    }
+
+    ///
+    /// 设置意图匹配的相似度阈值
+    ///
+    pub fn intent_threshold(mut self, threshold: f64) -> Self {
+        self.interpreter.set_intent_threshold(threshold);
+        self
+    }
+
+    ///
+    /// 设置 step 调用的最大深度
+    ///
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.interpreter.set_max_call_depth(depth);
+        self
+    }
+
+    ///
+    /// 设置资源限制
+    ///
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.interpreter.set_limits(limits);
+        self
+    }
+
+    ///
+    /// 设置取消令牌
+    ///
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.interpreter.set_cancel_token(token);
+        self
+    }
+
+    ///
+    /// 设置调试钩子
+    ///
+    pub fn debug_hook<H: Hook + 'static>(mut self, hook: H) -> Self {
+        self.interpreter.set_debug_hook(hook);
+        self
+    }
+
+    ///
+    /// 设置与用户对话的通道
+    ///
+    pub fn channel<C: Channel + 'static>(mut self, channel: C) -> Self {
+        self.interpreter.set_channel(channel);
+        self
+    }
+
+    ///
+    /// 设置 store 和 load 使用的存储及当前用户
+    ///
+    /// # 参数列表
+    /// * user: 用户标识，例如聊天平台的用户 id
+    /// * storage: 存储，如 `FileStorage` 或共享的 `MemoryStorage`
+    ///
+    pub fn storage<S: Storage + 'static>(mut self, user: &str, storage: S) -> Self {
+        self.interpreter.set_storage(user, storage);
+        self
+    }
+
+    ///
+    /// 完成构造
+    ///
+    pub fn build(self) -> Interpreter {
+        self.interpreter
+    }
+
+    fn define(self, name: &str, value: Object) -> Self {
+        self.interpreter
+            .globals
+            .borrow_mut()
+            .define(name.to_string(), value);
+        self
+    }
+}
+
+impl Default for InterpreterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromObject for Object {
+    fn from_object(object: &Object) -> Result<Self, &'static str> {
+        Ok(object.clone())
+    }
+}
+
+impl FromObject for f64 {
+    fn from_object(object: &Object) -> Result<Self, &'static str> {
+        match object {
+            Object::Number(n) => Ok(*n),
+            _ => Err("number"),
+        }
+    }
+}
+
+impl FromObject for i64 {
+    fn from_object(object: &Object) -> Result<Self, &'static str> {
+        match object {
+            Object::Number(n) if n.fract() == 0.0 => Ok(*n as i64),
+            _ => Err("integer"),
+        }
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(object: &Object) -> Result<Self, &'static str> {
+        match object {
+            Object::Boolean(b) => Ok(*b),
+            _ => Err("bool"),
+        }
+    }
+}
+
+impl FromObject for String {
+    fn from_object(object: &Object) -> Result<Self, &'static str> {
+        match object {
+            Object::String(s) => Ok(s.clone()),
+            _ => Err("string"),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(object: &Object) -> Result<Self, &'static str> {
+        match object {
+            Object::Null => Ok(None),
+            other => T::from_object(other).map(Some),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(object: &Object) -> Result<Self, &'static str> {
+        match object {
+            Object::List(items) => items.iter().map(T::from_object).collect(),
+            _ => Err("list"),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for BTreeMap<String, T> {
+    fn from_object(object: &Object) -> Result<Self, &'static str> {
+        match object {
+            Object::Map(entries) => entries
+                .iter()
+                .map(|(key, value)| T::from_object(value).map(|value| (key.clone(), value)))
+                .collect(),
+            _ => Err("map"),
+        }
+    }
+}
+
+impl IntoObject for Object {
+    fn into_object(self) -> Object {
+        self
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Object {
+        Object::Null
+    }
+}
+
+impl IntoObject for f64 {
+    fn into_object(self) -> Object {
+        Object::Number(self)
+    }
+}
+
+impl IntoObject for i64 {
+    fn into_object(self) -> Object {
+        Object::Number(self as f64)
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Object {
+        Object::Boolean(self)
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Object {
+        Object::String(self)
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Object {
+        Object::String(self.to_string())
+    }
+}
+
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Object {
+        self.map(IntoObject::into_object).unwrap_or(Object::Null)
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Object {
+        Object::List(Rc::new(
+            self.into_iter().map(IntoObject::into_object).collect(),
+        ))
+    }
+}
+
+impl<T: IntoObject> IntoObject for BTreeMap<String, T> {
+    fn into_object(self) -> Object {
+        Object::Map(Rc::new(
+            self.into_iter()
+                .map(|(key, value)| (key, value.into_object()))
+                .collect(),
+        ))
+    }
+}
@@ -0,0 +1,186 @@
+use std::io::{self, BufRead, Read, Write};
+
+/// 请求体的最大长度
+const MAX_BODY: usize = 64 * 1024;
+/// 请求行和每个请求头的最大长度
+const MAX_LINE: usize = 8 * 1024;
+/// 请求头的最多个数
+const MAX_HEADERS: usize = 64;
+
+///
+/// 一个 HTTP/1.1 请求，只解析服务模式需要的部分
+///
+pub struct Request {
+    /// 请求方法，如 GET、POST
+    pub method: String,
+    /// 请求路径，不含查询参数
+    pub path: String,
+    /// 查询参数，不含开头的 '?'
+    pub query: String,
+    /// 请求头，名字已转为小写
+    pub headers: Vec<(String, String)>,
+    /// 请求体
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    ///
+    /// 从连接中读取一个请求
+    ///
+    /// # 返回值
+    /// * 请求，连接在请求开始前关闭时为 None
+    /// * 请求格式错误，或者请求行、请求头、请求体过长时返回 InvalidData 错误
+    ///
+    pub fn read<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+        let line = match read_line(reader, MAX_LINE)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/1.") => {
+                (method.to_string(), target)
+            }
+            _ => return Err(invalid("malformed request line")),
+        };
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), query.to_string()),
+            None => (target.to_string(), String::new()),
+        };
+
+        let mut headers = Vec::new();
+        loop {
+            let line = match read_line(reader, MAX_LINE)? {
+                Some(line) => line,
+                None => return Err(invalid("connection closed inside the headers")),
+            };
+            if line.is_empty() {
+                break;
+            }
+            if headers.len() == MAX_HEADERS {
+                return Err(invalid("too many headers"));
+            }
+            match line.split_once(':') {
+                Some((name, value)) => {
+                    headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+                }
+                None => return Err(invalid("malformed header")),
+            }
+        }
+
+        let mut request = Request {
+            method,
+            path,
+            query,
+            headers,
+            body: Vec::new(),
+        };
+        let length = match request.header("content-length") {
+            Some(length) => length
+                .parse::<usize>()
+                .map_err(|_| invalid("malformed Content-Length"))?,
+            None => 0,
+        };
+        if length > MAX_BODY {
+            return Err(invalid("request body too large"));
+        }
+        request.body.resize(length, 0);
+        reader.read_exact(&mut request.body)?;
+        Ok(Some(request))
+    }
+
+    ///
+    /// 得到查询参数的值，不做百分号解码
+    ///
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    ///
+    /// 得到请求头的值，名字不区分大小写
+    ///
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+///
+/// 读取一行，去掉行尾的换行符
+///
+/// # 参数列表
+/// * reader: 输入
+/// * limit: 一行的最大字节数，不含换行符
+///
+/// # 返回值
+/// * 一行内容，输入已经结束时为 None
+/// * 超过最大长度或不是合法的 UTF-8 时返回 InvalidData 错误
+///
+pub fn read_line<R: BufRead>(reader: &mut R, limit: usize) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = reader
+        .by_ref()
+        .take(limit as u64 + 2)
+        .read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.ends_with(b"\n") {
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+    }
+    if line.len() > limit {
+        return Err(invalid("line too long"));
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| invalid("line is not valid UTF-8"))
+}
+
+///
+/// 写出一个 JSON 响应，之后关闭连接
+///
+/// # 参数列表
+/// * stream: 连接
+/// * status: 状态码
+/// * body: 响应体，204 时忽略
+///
+pub fn respond<W: Write>(stream: &mut W, status: u16, body: &serde_json::Value) -> io::Result<()> {
+    let body = match status {
+        204 => String::new(),
+        _ => body.to_string(),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
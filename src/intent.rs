@@ -0,0 +1,331 @@
+///
+/// 默认的相似度阈值，低于该值的意图不会被匹配
+///
+pub const DEFAULT_THRESHOLD: f64 = 0.75;
+
+///
+/// 给出“你是不是想说”建议所需的最低相似度
+///
+pub const SUGGEST_THRESHOLD: f64 = 0.5;
+
+///
+/// 规范化用户输入：全角转半角、转小写、标点替换为空格并合并连续空白
+///
+/// # 参数列表
+/// * text: 原始文本
+///
+/// # 返回值
+/// * 规范化后的文本
+///
+pub fn normalize(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut pending_space = false;
+    for c in text.chars() {
+        let c = to_half_width(c);
+        if c.is_whitespace() || is_punctuation(c) {
+            pending_space = !result.is_empty();
+            continue;
+        }
+        if pending_space {
+            result.push(' ');
+            pending_space = false;
+        }
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+fn to_half_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || "，。、；：？！“”‘’（）【】《》…—·".contains(c)
+}
+
+///
+/// 计算两个字符串按字符的编辑距离（Levenshtein 距离）
+///
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+///
+/// 由编辑距离得到的相似度，取值范围为 [0, 1]
+///
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(a, b) as f64 / longest as f64
+}
+
+///
+/// 计算规范化后的输入与一个意图关键词的匹配得分
+///
+/// - 关键词作为完整的词序列出现在输入中时得分为 1
+/// - 含中文等非 ASCII 字符的关键词，只要是输入的子串即得分为 1
+/// - 否则取整句相似度与逐词相似度平均值中的较大者
+///
+pub fn score(input: &str, pattern: &str) -> f64 {
+    if pattern.is_empty() {
+        return 0.0;
+    }
+    if input == pattern || (!pattern.is_ascii() && input.contains(pattern)) {
+        return 1.0;
+    }
+
+    let words: Vec<&str> = input.split(' ').collect();
+    let keywords: Vec<&str> = pattern.split(' ').collect();
+    if words
+        .windows(keywords.len())
+        .any(|window| window == keywords.as_slice())
+    {
+        return 1.0;
+    }
+
+    let per_word: f64 = keywords
+        .iter()
+        .map(|keyword| {
+            words
+                .iter()
+                .map(|word| similarity(word, keyword))
+                .fold(0.0, f64::max)
+        })
+        .sum::<f64>()
+        / keywords.len() as f64;
+    per_word.max(similarity(input, pattern))
+}
+
+///
+/// 意图匹配器，在多个意图中挑选与输入最相近的一个
+///
+pub struct IntentMatcher {
+    threshold: f64,
+}
+
+impl IntentMatcher {
+    ///
+    /// 创建匹配器
+    ///
+    /// # 参数列表
+    /// * threshold: 相似度阈值
+    ///
+    pub fn new(threshold: f64) -> Self {
+        IntentMatcher { threshold }
+    }
+
+    ///
+    /// 找到得分最高且不低于阈值的意图，得分相同时取靠前者
+    ///
+    /// # 参数列表
+    /// * input: 用户输入
+    /// * intents: 每个意图的同义词列表
+    ///
+    /// # 返回值
+    /// * 匹配到的意图下标
+    ///
+    pub fn best<'a, I>(&self, input: &str, intents: I) -> Option<usize>
+    where
+        I: IntoIterator<Item = &'a [String]>,
+    {
+        let input = normalize(input);
+        let mut best: Option<(usize, f64)> = None;
+        for (index, synonyms) in intents.into_iter().enumerate() {
+            let intent_score = synonyms
+                .iter()
+                .map(|synonym| score(&input, &normalize(synonym)))
+                .fold(0.0, f64::max);
+            let better = match best {
+                Some((_, best_score)) => intent_score > best_score,
+                None => true,
+            };
+            if intent_score >= self.threshold && better {
+                best = Some((index, intent_score));
+            }
+        }
+        best.map(|(index, _)| index)
+    }
+}
+
+///
+/// 在候选项中找到与输入编辑距离最近的一个
+///
+/// # 参数列表
+/// * input: 用户输入
+/// * options: 候选项
+///
+/// # 返回值
+/// * 相似度不低于 `SUGGEST_THRESHOLD` 的最相近候选项下标，相同时取靠前者
+///
+pub fn closest<'a, I>(input: &str, options: I) -> Option<usize>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let input = normalize(input);
+    let mut best: Option<(usize, f64)> = None;
+    for (index, option) in options.into_iter().enumerate() {
+        let option_similarity = similarity(&input, &normalize(option));
+        let better = match best {
+            Some((_, best_similarity)) => option_similarity > best_similarity,
+            None => true,
+        };
+        if option_similarity >= SUGGEST_THRESHOLD && better {
+            best = Some((index, option_similarity));
+        }
+    }
+    best.map(|(index, _)| index)
+}
+
+///
+/// 菜单中用户输入的识别结果
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum Choice {
+    /// 与某个选项完全一致（规范化后）
+    Exact(usize),
+    /// 没有完全一致的选项，但与某个选项足够相近，需要向用户确认
+    Suggest(usize),
+    /// 无法识别
+    Unknown,
+}
+
+///
+/// 识别用户在菜单中的选择
+///
+/// # 参数列表
+/// * input: 用户输入
+/// * options: 每个选项的同义词列表
+///
+/// # 返回值
+/// * 识别结果，其中的下标为选项下标
+///
+pub fn choose<'a, I>(input: &str, options: I) -> Choice
+where
+    I: IntoIterator<Item = &'a [String]>,
+{
+    let normalized = normalize(input);
+    let mut owners: Vec<usize> = Vec::new();
+    let mut synonyms: Vec<&str> = Vec::new();
+    for (index, option) in options.into_iter().enumerate() {
+        for synonym in option {
+            if normalize(synonym) == normalized {
+                return Choice::Exact(index);
+            }
+            owners.push(index);
+            synonyms.push(synonym);
+        }
+    }
+    match closest(&normalized, synonyms) {
+        Some(found) => Choice::Suggest(owners[found]),
+        None => Choice::Unknown,
+    }
+}
+
+///
+/// 判断用户对确认问题的回答是否为肯定
+///
+pub fn is_affirmative(answer: &str) -> bool {
+    matches!(
+        normalize(answer).as_str(),
+        "y" | "yes" | "yeah" | "yep" | "ok" | "sure" | "是" | "是的" | "对" | "好"
+    )
+}
+
+impl Default for IntentMatcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_THRESHOLD)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn intents(list: &[&[&str]]) -> Vec<Vec<String>> {
+        list.iter()
+            .map(|synonyms| synonyms.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    fn best(matcher: &IntentMatcher, input: &str, intents: &[Vec<String>]) -> Option<usize> {
+        matcher.best(input, intents.iter().map(|synonyms| synonyms.as_slice()))
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("  Check   BALANCE, please! "),
+            "check balance please"
+        );
+        assert_eq!(normalize("ＣＨＥＣＫ　ｂａｌａｎｃｅ？"), "check balance");
+        assert_eq!(normalize("查询余额！"), "查询余额");
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("balanse", "balance"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("余额", "余额"), 0);
+    }
+
+    #[test]
+    fn test_best_intent() {
+        let matcher = IntentMatcher::default();
+        let intents = intents(&[&["check balance", "balance"], &["recharge", "top up"]]);
+        assert_eq!(
+            best(&matcher, "I want to check my balance.", &intents),
+            Some(0)
+        );
+        assert_eq!(best(&matcher, "Please TOP UP my phone", &intents), Some(1));
+        assert_eq!(best(&matcher, "recharg", &intents), Some(1));
+        assert_eq!(best(&matcher, "goodbye", &intents), None);
+    }
+
+    #[test]
+    fn test_closest() {
+        let options = ["balance", "recharge", "exit"];
+        assert_eq!(closest("balanse", options), Some(0));
+        assert_eq!(closest("RECHARGE!", options), Some(1));
+        assert_eq!(closest("weather", options), None);
+    }
+
+    #[test]
+    fn test_choose() {
+        let options = intents(&[&["balance", "b"], &["recharge", "top up"]]);
+        let choose = |input| choose(input, options.iter().map(|option| option.as_slice()));
+        assert_eq!(choose("B"), Choice::Exact(0));
+        assert_eq!(choose("top up!"), Choice::Exact(1));
+        assert_eq!(choose("balanse"), Choice::Suggest(0));
+        assert_eq!(choose("top-op"), Choice::Suggest(1));
+        assert_eq!(choose("weather"), Choice::Unknown);
+        assert!(is_affirmative(" Yes "));
+        assert!(!is_affirmative("no"));
+    }
+
+    #[test]
+    fn test_threshold() {
+        let intents = intents(&[&["recharge"]]);
+        assert_eq!(best(&IntentMatcher::new(0.8), "rechage", &intents), Some(0));
+        assert_eq!(best(&IntentMatcher::new(0.95), "rechage", &intents), None);
+    }
+}
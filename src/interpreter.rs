@@ -1,16 +1,54 @@
-use crate::env::Environment;
+use crate::cancel::CancelToken;
+use crate::channel::{Channel, Console};
+use crate::debug::{Context, Hook};
+use crate::env::{constant_message, Environment};
 use crate::error::Error;
 use crate::function::Function;
+use crate::host::InterpreterBuilder;
+use crate::intent::{self, Choice, IntentMatcher};
+use crate::limits::{LimitExceeded, Limits, Meter};
+use crate::module::{Module, Modules};
 use crate::object::Object;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::state::State;
+use crate::stdlib;
+use crate::storage::{self, MemoryStorage, Storage};
+use crate::suspend::{Frame, MachinePhase, MenuPhase};
 use crate::syntax::{expr, stmt};
-use crate::syntax::{Expr, LiteralValue, Stmt};
+use crate::syntax::{Expr, IntentArm, LiteralValue, MenuOption, Stmt};
 use crate::token::{Token, TokenType};
 
+use regex::Regex;
+
 use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::thread::sleep;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 运行解释器的线程的栈大小
+///
+/// 解释器递归地执行语句，每层 step 调用都占用线程栈。
+/// 命令行、调试适配器和服务模式的会话都在这么大的栈上运行解释器，
+/// 宿主程序在自己的线程中使用默认调用深度时也应当如此。
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// 每层 step 调用最多占用的栈空间的估计值，包括函数体中嵌套的语句块
+const STACK_PER_CALL: usize = if cfg!(debug_assertions) {
+    64 * 1024
+} else {
+    16 * 1024
+};
+
+/// 默认的最大 step 调用深度，由 `STACK_SIZE` 推算，达到这个深度时栈不会溢出
+pub const DEFAULT_MAX_CALL_DEPTH: usize = STACK_SIZE / STACK_PER_CALL;
+
+/// 最多缓存的正则表达式个数，超出时淘汰最久未使用的
+pub const REGEX_CACHE_SIZE: usize = 64;
 
 ///
 /// 定义解释器的类型
@@ -19,6 +57,35 @@ pub struct Interpreter {
     /// 全局环境
     pub globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
+    modules: Modules,
+    intent_threshold: f64,
+    /// 最近使用的正则表达式，最近使用的在前
+    regexes: VecDeque<(String, Rc<Regex>)>,
+    channel: Box<dyn Channel>,
+    storage: Box<dyn Storage>,
+    /// 当前会话的用户标识，store 和 load 按它区分数据
+    user: String,
+    /// 正在运行的状态机层数，大于 0 时 goto 只发出状态转移
+    machine_depth: usize,
+    exited: bool,
+    /// 暂停时记录的执行位置，从内到外排列
+    suspension: Vec<Frame>,
+    /// 恢复执行时尚未回到的位置，末尾为最外层
+    resuming: Vec<Frame>,
+    /// 正在执行的 step 调用，记录 step 名与调用所在行
+    calls: Vec<(String, i32)>,
+    /// 刚进入 step 函数体、还没有执行其中的语句，包括不加深调用栈的尾调用，供调试钩子使用
+    entered: bool,
+    max_call_depth: usize,
+    /// 当前语句是否处在 step 函数体的尾部
+    tail: bool,
+    /// 尾部的 step 调用留下的待执行调用，由外层调用接着执行，不加深调用栈
+    tail_call: Option<(Function, Rc<RefCell<Environment>>)>,
+    /// 本次执行的资源用量
+    meter: Meter,
+    cancel: CancelToken,
+    /// 调试钩子，在每条语句之前调用
+    hook: Option<Box<dyn Hook>>,
 }
 
 impl Interpreter {
@@ -27,22 +94,211 @@ impl Interpreter {
     ///
     pub fn new() -> Self {
         let globals = Rc::new(RefCell::new(Environment::new()));
-        let clock: Object = Object::Callable(Function::Native {
-            arity: 0,
-            body: Box::new(|_args: &Vec<Object>| {
-                Object::Number(
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .expect("Could not retrieve time.")
-                        .as_millis() as f64,
-                )
-            }),
-        });
-        globals.borrow_mut().define("clock".to_string(), clock);
+        stdlib::register(&mut globals.borrow_mut());
         Interpreter {
             globals: Rc::clone(&globals),
             environment: Rc::clone(&globals),
+            modules: Modules::new(),
+            intent_threshold: intent::DEFAULT_THRESHOLD,
+            regexes: VecDeque::new(),
+            channel: Box::new(Console),
+            storage: Box::new(MemoryStorage::new()),
+            user: String::new(),
+            machine_depth: 0,
+            exited: false,
+            suspension: Vec::new(),
+            resuming: Vec::new(),
+            calls: Vec::new(),
+            entered: false,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            tail: false,
+            tail_call: None,
+            meter: Meter::new(Limits::default()),
+            cancel: CancelToken::new(),
+            hook: None,
+        }
+    }
+
+    ///
+    /// 编译正则表达式，最近使用的模式缓存在解释器中，不会重复编译
+    ///
+    /// # 参数列表
+    /// * pattern: 正则表达式
+    ///
+    /// # 返回值
+    /// * 编译后的正则表达式
+    /// * 模式不合法时返回 Error::Native
+    ///
+    pub fn regex(&mut self, pattern: &str) -> Result<Rc<Regex>, Error> {
+        if let Some(index) = self.regexes.iter().position(|(p, _)| p == pattern) {
+            let entry = self.regexes.remove(index).unwrap();
+            let regex = Rc::clone(&entry.1);
+            self.regexes.push_front(entry);
+            return Ok(regex);
         }
+        let regex = Regex::new(pattern)
+            .map(Rc::new)
+            .map_err(|e| Error::Native(format!("Invalid pattern '{}': {}", pattern, e)))?;
+        self.regexes.truncate(REGEX_CACHE_SIZE - 1);
+        self.regexes
+            .push_front((pattern.to_string(), Rc::clone(&regex)));
+        Ok(regex)
+    }
+
+    ///
+    /// 已缓存的正则表达式个数
+    ///
+    pub fn cached_regex_count(&self) -> usize {
+        self.regexes.len()
+    }
+
+    ///
+    /// 设置与用户对话的通道，默认为标准输入输出
+    ///
+    /// # 参数列表
+    /// * channel: speak、input 和 menu 使用的通道
+    ///
+    pub fn set_channel<C: Channel + 'static>(&mut self, channel: C) {
+        self.channel = Box::new(channel);
+    }
+
+    ///
+    /// 设置 store 和 load 使用的存储，默认为进程内的内存存储
+    ///
+    /// # 参数列表
+    /// * user: 宿主程序提供的用户标识，不同用户的数据互不可见
+    /// * storage: 存储
+    ///
+    pub fn set_storage<S: Storage + 'static>(&mut self, user: &str, storage: S) {
+        self.user = user.to_string();
+        self.storage = Box::new(storage);
+    }
+
+    ///
+    /// 为当前用户保存一个值
+    ///
+    /// # 参数列表
+    /// * key: 键
+    /// * value: 值，只能是 nil、布尔值、数字、字符串，或由它们组成的列表和映射
+    ///
+    pub fn store(&mut self, key: &str, value: &Object) -> Result<(), Error> {
+        let value = storage::to_json(value)?;
+        self.storage.store(&self.user, key, value)
+    }
+
+    ///
+    /// 读取当前用户保存的值
+    ///
+    /// # 返回值
+    /// * 保存过的值，没有保存过时为 nil
+    ///
+    pub fn load(&self, key: &str) -> Result<Object, Error> {
+        Ok(self
+            .storage
+            .load(&self.user, key)?
+            .map(storage::from_json)
+            .unwrap_or(Object::Null))
+    }
+
+    ///
+    /// 设置意图匹配的相似度阈值
+    ///
+    /// # 参数列表
+    /// * threshold: 阈值，取值范围为 [0, 1]，越大匹配越严格
+    ///
+    pub fn set_intent_threshold(&mut self, threshold: f64) {
+        self.intent_threshold = threshold;
+    }
+
+    ///
+    /// 设置 step 调用的最大深度，超出时报告运行时错误并附上调用轨迹
+    ///
+    /// 处在 step 函数体尾部的调用不加深调用栈，因此用尾调用实现的
+    /// “返回上级菜单”不受此限制。
+    ///
+    /// 默认深度需要 `STACK_SIZE` 大小的线程栈，在更小的栈上运行时应当相应地调低。
+    ///
+    /// # 参数列表
+    /// * depth: 最大深度，默认为 `DEFAULT_MAX_CALL_DEPTH`
+    ///
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
+    ///
+    /// step 调用的最大深度
+    ///
+    pub fn max_call_depth(&self) -> usize {
+        self.max_call_depth
+    }
+
+    ///
+    /// 设置资源限制，超出时返回 `Error::Limit`
+    ///
+    /// # 参数列表
+    /// * limits: 资源限制，默认不做限制
+    ///
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.meter = Meter::new(limits);
+    }
+
+    ///
+    /// 当前的资源限制
+    ///
+    pub fn limits(&self) -> Limits {
+        self.meter.limits()
+    }
+
+    ///
+    /// 设置取消令牌，令牌被取消后执行以 `Error::Cancelled` 结束
+    ///
+    /// # 参数列表
+    /// * token: 宿主持有其克隆的令牌
+    ///
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
+
+    ///
+    /// 解释器使用的取消令牌，克隆后可以在其他线程中取消
+    ///
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    ///
+    /// 设置调试钩子，之后每条语句执行之前都会调用它
+    ///
+    /// # 参数列表
+    /// * hook: 调试钩子，通常是 `debug::Debugger`
+    ///
+    pub fn set_debug_hook<H: Hook + 'static>(&mut self, hook: H) {
+        self.hook = Some(Box::new(hook));
+    }
+
+    ///
+    /// 设置入口脚本的路径，import 的相对路径以该脚本所在目录为基准
+    ///
+    /// # 参数列表
+    /// * path: 入口脚本路径
+    ///
+    pub fn set_script_path(&mut self, path: &Path) -> Result<(), Error> {
+        self.modules.set_entry(path)?;
+        Ok(())
+    }
+
+    ///
+    /// 设置已经规范化的入口脚本路径，供共享同一程序的会话使用
+    ///
+    pub(crate) fn set_entry_path(&mut self, canonical: &Path) {
+        self.modules.set_canonical_entry(canonical.to_path_buf());
+    }
+
+    ///
+    /// 创建解释器的构造器，用于注册宿主函数和全局常量
+    ///
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::new()
     }
 
     ///
@@ -52,7 +308,7 @@ impl Interpreter {
     /// * statements: 语句列表
     ///
     /// # 返回值
-    /// * 执行过程是否出错
+    /// * 执行过程是否出错，执行到 exit 语句时视为正常结束
     ///
     /// # 使用示例
     /// let mut interpreter = Interpreter::new();
@@ -65,11 +321,151 @@ impl Interpreter {
     /// let statements = parser.parse(tokens);
     /// interpreter.interpret(statements);
     ///
-    pub fn interpret(&mut self, statements: &Vec<Stmt>) -> Result<(), Error> {
-        for statement in statements {
-            self.execute(statement)?;
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), Error> {
+        self.run(|interpreter| interpreter.execute_statements(statements))
+    }
+
+    ///
+    /// 执行一次完整的运行，开始前重置运行状态，结束后处理暂停和 exit
+    ///
+    fn run(&mut self, body: impl FnOnce(&mut Self) -> Result<(), Error>) -> Result<(), Error> {
+        self.calls.clear();
+        self.entered = false;
+        self.tail = false;
+        self.tail_call = None;
+        self.meter.reset();
+        let result = body(self);
+        self.resuming.clear();
+        match result {
+            Err(Error::Suspend) => Err(Error::Suspend),
+            Err(Error::Exit) => {
+                self.suspension.clear();
+                self.exited = true;
+                Ok(())
+            }
+            other => {
+                self.suspension.clear();
+                other
+            }
         }
-        Ok(())
+    }
+
+    ///
+    /// 从上次暂停的位置继续解释语句
+    ///
+    /// 通道在没有输入时返回 `Error::Suspend`，解释器随即暂停并记录执行位置，
+    /// 之后以同一组语句调用本方法，即可跳过已执行的部分，回到暂停处重新读取输入。
+    ///
+    /// # 参数列表
+    /// * statements: 暂停前传给 interpret 的语句列表
+    ///
+    /// # 返回值
+    /// * 再次暂停时为 Err(Error::Suspend)
+    ///
+    pub fn resume(&mut self, statements: &[Stmt]) -> Result<(), Error> {
+        self.resuming = std::mem::take(&mut self.suspension);
+        match self.resuming.last() {
+            // 最外层是状态机时，暂停发生在 run_state_machine 中，语句早已执行完毕
+            Some(Frame::Machine { state, .. }) => {
+                let state = Rc::clone(state);
+                self.run(|interpreter| interpreter.run_machine(state))
+            }
+            _ => self.interpret(statements),
+        }
+    }
+
+    ///
+    /// 丢弃暂停时记录的执行位置
+    ///
+    pub(crate) fn clear_suspension(&mut self) {
+        self.suspension.clear();
+    }
+
+    ///
+    /// 暂停时记录的执行位置，从内到外排列
+    ///
+    pub(crate) fn suspension(&self) -> &[Frame] {
+        &self.suspension
+    }
+
+    ///
+    /// 设置暂停位置，用于从快照恢复会话
+    ///
+    pub(crate) fn set_suspension(&mut self, frames: Vec<Frame>) {
+        self.suspension = frames;
+    }
+
+    ///
+    /// 已导入的模块的环境
+    ///
+    pub(crate) fn module_environments(&self) -> impl Iterator<Item = &Rc<RefCell<Environment>>> {
+        self.modules.environments()
+    }
+
+    ///
+    /// 是否因等待输入而暂停
+    ///
+    pub fn is_suspended(&self) -> bool {
+        !self.suspension.is_empty()
+    }
+
+    ///
+    /// 通过通道输出，计入输出量
+    ///
+    fn speak(&mut self, text: &str) -> Result<(), Error> {
+        self.meter.speak(text)?;
+        self.channel.speak(text)
+    }
+
+    ///
+    /// 从通道读取输入，等待的时间不计入运行时间
+    ///
+    fn listen(&mut self) -> Result<Option<String>, Error> {
+        let since = Instant::now();
+        let input = self.channel.listen();
+        self.meter.waited_since(since);
+        if let Ok(Some(text)) = &input {
+            self.meter.check_string(text)?;
+        }
+        input
+    }
+
+    ///
+    /// 每条语句执行前的检查点，检查取消令牌与资源用量
+    ///
+    fn checkpoint(&mut self) -> Result<(), Error> {
+        if self.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        self.meter.tick()
+    }
+
+    ///
+    /// listen 语句的等待，被取消时提前结束
+    ///
+    /// 等待计入运行时间，超过剩余时间时只等到时间用完，然后报告超时。
+    ///
+    fn pause(&mut self, duration: Duration) -> Result<(), Error> {
+        let left = self.meter.time_left();
+        if !self
+            .cancel
+            .sleep(left.map_or(duration, |left| left.min(duration)))
+        {
+            return Err(Error::Cancelled);
+        }
+        match (left, self.meter.limits().time) {
+            (Some(left), Some(limit)) if left < duration => {
+                Err(Error::Limit(LimitExceeded::Time(limit)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// 是否执行过 exit 语句
+    ///
+    pub fn has_exited(&self) -> bool {
+        self.exited
     }
 
     ///
@@ -98,14 +494,104 @@ impl Interpreter {
         self.evaluate(expression).map(|value| self.stringify(value))
     }
 
+    ///
+    /// 计算表达式，得到它的值而不是显示用的文本
+    ///
+    /// # 参数列表
+    /// * expression: 表达式
+    ///
+    pub fn evaluate_expression(&mut self, expression: &Expr) -> Result<Object, Error> {
+        self.evaluate(expression)
+    }
+
+    ///
+    /// 从指定的全局状态开始运行状态机，直到进入终止状态
+    ///
+    /// 在状态机中暂停后，以原来的语句调用 `resume` 即可回到状态机中继续运行。
+    ///
+    /// # 参数列表
+    /// * start: 起始状态名
+    ///
+    /// # 使用示例
+    /// interpreter.interpret(&statements)?;
+    /// interpreter.run_state_machine("Welcome")?;
+    ///
+    pub fn run_state_machine(&mut self, start: &str) -> Result<(), Error> {
+        let state = match self.globals.borrow().lookup(start) {
+            Some(Object::State(state)) => state,
+            Some(other) => {
+                return Err(Error::Native(format!(
+                    "'{}' is not a state, got {}.",
+                    start,
+                    other.type_name()
+                )))
+            }
+            None => return Err(Error::Native(format!("Undefined state '{}'.", start))),
+        };
+        self.run(|interpreter| interpreter.run_machine(state))
+    }
+
+    ///
+    /// 调用一个可调用对象，供原生函数回调 dsl 中的 step 使用
+    ///
+    /// # 参数列表
+    /// * callee: 被调用的对象
+    /// * arguments: 参数列表
+    ///
+    /// # 返回值
+    /// * 调用结果
+    /// * 错误，不是函数或参数个数不符时返回 Error::Native
+    ///
+    pub fn call(&mut self, callee: &Object, arguments: &[Object]) -> Result<Object, Error> {
+        match callee {
+            Object::Callable(function) => {
+                if arguments.len() != function.arity() {
+                    Err(Error::Native(format!(
+                        "Expected {} arguments but got {}.",
+                        function.arity(),
+                        arguments.len()
+                    )))
+                } else {
+                    function.call(self, arguments)
+                }
+            }
+            _ => Err(Error::Native(
+                "Can only call functions and classes.".to_string(),
+            )),
+        }
+    }
+
     fn evaluate(&mut self, expression: &Expr) -> Result<Object, Error> {
-        expression.accept(self)
+        let value = expression.accept(self)?;
+        self.meter.check(&value)?;
+        Ok(value)
     }
 
     fn execute(&mut self, statement: &Stmt) -> Result<(), Error> {
+        self.checkpoint()?;
+        if self.hook.is_some() && self.resuming.is_empty() {
+            self.call_hook(statement)?;
+        }
         statement.accept(self)
     }
 
+    fn call_hook(&mut self, statement: &Stmt) -> Result<(), Error> {
+        let line = match statement.line() {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        // 钩子不会重新进入解释器，调用期间暂时取出
+        let mut hook = match self.hook.take() {
+            Some(hook) => hook,
+            None => return Ok(()),
+        };
+        let entered = std::mem::take(&mut self.entered);
+        let context = Context::new(line, statement, &self.environment, &self.calls, entered);
+        let result = hook.statement(&context);
+        self.hook = Some(hook);
+        result
+    }
+
     ///
     /// 执行语句块
     ///
@@ -118,26 +604,342 @@ impl Interpreter {
     ///
     pub fn execute_block(
         &mut self,
-        statements: &Vec<Stmt>,
+        statements: &[Stmt],
         environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Error> {
+        self.run_block(statements, environment, false)
+    }
+
+    ///
+    /// 执行语句块，tail 表示语句块是否处在 step 函数体的尾部
+    ///
+    fn run_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+        tail: bool,
     ) -> Result<(), Error> {
         let previous = self.environment.clone();
-        let steps = || -> Result<(), Error> {
-            self.environment = environment;
-            for statement in statements {
-                self.execute(statement)?
-            }
-            Ok(())
-        };
-        let result = steps();
+        let enclosing_tail = std::mem::replace(&mut self.tail, tail);
+        self.environment = environment;
+        let result = self.execute_statements(statements);
         self.environment = previous;
+        self.tail = enclosing_tail;
+        result
+    }
+
+    fn execute_statements(&mut self, statements: &[Stmt]) -> Result<(), Error> {
+        let start = match self.resume_frame() {
+            Some(Frame::Statement(index)) => index,
+            Some(_) => return Err(resume_mismatch()),
+            None => 0,
+        };
+        let tail = self.tail;
+        for (index, statement) in statements.iter().enumerate().skip(start) {
+            self.tail = tail && index + 1 == statements.len();
+            let result = self.execute(statement);
+            self.tail = tail;
+            self.on_suspend(result, || Frame::Statement(index))?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// 进入一次 step 调用，超出最大深度时报错
+    ///
+    fn enter_call(&mut self, function: &Function, paren: &Token) -> Result<(), Error> {
+        if self.calls.len() >= self.max_call_depth {
+            return Err(Error::Runtime {
+                token: paren.clone(),
+                message: depth_message(self.max_call_depth, &self.calls),
+            });
+        }
+        self.calls.push((function.name().to_string(), paren.line));
+        self.entered = true;
+        Ok(())
+    }
+
+    ///
+    /// 离开一次 step 调用
+    ///
+    fn leave_call(&mut self) {
+        self.calls.pop();
+        // 函数体为空时没有语句取走这个标记
+        self.entered = false;
+    }
+
+    ///
+    /// 恢复执行时取出当前语句的位置记录，不在恢复过程中时返回 None
+    ///
+    fn resume_frame(&mut self) -> Option<Frame> {
+        self.resuming.pop()
+    }
+
+    ///
+    /// 执行结果为暂停时，记录当前语句的位置
+    ///
+    fn on_suspend<T>(
+        &mut self,
+        result: Result<T, Error>,
+        frame: impl FnOnce() -> Frame,
+    ) -> Result<T, Error> {
+        if let Err(Error::Suspend) = result {
+            self.suspension.push(frame());
+        }
+        result
+    }
+
+    ///
+    /// 在无法恢复的位置（表达式内部、原生函数、模块加载）暂停时转为运行时错误
+    ///
+    fn forbid_suspend<T>(
+        &mut self,
+        result: Result<T, Error>,
+        token: &Token,
+        context: &str,
+    ) -> Result<T, Error> {
+        match result {
+            Err(Error::Suspend) => {
+                self.suspension.clear();
+                Err(Error::Runtime {
+                    token: token.clone(),
+                    message: format!("Cannot wait for input {}.", context),
+                })
+            }
+            other => other,
+        }
+    }
+
+    fn load_module(
+        &mut self,
+        keyword: &Token,
+        path: PathBuf,
+    ) -> Result<Rc<RefCell<Environment>>, Error> {
+        self.modules.enter(&path).map_err(|cycle| Error::Runtime {
+            token: keyword.clone(),
+            message: format!("Import cycle detected: {}.", cycle),
+        })?;
+
+        let environment = Rc::new(RefCell::new(Environment::from(&self.globals)));
+        let result = fs::read_to_string(&path)
+            .map_err(Error::from)
+            .and_then(|source| {
+                let mut scanner = Scanner::new(source);
+                let tokens = scanner.scan_tokens();
+                // 语法错误已由解析器报告，这里补上出错的模块文件
+                let statements = Parser::new(tokens).parse().map_err(|_| Error::Runtime {
+                    token: keyword.clone(),
+                    message: format!("Syntax error in imported file '{}'.", path.display()),
+                })?;
+                let result = self.execute_block(&statements, Rc::clone(&environment));
+                self.forbid_suspend(result, keyword, "while importing a module")
+            });
+
+        match result {
+            Ok(()) => {
+                self.modules.leave(Some(Rc::clone(&environment)));
+                Ok(environment)
+            }
+            Err(error) => {
+                self.modules.leave(None);
+                Err(error)
+            }
+        }
+    }
+
+    // Defines a variable in the current scope unless a constant already owns the name.
+    fn declare(&mut self, name: &Token, value: Object) -> Result<(), Error> {
+        self.check_redeclaration(name)?;
+        self.environment.borrow_mut().define(name.symbol, value);
+        Ok(())
+    }
+
+    fn check_redeclaration(&self, name: &Token) -> Result<(), Error> {
+        match self.environment.borrow().constant_line(name.symbol) {
+            Some(line) => Err(Error::Runtime {
+                token: name.clone(),
+                message: constant_message(&name.lexeme, line),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn run_machine(&mut self, start: Rc<State>) -> Result<(), Error> {
+        self.machine_depth += 1;
+        let result = self.drive(start);
+        self.machine_depth -= 1;
         result
     }
 
+    ///
+    /// 状态机的驱动循环，goto 发出的状态转移在这里被捕获，
+    /// 因此状态之间的转移不会加深调用栈
+    ///
+    fn drive(&mut self, start: Rc<State>) -> Result<(), Error> {
+        let (mut current, mut phase, mut environment) = match self.resume_frame() {
+            Some(Frame::Machine {
+                state,
+                phase,
+                environment,
+            }) => (state, phase, environment),
+            Some(_) => return Err(resume_mismatch()),
+            None => (start, MachinePhase::Enter, None),
+        };
+        loop {
+            let state = Rc::clone(&current);
+            let scope = environment
+                .take()
+                .unwrap_or_else(|| Rc::new(RefCell::new(Environment::from(&state.closure))));
+            let result = match phase {
+                MachinePhase::Enter => self.execute_block(&state.enter, Rc::clone(&scope)),
+                MachinePhase::Waiting => match self.listen() {
+                    Ok(Some(input)) => {
+                        let matcher = IntentMatcher::new(self.intent_threshold);
+                        let handlers = state.handlers.iter().map(|arm| arm.patterns.as_slice());
+                        phase = match matcher.best(&input, handlers) {
+                            Some(index) => MachinePhase::Handler(Some(index)),
+                            None if state.fallback.is_some() => MachinePhase::Handler(None),
+                            None => MachinePhase::Enter,
+                        };
+                        continue;
+                    }
+                    Ok(None) => {
+                        return Err(input_ended(&format!(
+                            "input in state {}",
+                            state.name.lexeme
+                        )))
+                    }
+                    Err(error) => Err(error),
+                },
+                MachinePhase::Handler(index) => {
+                    let handler = match index {
+                        Some(index) => &state.handlers[index].body,
+                        None => state.fallback.as_deref().ok_or_else(resume_mismatch)?,
+                    };
+                    self.execute_block(std::slice::from_ref(handler), Rc::clone(&scope))
+                }
+            };
+
+            match result {
+                Err(Error::Transition(next)) => {
+                    current = next;
+                    phase = MachinePhase::Enter;
+                }
+                Err(Error::Suspend) => {
+                    let environment = match phase {
+                        MachinePhase::Waiting => None,
+                        _ => Some(scope),
+                    };
+                    self.suspension.push(Frame::Machine {
+                        state,
+                        phase,
+                        environment,
+                    });
+                    return Err(Error::Suspend);
+                }
+                Err(error) => return Err(error),
+                Ok(()) => {
+                    phase = match phase {
+                        MachinePhase::Enter if state.is_terminal() => return Ok(()),
+                        MachinePhase::Enter => MachinePhase::Waiting,
+                        _ => MachinePhase::Enter,
+                    };
+                }
+            }
+        }
+    }
+
+    fn render_menu(
+        &mut self,
+        prompt: &Option<String>,
+        options: &[MenuOption],
+    ) -> Result<(), Error> {
+        if let Some(prompt) = prompt {
+            self.speak(prompt)?;
+        }
+        for (number, option) in options.iter().enumerate() {
+            let line = match &option.label {
+                Some(label) => format!("{}. {} ({})", number + 1, label, option.keys[0]),
+                None => format!("{}. {}", number + 1, option.keys[0]),
+            };
+            self.speak(&line)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// 执行作为语句的调用，只有这种调用中的 step 可以暂停等待输入
+    ///
+    fn call_statement(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+    ) -> Result<(), Error> {
+        let (mut function, mut environment) = match self.resume_frame() {
+            Some(Frame::Call {
+                function,
+                environment,
+            }) => (function, environment),
+            Some(_) => return Err(resume_mismatch()),
+            None => {
+                let callee_value = self.evaluate(callee)?;
+                let args: Result<Vec<Object>, Error> =
+                    arguments.iter().map(|expr| self.evaluate(expr)).collect();
+                let args = args?;
+                match callee_value {
+                    Object::Callable(function @ Function::User { .. })
+                        if function.arity() == args.len() =>
+                    {
+                        let environment = match &function {
+                            Function::User { closure, .. } => function.bind(closure, &args),
+                            Function::Native { .. } => unreachable!(),
+                        };
+                        if self.tail {
+                            // 尾调用：交给外层调用执行，当前调用在轨迹中被替换
+                            if let Some(call) = self.calls.last_mut() {
+                                *call = (function.name().to_string(), paren.line);
+                            }
+                            self.entered = true;
+                            self.tail_call = Some((function, environment));
+                            return Ok(());
+                        }
+                        (function, environment)
+                    }
+                    other => {
+                        let result = self.call(&other, &args);
+                        let result = self.forbid_suspend(result, paren, "inside a native function");
+                        return result.map(|_| ()).map_err(|error| locate(error, paren));
+                    }
+                }
+            }
+        };
+
+        self.enter_call(&function, paren)?;
+        let result = loop {
+            let result = match &function {
+                Function::User { body, .. } => self.run_block(body, Rc::clone(&environment), true),
+                Function::Native { .. } => Err(resume_mismatch()),
+            };
+            match (result, self.tail_call.take()) {
+                (Ok(()), Some((next, scope))) => {
+                    function = next;
+                    environment = scope;
+                }
+                (result, _) => break result,
+            }
+        };
+        self.leave_call();
+        self.on_suspend(result, || Frame::Call {
+            function,
+            environment,
+        })
+    }
+
     fn is_truthy(&self, object: &Object) -> bool {
         match object {
             Object::Null => false,
-            Object::Boolean(b) => b.clone(),
+            Object::Boolean(b) => *b,
             _ => true,
         }
     }
@@ -148,11 +950,8 @@ impl Interpreter {
 
     fn stringify(&self, object: Object) -> String {
         match object {
-            Object::Null => "nil".to_string(),
-            Object::Number(n) => n.to_string(),
-            Object::Boolean(b) => b.to_string(),
-            Object::Callable(f) => f.to_string(),
             Object::String(s) => s,
+            other => other.to_string(),
         }
     }
 
@@ -212,43 +1011,52 @@ impl expr::Visitor<Object> for Interpreter {
         &mut self,
         callee: &Expr,
         paren: &Token,
-        arguments: &Vec<Expr>,
+        arguments: &[Expr],
     ) -> Result<Object, Error> {
         let callee_value = self.evaluate(callee)?;
 
-        let argument_values: Result<Vec<Object>, Error> = arguments
-            .into_iter()
-            .map(|expr| self.evaluate(expr))
-            .collect();
+        let argument_values: Result<Vec<Object>, Error> =
+            arguments.iter().map(|expr| self.evaluate(expr)).collect();
         let args = argument_values?;
 
-        if let Object::Callable(function) = callee_value {
-            let args_size = args.len();
-            if args_size != function.arity() {
-                Err(Error::Runtime {
-                    token: paren.clone(),
-                    message: format!(
-                        "Expected {} arguments but got {}.",
-                        function.arity(),
-                        args_size
-                    ),
-                })
-            } else {
-                function.call(self, &args)
+        let result = match &callee_value {
+            Object::Callable(function @ Function::User { .. }) => {
+                self.enter_call(function, paren)?;
+                let result = self.call(&callee_value, &args);
+                self.leave_call();
+                result
             }
-        } else {
-            Err(Error::Runtime {
-                token: paren.clone(),
-                message: "Can only call functions and classes.".to_string(),
-            })
+            _ => self.call(&callee_value, &args),
+        };
+        let result = self.forbid_suspend(result, paren, "inside an expression");
+        result.map_err(|error| locate(error, paren))
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<Object, Error> {
+        match self.evaluate(object)? {
+            Object::Module(module) => module.environment.borrow().get(name),
+            Object::Map(entries) => match entries.get(&name.lexeme) {
+                Some(value) => Ok(value.clone()),
+                None => Err(Error::Runtime {
+                    token: name.clone(),
+                    message: format!("Undefined key '{}'.", name.lexeme),
+                }),
+            },
+            other => Err(Error::Runtime {
+                token: name.clone(),
+                message: format!(
+                    "Only modules and maps have members, got {}.",
+                    other.type_name()
+                ),
+            }),
         }
     }
 
     fn visit_literal_expr(&self, value: &LiteralValue) -> Result<Object, Error> {
         match value {
-            LiteralValue::Boolean(b) => Ok(Object::Boolean(b.clone())),
+            LiteralValue::Boolean(b) => Ok(Object::Boolean(*b)),
             LiteralValue::Null => Ok(Object::Null),
-            LiteralValue::Number(n) => Ok(Object::Number(n.clone())),
+            LiteralValue::Number(n) => Ok(Object::Number(*n)),
             LiteralValue::String(s) => Ok(Object::String(s.clone())),
         }
     }
@@ -258,7 +1066,7 @@ impl expr::Visitor<Object> for Interpreter {
 
         match &operator.tpe {
             TokenType::Minus => match right {
-                Object::Number(n) => Ok(Object::Number(-n.clone())),
+                Object::Number(n) => Ok(Object::Number(-n)),
                 _ => self.number_operand_error(operator),
             },
             TokenType::Bang => Ok(Object::Boolean(!self.is_truthy(&right))), // TODO: is_truthy could simply return an Object.
@@ -277,93 +1085,119 @@ impl expr::Visitor<Object> for Interpreter {
     }
 }
 
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl stmt::Visitor<()> for Interpreter {
-    fn visit_block_stmt(&mut self, statements: &Vec<Stmt>) -> Result<(), Error> {
-        self.execute_block(
-            statements,
-            Rc::new(RefCell::new(Environment::from(&self.environment))),
-        );
-        Ok(())
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<(), Error> {
+        let environment = match self.resume_frame() {
+            Some(Frame::Scope(environment)) => environment,
+            Some(_) => return Err(resume_mismatch()),
+            None => Rc::new(RefCell::new(Environment::from(&self.environment))),
+        };
+        let result = self.run_block(statements, Rc::clone(&environment), self.tail);
+        self.on_suspend(result, || Frame::Scope(environment))
     }
 
     fn visit_expression_stmt(&mut self, expression: &Expr) -> Result<(), Error> {
+        if let Expr::Call {
+            callee,
+            paren,
+            arguments,
+        } = expression
+        {
+            return self.call_statement(callee, paren, arguments);
+        }
         self.evaluate(expression)?;
         Ok(())
     }
 
     fn visit_branch_stmt(&mut self, condition: &Expr, then: &Stmt) -> Result<(), Error> {
-        let condition = self.evaluate(condition)?;
-        if self.is_truthy(&condition) {
-            self.execute(then)?;
+        let taken = match self.resume_frame() {
+            Some(Frame::Branch) => true,
+            Some(_) => return Err(resume_mismatch()),
+            None => {
+                let condition = self.evaluate(condition)?;
+                self.is_truthy(&condition)
+            }
+        };
+        if taken {
+            let result = self.execute(then);
+            self.on_suspend(result, || Frame::Branch)?;
         }
         Ok(())
     }
 
     fn visit_loop_stmt(&mut self, body: &Stmt) -> Result<(), Error> {
-        loop {
-            self.execute(body)?;
-        }
+        // 循环体执行完还要回到循环开头，其中的调用不是尾调用
+        let tail = std::mem::replace(&mut self.tail, false);
+        let result = loop {
+            if let Err(error) = self.execute(body) {
+                break Err(error);
+            }
+        };
+        self.tail = tail;
+        result
     }
 
     fn visit_function_stmt(
         &mut self,
         name: &Token,
-        params: &Vec<Token>,
-        body: &Vec<Stmt>,
+        params: &Arc<[Token]>,
+        body: &Arc<[Stmt]>,
     ) -> Result<(), Error> {
         let function = Function::User {
             name: name.clone(),
-            params: params.clone(),
-            body: body.clone(),
+            params: Arc::clone(params),
+            body: Arc::clone(body),
             closure: Rc::clone(&self.environment),
         };
-        self.environment
-            .borrow_mut()
-            .define(name.lexeme.clone(), Object::Callable(function));
-        Ok(())
+        self.declare(name, Object::Callable(function))
     }
 
     fn visit_speak_stmt(&mut self, expression: &Expr) -> Result<(), Error> {
         let value = self.evaluate(expression)?;
-        println!("{}", self.stringify(value));
-        Ok(())
+        let text = self.stringify(value);
+        self.speak(&text)
     }
 
     fn visit_input_stmt(&mut self, name: &Token) -> Result<(), Error> {
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        // remove '\n'
-        input.pop();
-        self.environment
-            .borrow_mut()
-            .define(name.lexeme.clone(), Object::String(input));
-        Ok(())
+        self.check_redeclaration(name)?;
+        let input = self.listen()?.unwrap_or_default();
+        self.declare(name, Object::String(input))
     }
 
     fn visit_inputn_stmt(&mut self, name: &Token) -> Result<(), Error> {
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        // remove '\n'
-        input.pop();
-        let number: f64 = input.parse().unwrap();
-        self.environment
-            .borrow_mut()
-            .define(name.lexeme.clone(), Object::Number(number));
-        Ok(())
+        self.check_redeclaration(name)?;
+        let input = self.listen()?.unwrap_or_default();
+        let number: f64 = input.trim().parse().map_err(|_| Error::Runtime {
+            token: name.clone(),
+            message: format!("Expected a number but got \"{}\".", input),
+        })?;
+        self.declare(name, Object::Number(number))
     }
 
-    fn visit_listen_stmt(&mut self, time: &Expr) -> Result<(), Error> {
-        let temp = self.evaluate(time)?;
-        // from object to number
-        let num = match temp {
-            Object::Number(n) => n,
-            _ => unreachable!(),
+    fn visit_listen_stmt(&mut self, keyword: &Token, time: &Expr) -> Result<(), Error> {
+        let seconds = match self.evaluate(time)? {
+            Object::Number(n) if n.is_finite() && n >= 0.0 => n,
+            Object::Number(n) => {
+                return Err(Error::Runtime {
+                    token: keyword.clone(),
+                    message: format!("Listen time must be a non-negative number, got {}.", n),
+                })
+            }
+            other => {
+                return Err(Error::Runtime {
+                    token: keyword.clone(),
+                    message: format!("Listen time must be a number, got {}.", other.type_name()),
+                })
+            }
         };
-        // from number to duration
-        // sleep as seconds
-        let dur = Duration::from_secs_f64(num);
-        sleep(dur);
-        Ok(())
+        // sleep as seconds, waking up early when cancelled
+        self.pause(Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX))
     }
 
     fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) -> Result<(), Error> {
@@ -372,13 +1206,267 @@ impl stmt::Visitor<()> for Interpreter {
             .map(|i| self.evaluate(i))
             .unwrap_or(Ok(Object::Null))?;
 
+        self.declare(name, value)
+    }
+
+    fn visit_const_stmt(&mut self, name: &Token, initializer: &Expr) -> Result<(), Error> {
+        let value = self.evaluate(initializer)?;
+        self.check_redeclaration(name)?;
         self.environment
             .borrow_mut()
-            .define(name.lexeme.clone(), value);
+            .define_constant(name.symbol, value, name.line);
         Ok(())
     }
 
     fn visit_exit_stmt(&mut self) -> Result<(), Error> {
-        std::process::exit(0);
+        Err(Error::Exit)
+    }
+
+    fn visit_match_stmt(
+        &mut self,
+        keyword: &Token,
+        subject: &Expr,
+        arms: &[IntentArm],
+        fallback: &Option<Box<Stmt>>,
+    ) -> Result<(), Error> {
+        let chosen = match self.resume_frame() {
+            Some(Frame::Arm(chosen)) => chosen,
+            Some(_) => return Err(resume_mismatch()),
+            None => {
+                let input = match self.evaluate(subject)? {
+                    Object::String(s) => s,
+                    other => {
+                        return Err(Error::Runtime {
+                            token: keyword.clone(),
+                            message: format!(
+                                "Intent subject must be a string, got {}.",
+                                other.type_name()
+                            ),
+                        })
+                    }
+                };
+                let matcher = IntentMatcher::new(self.intent_threshold);
+                matcher.best(&input, arms.iter().map(|arm| arm.patterns.as_slice()))
+            }
+        };
+
+        let body = match (chosen, fallback) {
+            (Some(index), _) => &arms[index].body,
+            (None, Some(statement)) => statement.as_ref(),
+            (None, None) => return Ok(()),
+        };
+        let result = self.execute(body);
+        self.on_suspend(result, || Frame::Arm(chosen))
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        keyword: &Token,
+        path: &str,
+        alias: &Option<Token>,
+    ) -> Result<(), Error> {
+        let resolved = self.modules.resolve(path).map_err(|e| Error::Runtime {
+            token: keyword.clone(),
+            message: format!("Cannot import '{}': {}.", path, e),
+        })?;
+        let environment = match self.modules.get(&resolved) {
+            Some(environment) => environment,
+            None => self.load_module(keyword, resolved)?,
+        };
+
+        match alias {
+            Some(alias) => {
+                let module = Module {
+                    name: alias.lexeme.clone(),
+                    environment,
+                };
+                self.declare(alias, Object::Module(Rc::new(module)))?;
+            }
+            None => {
+                let module = environment.borrow();
+                for (name, _) in module.entries() {
+                    if let Some(line) = self.environment.borrow().constant_line(name) {
+                        return Err(Error::Runtime {
+                            token: keyword.clone(),
+                            message: constant_message(name.as_str(), line),
+                        });
+                    }
+                }
+                let mut current = self.environment.borrow_mut();
+                for (name, value) in module.entries() {
+                    match module.constant_line(name) {
+                        Some(line) => current.define_constant(name, value.clone(), line),
+                        None => current.define(name, value.clone()),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_menu_stmt(
+        &mut self,
+        _keyword: &Token,
+        prompt: &Option<Expr>,
+        options: &[MenuOption],
+    ) -> Result<(), Error> {
+        let (prompt, mut phase, mut rendered) = match self.resume_frame() {
+            Some(Frame::Menu { prompt, phase }) => (prompt, phase, true),
+            Some(_) => return Err(resume_mismatch()),
+            None => {
+                let prompt = match prompt {
+                    Some(prompt) => {
+                        let value = self.evaluate(prompt)?;
+                        Some(self.stringify(value))
+                    }
+                    None => None,
+                };
+                (prompt, MenuPhase::Choosing, false)
+            }
+        };
+        let synonyms: Vec<Vec<String>> = options.iter().map(MenuOption::synonyms).collect();
+
+        loop {
+            let result = match phase {
+                MenuPhase::Choosing => {
+                    if !rendered {
+                        self.render_menu(&prompt, options)?;
+                    }
+                    rendered = false;
+                    match self.listen() {
+                        Ok(Some(answer)) => {
+                            let choice = match answer.trim().parse::<usize>() {
+                                Ok(number) if (1..=options.len()).contains(&number) => {
+                                    Choice::Exact(number - 1)
+                                }
+                                _ => intent::choose(&answer, synonyms.iter().map(Vec::as_slice)),
+                            };
+                            phase = match choice {
+                                Choice::Exact(index) => MenuPhase::Running(index),
+                                Choice::Suggest(index) => {
+                                    let question =
+                                        format!("Did you mean \"{}\"?", options[index].title());
+                                    self.speak(&question)?;
+                                    MenuPhase::Confirming(index)
+                                }
+                                Choice::Unknown => {
+                                    let message = format!(
+                                        "Sorry, I didn't understand \"{}\". Please choose 1-{}.",
+                                        answer,
+                                        options.len()
+                                    );
+                                    self.speak(&message)?;
+                                    MenuPhase::Choosing
+                                }
+                            };
+                            continue;
+                        }
+                        Ok(None) => return Err(input_ended("a menu choice")),
+                        Err(error) => Err(error),
+                    }
+                }
+                MenuPhase::Confirming(index) => match self.listen() {
+                    Ok(Some(confirm)) => {
+                        phase = if intent::is_affirmative(&confirm) {
+                            MenuPhase::Running(index)
+                        } else {
+                            MenuPhase::Choosing
+                        };
+                        continue;
+                    }
+                    Ok(None) => return Err(input_ended("a menu choice")),
+                    Err(error) => Err(error),
+                },
+                MenuPhase::Running(index) => self.execute(&options[index].body),
+            };
+            return self.on_suspend(result, || Frame::Menu { prompt, phase });
+        }
     }
+
+    fn visit_state_stmt(
+        &mut self,
+        name: &Token,
+        enter: &Arc<[Stmt]>,
+        handlers: &Arc<[IntentArm]>,
+        fallback: &Option<Arc<Stmt>>,
+    ) -> Result<(), Error> {
+        let state = State {
+            name: name.clone(),
+            enter: Arc::clone(enter),
+            handlers: Arc::clone(handlers),
+            fallback: fallback.clone(),
+            closure: Rc::clone(&self.environment),
+        };
+        self.declare(name, Object::State(Rc::new(state)))
+    }
+
+    fn visit_goto_stmt(&mut self, _keyword: &Token, target: &Token) -> Result<(), Error> {
+        let state = match self.environment.borrow().get(target)? {
+            Object::State(state) => state,
+            other => {
+                return Err(Error::Runtime {
+                    token: target.clone(),
+                    message: format!("Can only goto a state, got {}.", other.type_name()),
+                })
+            }
+        };
+        if self.machine_depth > 0 {
+            Err(Error::Transition(state))
+        } else {
+            self.run_machine(state)
+        }
+    }
+}
+
+fn input_ended(waiting_for: &str) -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!("input ended while waiting for {}", waiting_for),
+    ))
+}
+
+///
+/// 生成超出最大调用深度时的报错信息，附上调用轨迹
+///
+/// 连续重复的调用合并为一行，只保留最近的几种调用。
+///
+fn depth_message(limit: usize, calls: &[(String, i32)]) -> String {
+    const SHOWN: usize = 8;
+    let mut collapsed: Vec<(&str, i32, usize)> = Vec::new();
+    for (name, line) in calls {
+        match collapsed.last_mut() {
+            Some((last, at, count)) if *last == name.as_str() && *at == *line => *count += 1,
+            _ => collapsed.push((name, *line, 1)),
+        }
+    }
+    let mut message = format!("Maximum call depth of {} exceeded.", limit);
+    if collapsed.len() > SHOWN {
+        let hidden: usize = collapsed[..collapsed.len() - SHOWN]
+            .iter()
+            .map(|(_, _, count)| count)
+            .sum();
+        message.push_str(&format!("\n  ... {} earlier calls", hidden));
+    }
+    for (name, line, count) in collapsed.iter().skip(collapsed.len().saturating_sub(SHOWN)) {
+        message.push_str(&format!("\n  in {} called on line {}", name, line));
+        if *count > 1 {
+            message.push_str(&format!(" ({} times)", count));
+        }
+    }
+    message
+}
+
+// Gives a native error the position of the call that raised it.
+fn locate(error: Error, paren: &Token) -> Error {
+    match error {
+        Error::Native(message) => Error::Runtime {
+            token: paren.clone(),
+            message,
+        },
+        other => other,
+    }
+}
+
+fn resume_mismatch() -> Error {
+    Error::Native("Cannot resume: the saved position does not match the program.".to_string())
 }
@@ -1,4 +1,20 @@
 ///
+/// 取消令牌，供宿主中止正在运行的解释器
+///
+pub mod cancel;
+///
+/// 与用户对话的输入输出通道
+///
+pub mod channel;
+///
+/// 调试适配器协议（DAP）服务，供编辑器通过标准输入输出调试脚本
+///
+pub mod dap;
+///
+/// 调试钩子、断点与单步执行，以及命令行调试前端
+///
+pub mod debug;
+///
 /// 定义 dsl 运行的环境
 ///
 pub mod env;
@@ -11,9 +27,29 @@ pub mod error;
 ///
 pub mod function;
 ///
+/// 宿主程序嵌入 dsl 的接口，注册 Rust 函数和全局常量
+///
+pub mod host;
+///
+/// 服务模式使用的最小 HTTP/1.1 请求解析与响应
+///
+pub mod http;
+///
+/// 意图匹配，对用户的自由文本回复进行规范化和相似度匹配
+///
+pub mod intent;
+///
 /// 定义 dsl 的解释器
 pub mod interpreter;
 ///
+/// 脚本执行的资源限制
+///
+pub mod limits;
+///
+/// 模块系统，加载并缓存 import 的 dsl 文件
+///
+pub mod module;
+///
 /// 定义 dsl 变量的对象类型
 ///
 pub mod object;
@@ -22,10 +58,50 @@ pub mod object;
 ///
 pub mod parser;
 ///
+/// 编译好的、可在多个会话间共享的 dsl 程序
+///
+pub mod program;
+///
+/// 交互式解释器，支持多行输入与元命令
+///
+pub mod repl;
+///
 /// 扫入源代码，进行词法分析，处理 token
 ///
 pub mod scanner;
 ///
+/// 服务模式，通过 TCP 行协议和 HTTP 同时为多个客户端提供对话
+///
+pub mod server;
+///
+/// 每个用户独立的对话会话
+///
+pub mod session;
+///
+/// 暂停中会话的快照，用于在另一个进程中恢复对话
+///
+pub mod snapshot;
+///
+/// 对话状态机的状态定义
+///
+pub mod state;
+///
+/// dsl 的标准库，注册字符串、数字、类型判断、随机数与时间等原生函数
+///
+pub mod stdlib;
+///
+/// 按用户持久保存键值数据的存储
+///
+pub mod storage;
+///
+/// 暂停与恢复执行时记录的执行位置
+///
+pub mod suspend;
+///
+/// 标识符驻留，把变量名转换为可以快速比较的符号
+///
+pub mod symbol;
+///
 /// 定义 dsl 的语法树
 ///
 pub mod syntax;
@@ -0,0 +1,197 @@
+use crate::error::Error;
+use crate::object::Object;
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+///
+/// 脚本执行的资源限制，默认不做任何限制
+///
+/// 用量在每次 `interpret` 或 `resume` 时重新计算，对会话来说就是每一轮对话，
+/// 超出限制时返回 `Error::Limit`。
+///
+/// # 使用示例
+/// let interpreter = Interpreter::builder()
+///     .limits(Limits {
+///         statements: Some(10_000),
+///         time: Some(Duration::from_secs(1)),
+///         ..Limits::default()
+///     })
+///     .build();
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// 最多执行的语句数
+    pub statements: Option<u64>,
+    /// 最长运行时间，不含等待用户输入的时间
+    pub time: Option<Duration>,
+    /// 字符串的最大长度，按字节计算
+    pub string_length: Option<usize>,
+    /// 列表和映射的最大元素个数
+    pub collection_length: Option<usize>,
+    /// speak 输出的最大总字节数
+    pub output: Option<usize>,
+}
+
+impl Limits {
+    ///
+    /// 适合运行不受信任脚本的限制，服务模式默认使用
+    ///
+    pub fn sandbox() -> Self {
+        Limits {
+            statements: Some(1_000_000),
+            time: Some(Duration::from_secs(10)),
+            string_length: Some(1024 * 1024),
+            collection_length: Some(100_000),
+            output: Some(1024 * 1024),
+        }
+    }
+}
+
+///
+/// 被超出的资源限制，携带限制的值
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitExceeded {
+    /// 执行的语句数
+    Statements(u64),
+    /// 运行时间
+    Time(Duration),
+    /// 字符串长度
+    StringLength(usize),
+    /// 列表或映射的元素个数
+    CollectionLength(usize),
+    /// 输出的总字节数
+    Output(usize),
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Statements(limit) => {
+                write!(f, "Executed more than {} statements.", limit)
+            }
+            LimitExceeded::Time(limit) => write!(f, "Ran longer than {:?}.", limit),
+            LimitExceeded::StringLength(limit) => {
+                write!(f, "Created a string longer than {} bytes.", limit)
+            }
+            LimitExceeded::CollectionLength(limit) => {
+                write!(f, "Created a collection with more than {} items.", limit)
+            }
+            LimitExceeded::Output(limit) => write!(f, "Spoke more than {} bytes.", limit),
+        }
+    }
+}
+
+///
+/// 按资源限制统计一次执行的用量
+///
+pub(crate) struct Meter {
+    limits: Limits,
+    statements: u64,
+    output: usize,
+    started: Instant,
+    /// 等待输入的时间，不计入运行时间
+    waited: Duration,
+}
+
+impl Meter {
+    pub(crate) fn new(limits: Limits) -> Self {
+        Meter {
+            limits,
+            statements: 0,
+            output: 0,
+            started: Instant::now(),
+            waited: Duration::ZERO,
+        }
+    }
+
+    pub(crate) fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    ///
+    /// 开始新的一次执行，清空用量
+    ///
+    pub(crate) fn reset(&mut self) {
+        *self = Meter::new(self.limits);
+    }
+
+    ///
+    /// 记录执行了一条语句，同时检查运行时间
+    ///
+    pub(crate) fn tick(&mut self) -> Result<(), Error> {
+        self.statements += 1;
+        if let Some(limit) = self.limits.statements {
+            if self.statements > limit {
+                return Err(Error::Limit(LimitExceeded::Statements(limit)));
+            }
+        }
+        if let Some(limit) = self.limits.time {
+            if self.started.elapsed().saturating_sub(self.waited) > limit {
+                return Err(Error::Limit(LimitExceeded::Time(limit)));
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// 距离运行时间上限还剩的时间，没有限制时为 None
+    ///
+    pub(crate) fn time_left(&self) -> Option<Duration> {
+        let used = self.started.elapsed().saturating_sub(self.waited);
+        self.limits.time.map(|limit| limit.saturating_sub(used))
+    }
+
+    ///
+    /// 检查新产生的值的大小，只检查最外层
+    ///
+    pub(crate) fn check(&self, value: &Object) -> Result<(), Error> {
+        let exceeded = match value {
+            Object::String(s) => return self.check_string(s),
+            Object::List(items) => self
+                .limits
+                .collection_length
+                .filter(|&limit| items.len() > limit)
+                .map(LimitExceeded::CollectionLength),
+            Object::Map(entries) => self
+                .limits
+                .collection_length
+                .filter(|&limit| entries.len() > limit)
+                .map(LimitExceeded::CollectionLength),
+            _ => None,
+        };
+        match exceeded {
+            Some(exceeded) => Err(Error::Limit(exceeded)),
+            None => Ok(()),
+        }
+    }
+
+    ///
+    /// 检查字符串长度，用于新产生的字符串和用户输入
+    ///
+    pub(crate) fn check_string(&self, s: &str) -> Result<(), Error> {
+        match self.limits.string_length {
+            Some(limit) if s.len() > limit => Err(Error::Limit(LimitExceeded::StringLength(limit))),
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// 记录一次输出
+    ///
+    pub(crate) fn speak(&mut self, text: &str) -> Result<(), Error> {
+        self.output += text.len();
+        match self.limits.output {
+            Some(limit) if self.output > limit => Err(Error::Limit(LimitExceeded::Output(limit))),
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// 记录从 since 开始等待输入的时间
+    ///
+    pub(crate) fn waited_since(&mut self, since: Instant) {
+        self.waited += since.elapsed();
+    }
+}
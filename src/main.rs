@@ -1,34 +1,94 @@
 use std::fs;
-use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::thread;
 
-use robot_dsl::{error::Error, interpreter::Interpreter, parser::Parser, scanner::Scanner};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use robot_dsl::{
+    channel::Console,
+    debug::{CommandLine, Debugger},
+    error::Error,
+    interpreter::{Interpreter, STACK_SIZE},
+    limits::Limits,
+    parser::Parser,
+    program::Program,
+    repl::{Repl, Reply},
+    scanner::Scanner,
+    server::Server,
+    storage::FileStorage,
+};
 
 struct Dsl {
     interpreter: Interpreter,
+    /// --store 指定的存储文件，REPL 重置时重新使用
+    store: Option<String>,
 }
 
 impl Dsl {
-    fn new() -> Self {
+    fn new(store: Option<String>) -> Self {
         Dsl {
-            interpreter: Interpreter::new(),
+            interpreter: create_interpreter(&store),
+            store,
         }
     }
 
     fn run_file(&mut self, path: &str) -> Result<(), Error> {
         let source = fs::read_to_string(path)?;
+        self.interpreter.set_script_path(Path::new(path))?;
         self.run(source)
     }
 
-    fn run_prompt(&mut self) -> Result<(), Error> {
-        let stdin = io::stdin();
+    fn debug_file(&mut self, path: &str) -> Result<(), Error> {
+        let source = fs::read_to_string(path)?;
+        let frontend = CommandLine::new(Console).source(&source);
+        self.interpreter.set_debug_hook(Debugger::new(frontend));
+        println!("Debugging {}, type help for commands.", path);
+        self.run_file(path)
+    }
+
+    fn run_prompt(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let store = self.store.clone();
+        let mut repl = Repl::new(move || create_interpreter(&store));
+        let mut editor = DefaultEditor::new()?;
+        let history = history_path();
+        if let Some(path) = &history {
+            // 第一次运行时还没有历史文件
+            let _ = editor.load_history(path);
+        }
+        println!("robot-dsl REPL, type :help for help.");
         loop {
-            print!("> ");
-            io::stdout().flush()?;
-            let mut line = String::new();
-            stdin.lock().read_line(&mut line)?;
-            self.run(line)?;
+            let line = match editor.readline(repl.prompt()) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    repl.discard();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(e.into()),
+            };
+            let recorded = repl.history().len();
+            let reply = repl.feed(&line);
+            // 跨多行的输入在完整之后作为一条历史记录
+            if repl.history().len() > recorded {
+                if let Some(entry) = repl.history().last() {
+                    editor.add_history_entry(entry.as_str())?;
+                }
+            }
+            match reply {
+                Reply::More => (),
+                Reply::Print(text) if text.is_empty() => (),
+                Reply::Print(text) => println!("{}", text),
+                Reply::Error(message) if message.is_empty() => (),
+                Reply::Error(message) => eprintln!("{}", message),
+                Reply::Quit => break,
+            }
+        }
+        if let Some(path) = &history {
+            editor.save_history(path)?;
         }
+        Ok(())
     }
 
     fn run(&mut self, source: String) -> Result<(), Error> {
@@ -47,19 +107,117 @@ impl Dsl {
     }
 }
 
+fn create_interpreter(store: &Option<String>) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    if let Some(path) = store {
+        interpreter.set_storage("local", FileStorage::new(path));
+    }
+    interpreter
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| Path::new(&home).join(".robot_dsl_history"))
+}
+
+fn finish(result: Result<(), Error>) {
+    match result {
+        Ok(_) | Err(Error::Exit) => (),
+        Err(Error::Runtime { token, message }) => {
+            eprintln!("[line {}] {}", token.line, message);
+            exit(70)
+        }
+        Err(Error::Native(message)) => {
+            eprintln!("{}", message);
+            exit(70)
+        }
+        Err(Error::Transition(_)) | Err(Error::Suspend) | Err(Error::Cancelled) => exit(70),
+        Err(Error::Limit(exceeded)) => {
+            eprintln!("{}", exceeded);
+            exit(70)
+        }
+        Err(Error::Parse) => exit(65),
+        Err(Error::Io(e)) => {
+            eprintln!("{}", e);
+            exit(74)
+        }
+    }
+}
+
+fn serve(script: &str, port: &str, store: &Option<String>) -> Result<(), Error> {
+    let port: u16 = match port.parse() {
+        Ok(port) => port,
+        Err(_) => {
+            eprintln!("Invalid port '{}'.", port);
+            exit(64)
+        }
+    };
+    let program = Program::from_file(Path::new(script))?;
+    let mut server = Server::bind(("127.0.0.1", port), program)?;
+    server.set_limits(Limits::sandbox());
+    if let Some(path) = store {
+        server.set_storage(FileStorage::new(path));
+    }
+    // 访问令牌不放在命令行上，避免出现在进程列表中
+    if let Some(tokens) = std::env::var_os("ROBOT_DSL_TOKENS") {
+        for pair in tokens
+            .to_string_lossy()
+            .split(',')
+            .filter(|pair| !pair.is_empty())
+        {
+            match pair.split_once('=') {
+                Some((token, user)) if !token.is_empty() && !user.is_empty() => {
+                    server.add_user(token, user)
+                }
+                _ => {
+                    eprintln!("Invalid ROBOT_DSL_TOKENS entry, expected token=user.");
+                    exit(64)
+                }
+            }
+        }
+    }
+    eprintln!("Serving {} on {}", script, server.local_addr()?);
+    server.run()?;
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    // 解释器递归地执行语句，在足够大的栈上运行才能达到默认的调用深度
+    let cli = thread::Builder::new().stack_size(STACK_SIZE).spawn(cli)?;
+    match cli.join() {
+        Ok(result) => Ok(result?),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+fn cli() -> Result<(), String> {
     let args: Vec<String> = std::env::args().collect();
-    let mut dsl = Dsl::new();
+    let (store, args) = match args.as_slice() {
+        [program, flag, path, rest @ ..] if flag == "--store" => (
+            Some(path.clone()),
+            [std::slice::from_ref(program), rest].concat(),
+        ),
+        _ => (None, args),
+    };
+    let mut dsl = Dsl::new(store);
     match args.as_slice() {
-        [_, file] => match dsl.run_file(file) {
-            Ok(_) => (),
-            Err(Error::Runtime { .. }) => exit(70),
-            Err(Error::Parse) => exit(65),
-            Err(Error::Io(_)) => unimplemented!(),
-        },
-        [_] => dsl.run_prompt()?,
+        [_, file] => finish(dsl.run_file(file)),
+        [_, command, script] if command == "debug" => finish(dsl.debug_file(script)),
+        [_, command, script, flag, port] if command == "serve" && flag == "--port" => {
+            match serve(script, port, &dsl.store) {
+                Ok(()) => (),
+                Err(Error::Parse) => exit(65),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(74)
+                }
+            }
+        }
+        [_] => dsl.run_prompt().map_err(|e| e.to_string())?,
         _ => {
-            eprintln!("Usage: robot-dsl [script]");
+            eprintln!("Usage: robot-dsl [--store file.json] [script]");
+            eprintln!("       robot-dsl debug script");
+            eprintln!("       robot-dsl [--store file.json] serve script --port N");
+            eprintln!("       (serve reads access tokens from ROBOT_DSL_TOKENS=token=user,...)");
             exit(64)
         }
     }
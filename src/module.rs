@@ -0,0 +1,132 @@
+use crate::env::Environment;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+///
+/// 已加载的模块，保存模块顶层定义所在的环境
+///
+pub struct Module {
+    /// 模块名，即 import 时的别名
+    pub name: String,
+    /// 模块顶层环境
+    pub environment: Rc<RefCell<Environment>>,
+}
+
+impl fmt::Debug for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+///
+/// 模块加载表，按规范路径缓存已执行过的模块，并记录正在加载的文件以检测循环导入
+///
+#[derive(Default)]
+pub struct Modules {
+    cache: HashMap<PathBuf, Rc<RefCell<Environment>>>,
+    loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    ///
+    /// 创建一个空的模块加载表
+    ///
+    pub fn new() -> Self {
+        Modules {
+            cache: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+
+    ///
+    /// 设置入口脚本，之后的相对路径以它所在目录为基准
+    ///
+    /// # 参数列表
+    /// * path: 入口脚本路径
+    ///
+    pub fn set_entry(&mut self, path: &Path) -> io::Result<()> {
+        self.set_canonical_entry(path.canonicalize()?);
+        Ok(())
+    }
+
+    ///
+    /// 设置已经规范化的入口脚本路径
+    ///
+    pub fn set_canonical_entry(&mut self, canonical: PathBuf) {
+        self.loading.clear();
+        self.loading.push(canonical);
+    }
+
+    ///
+    /// 将 import 中的路径解析为规范路径，相对路径以当前正在执行的文件为基准
+    ///
+    /// # 参数列表
+    /// * path: import 语句中的路径
+    ///
+    /// # 返回值
+    /// * 规范路径
+    /// * 文件不存在等错误
+    ///
+    pub fn resolve(&self, path: &str) -> io::Result<PathBuf> {
+        let base = match self.loading.last().and_then(|file| file.parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::from("."),
+        };
+        base.join(path).canonicalize()
+    }
+
+    ///
+    /// 得到已缓存的模块环境
+    ///
+    pub fn get(&self, path: &Path) -> Option<Rc<RefCell<Environment>>> {
+        self.cache.get(path).cloned()
+    }
+
+    ///
+    /// 遍历全部已缓存的模块环境
+    ///
+    pub fn environments(&self) -> impl Iterator<Item = &Rc<RefCell<Environment>>> {
+        self.cache.values()
+    }
+
+    ///
+    /// 开始加载一个模块
+    ///
+    /// # 返回值
+    /// * 若该模块正在加载中，返回描述导入环的字符串
+    ///
+    pub fn enter(&mut self, path: &Path) -> Result<(), String> {
+        if let Some(start) = self.loading.iter().position(|file| file == path) {
+            let cycle: Vec<String> = self.loading[start..]
+                .iter()
+                .chain(std::iter::once(&path.to_path_buf()))
+                .map(|file| display_name(file))
+                .collect();
+            return Err(cycle.join(" -> "));
+        }
+        self.loading.push(path.to_path_buf());
+        Ok(())
+    }
+
+    ///
+    /// 结束加载一个模块，加载成功时缓存其环境
+    ///
+    pub fn leave(&mut self, environment: Option<Rc<RefCell<Environment>>>) {
+        if let Some(path) = self.loading.pop() {
+            if let Some(environment) = environment {
+                self.cache.insert(path, environment);
+            }
+        }
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
@@ -1,4 +1,10 @@
 use crate::function::Function;
+use crate::module::Module;
+use crate::state::State;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
 
 ///
 /// 定义 dsl 对象的枚举类型
@@ -9,8 +15,16 @@ pub enum Object {
     Boolean(bool),
     /// 函数
     Callable(Function),
+    /// 列表，由标准库函数（如 split）产生
+    List(Rc<Vec<Object>>),
+    /// 映射，由标准库函数（如 matches）产生，通过 `.` 访问成员
+    Map(Rc<BTreeMap<String, Object>>),
+    /// 以别名导入的模块
+    Module(Rc<Module>),
     /// 空值
     Null,
+    /// 对话状态机的状态
+    State(Rc<State>),
     /// 数字
     Number(f64),
     /// 字符串
@@ -35,7 +49,69 @@ impl Object {
             (Object::Boolean(left), Object::Boolean(right)) => left == right,
             (Object::Number(left), Object::Number(right)) => left == right,
             (Object::String(left), Object::String(right)) => left.eq(right),
+            (Object::State(left), Object::State(right)) => Rc::ptr_eq(left, right),
+            (Object::List(left), Object::List(right)) => {
+                left.len() == right.len() && left.iter().zip(right.iter()).all(|(l, r)| l.equals(r))
+            }
+            (Object::Map(left), Object::Map(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right.iter())
+                        .all(|((lk, lv), (rk, rv))| lk == rk && lv.equals(rv))
+            }
             _ => false,
         }
     }
+
+    ///
+    /// 得到对象的类型名，用于报错信息和 type_of 函数
+    ///
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Boolean(_) => "bool",
+            Object::Callable(_) => "step",
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::Module(_) => "module",
+            Object::Null => "nil",
+            Object::State(_) => "state",
+            Object::Number(_) => "number",
+            Object::String(_) => "string",
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Object::Null => write!(f, "nil"),
+            Object::Number(n) => write!(f, "{}", n),
+            Object::Boolean(b) => write!(f, "{}", b),
+            Object::Callable(function) => write!(f, "{}", function),
+            Object::Module(module) => write!(f, "<module {}>", module.name),
+            Object::State(state) => write!(f, "{}", state),
+            Object::String(s) => write!(f, "{}", s),
+            Object::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Object::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
 }
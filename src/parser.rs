@@ -1,13 +1,16 @@
 use crate::error::{parser_error, Error};
-use crate::syntax::{Expr, LiteralValue, Stmt};
+use crate::syntax::{Expr, IntentArm, LiteralValue, MenuOption, Stmt};
 use crate::token::{Token, TokenType};
 
+use std::sync::Arc;
+
 ///
 /// 定义 dsl 的解析器
 ///
 pub struct Parser<'t> {
     pub tokens: &'t Vec<Token>,
     current: usize,
+    had_error: bool,
 }
 
 macro_rules! matches {
@@ -28,7 +31,11 @@ impl<'t> Parser<'t> {
     /// 创建一个解析器
     ///
     pub fn new(tokens: &'t Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            had_error: false,
+        }
     }
 
     ///
@@ -38,6 +45,19 @@ impl<'t> Parser<'t> {
         self.expression().ok()
     }
 
+    ///
+    /// 若全部输入是单个表达式（末尾的分号可有可无），解析出该表达式，供 REPL 显示它的值
+    ///
+    /// # 返回值
+    /// * 表达式，输入在表达式之后还有其他内容时为 None
+    /// * 错误，表达式本身有语法错误时返回
+    ///
+    pub fn single_expression(&mut self) -> Result<Option<Expr>, Error> {
+        let expression = self.expression()?;
+        matches!(self, TokenType::SemiColon);
+        Ok(self.is_at_end().then_some(expression))
+    }
+
     ///
     /// 解析 dsl 语句
     ///
@@ -67,18 +87,24 @@ impl<'t> Parser<'t> {
         while !self.is_at_end() {
             statements.push(self.declaration()?);
         }
+        if self.had_error {
+            return Err(Error::Parse);
+        }
         Ok(statements)
     }
 
     fn declaration(&mut self) -> Result<Stmt, Error> {
         let statement = if matches!(self, TokenType::Var) {
             self.var_declaration()
+        } else if matches!(self, TokenType::Const) {
+            self.const_declaration()
         } else {
             self.statement()
         };
 
         match statement {
             Err(Error::Parse) => {
+                self.had_error = true;
                 self.synchronize();
                 Ok(Stmt::Null)
             }
@@ -103,6 +129,16 @@ impl<'t> Parser<'t> {
             self.exit_statement()
         } else if matches!(self, TokenType::Inputn) {
             self.inputn_statement()
+        } else if matches!(self, TokenType::Import) {
+            self.import_statement()
+        } else if matches!(self, TokenType::Match) {
+            self.match_statement()
+        } else if matches!(self, TokenType::Menu) {
+            self.menu_statement()
+        } else if matches!(self, TokenType::State) {
+            self.state_declaration()
+        } else if matches!(self, TokenType::Goto) {
+            self.goto_statement()
         } else if matches!(self, TokenType::LeftBrace) {
             Ok(Stmt::Block {
                 statements: self.block()?,
@@ -113,7 +149,158 @@ impl<'t> Parser<'t> {
     }
 
     fn exit_statement(&mut self) -> Result<Stmt, Error> {
-        Ok(Stmt::Exit)
+        let keyword = self.previous().clone();
+        matches!(self, TokenType::SemiColon);
+        Ok(Stmt::Exit { keyword })
+    }
+
+    fn import_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
+        let path = match &self.peek().tpe {
+            TokenType::String { literal } => literal.clone(),
+            _ => return Err(self.error(self.peek(), "Expect module path string.")),
+        };
+        self.advance();
+
+        let alias = if matches!(self, TokenType::As) {
+            Some(self.consume(TokenType::Identifier, "Expect module alias after 'as'.")?)
+        } else {
+            None
+        };
+        self.consume(TokenType::SemiColon, "Expect ';' after import.")?;
+        Ok(Stmt::Import {
+            keyword,
+            path,
+            alias,
+        })
+    }
+
+    fn match_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::Intent, "Expect 'intent' after 'match'.")?;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'intent'.")?;
+        let subject = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after intent subject.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before intent arms.")?;
+
+        let mut arms: Vec<IntentArm> = Vec::new();
+        let mut fallback: Option<Box<Stmt>> = None;
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            if self.check(TokenType::Identifier) && self.peek().lexeme == "_" {
+                self.advance();
+                self.consume(TokenType::Arrow, "Expect '=>' after '_'.")?;
+                fallback = Some(Box::new(self.statement()?));
+                continue;
+            }
+
+            arms.push(self.intent_arm()?);
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after intent arms.")?;
+
+        Ok(Stmt::Match {
+            keyword,
+            subject,
+            arms,
+            fallback,
+        })
+    }
+
+    fn menu_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
+        let prompt = if self.check(TokenType::LeftBrace) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::LeftBrace, "Expect '{' before menu options.")?;
+
+        let mut options: Vec<MenuOption> = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let keys = self.patterns("option")?;
+            let label = match &self.peek().tpe {
+                TokenType::String { literal } => Some(literal.clone()),
+                _ => None,
+            };
+            if label.is_some() {
+                self.advance();
+            }
+            self.consume(TokenType::Arrow, "Expect '=>' after option.")?;
+            let body = self.statement()?;
+            options.push(MenuOption { keys, label, body });
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after menu options.")?;
+        if options.is_empty() {
+            return Err(self.error(&keyword, "Expect at least one menu option."));
+        }
+
+        Ok(Stmt::Menu {
+            keyword,
+            prompt,
+            options,
+        })
+    }
+
+    fn state_declaration(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume(TokenType::Identifier, "Expect state name.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before state body.")?;
+
+        let mut enter: Option<Vec<Stmt>> = None;
+        let mut handlers: Vec<IntentArm> = Vec::new();
+        let mut fallback: Option<Arc<Stmt>> = None;
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            self.consume(TokenType::On, "Expect 'on' in state body.")?;
+            if self.check(TokenType::Identifier) && self.peek().lexeme == "enter" {
+                let token = self.advance().clone();
+                if enter.is_some() {
+                    return Err(self.error(&token, "A state can only have one 'on enter' block."));
+                }
+                self.consume(TokenType::LeftBrace, "Expect '{' after 'on enter'.")?;
+                enter = Some(self.block()?);
+            } else if self.check(TokenType::Identifier) && self.peek().lexeme == "_" {
+                self.advance();
+                self.consume(TokenType::Arrow, "Expect '=>' after '_'.")?;
+                fallback = Some(Arc::new(self.statement()?));
+            } else {
+                handlers.push(self.intent_arm()?);
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after state body.")?;
+
+        Ok(Stmt::State {
+            name,
+            enter: enter.unwrap_or_default().into(),
+            handlers: handlers.into(),
+            fallback,
+        })
+    }
+
+    fn goto_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
+        let target = self.consume(TokenType::Identifier, "Expect state name after 'goto'.")?;
+        self.consume(TokenType::SemiColon, "Expect ';' after goto.")?;
+        Ok(Stmt::Goto { keyword, target })
+    }
+
+    fn intent_arm(&mut self) -> Result<IntentArm, Error> {
+        let patterns = self.patterns("intent")?;
+        self.consume(TokenType::Arrow, "Expect '=>' after intent.")?;
+        let body = self.statement()?;
+        Ok(IntentArm { patterns, body })
+    }
+
+    fn patterns(&mut self, kind: &str) -> Result<Vec<String>, Error> {
+        let mut patterns: Vec<String> = Vec::new();
+        loop {
+            match &self.peek().tpe {
+                TokenType::String { literal } => patterns.push(literal.clone()),
+                _ => return Err(self.error(self.peek(), &format!("Expect {} string.", kind))),
+            }
+            self.advance();
+            if !matches!(self, TokenType::Pipe) {
+                break;
+            }
+        }
+        Ok(patterns)
     }
 
     fn inputn_statement(&mut self) -> Result<Stmt, Error> {
@@ -139,6 +326,9 @@ impl<'t> Parser<'t> {
                     self.error(self.peek(), "Cannot have more than 255 parameters.");
                 }
                 params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+                if !matches!(self, TokenType::Comma) {
+                    break;
+                }
             }
         }
         self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
@@ -148,26 +338,40 @@ impl<'t> Parser<'t> {
             format!("Expect '{{' before {} body.", kind).as_str(),
         )?;
         let body = self.block()?;
-        Ok(Stmt::Function { name, params, body })
+        Ok(Stmt::Function {
+            name,
+            params: params.into(),
+            body: body.into(),
+        })
     }
 
     fn loop_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
         let body = Box::new(self.statement()?);
-        Ok(Stmt::Loop { body })
+        Ok(Stmt::Loop { keyword, body })
     }
 
     fn branch_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expect '(' after 'branch'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after branch condition.")?;
         let then = Box::new(self.statement()?);
-        Ok(Stmt::Branch { condition, then })
+        Ok(Stmt::Branch {
+            keyword,
+            condition,
+            then,
+        })
     }
 
     fn speak_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
         let value = self.expression()?;
         self.consume(TokenType::SemiColon, "Expect ';' after value.")?;
-        Ok(Stmt::Speak { expression: value })
+        Ok(Stmt::Speak {
+            keyword,
+            expression: value,
+        })
     }
 
     fn input_statement(&mut self) -> Result<Stmt, Error> {
@@ -178,9 +382,10 @@ impl<'t> Parser<'t> {
     }
 
     fn listen_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous().clone();
         let time = self.expression()?;
         self.consume(TokenType::SemiColon, "Expect ';' after time.")?;
-        Ok(Stmt::Listen { time })
+        Ok(Stmt::Listen { keyword, time })
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, Error> {
@@ -199,6 +404,17 @@ impl<'t> Parser<'t> {
         Ok(Stmt::Var { name, initializer })
     }
 
+    fn const_declaration(&mut self) -> Result<Stmt, Error> {
+        let name = self.consume(TokenType::Identifier, "Expect constant name.")?;
+        self.consume(TokenType::Equal, "Expect '=' after constant name.")?;
+        let initializer = self.expression()?;
+        self.consume(
+            TokenType::SemiColon,
+            "Expect ';' after constant declaration.",
+        )?;
+        Ok(Stmt::Const { name, initializer })
+    }
+
     fn expression_statement(&mut self) -> Result<Stmt, Error> {
         let expr = self.expression()?;
         self.consume(TokenType::SemiColon, "Expect ';' after expression.")?;
@@ -288,6 +504,12 @@ impl<'t> Parser<'t> {
         loop {
             if matches!(self, TokenType::LeftParen) {
                 expr = self.finish_call(expr)?;
+            } else if matches!(self, TokenType::Dot) {
+                let name = self.consume(TokenType::Identifier, "Expect member name after '.'.")?;
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
             } else {
                 break;
             }
@@ -305,6 +527,9 @@ impl<'t> Parser<'t> {
                     self.error(self.peek(), "Cannot have more than 255 arguments.");
                 }
                 arguments.push(self.expression()?);
+                if !matches!(self, TokenType::Comma) {
+                    break;
+                }
             }
         }
 
@@ -328,7 +553,7 @@ impl<'t> Parser<'t> {
                 value: LiteralValue::Null,
             },
             TokenType::Number { literal } => Expr::Literal {
-                value: LiteralValue::Number(literal.clone()),
+                value: LiteralValue::Number(*literal),
             },
             TokenType::String { literal } => Expr::Literal {
                 value: LiteralValue::String(literal.clone()),
@@ -399,12 +624,17 @@ impl<'t> Parser<'t> {
 
             match self.peek().tpe {
                 TokenType::Var
+                | TokenType::Const
                 | TokenType::Branch
                 | TokenType::Exit
                 | TokenType::Input
                 | TokenType::Listen
                 | TokenType::Speak
                 | TokenType::Loop
+                | TokenType::Import
+                | TokenType::Match
+                | TokenType::Menu
+                | TokenType::State
                 | TokenType::Step => return,
                 _ => self.advance(),
             };
@@ -0,0 +1,133 @@
+use crate::error::Error;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::symbol::Symbol;
+use crate::syntax::Stmt;
+use crate::token::{Token, TokenType};
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+///
+/// 编译好的 dsl 程序，只读，可以由多个会话共享
+///
+/// 程序只在创建时扫描和解析一次，之后每个会话直接执行其中的语句。
+///
+/// # 使用示例
+/// let program = Arc::new(Program::from_file(Path::new("example/charge.txt"))?);
+/// let mut alice = Session::new(Arc::clone(&program));
+/// let mut bob = Session::new(Arc::clone(&program));
+///
+#[derive(Clone)]
+pub struct Program {
+    statements: Vec<Stmt>,
+    steps: Vec<String>,
+    names: HashSet<Symbol>,
+    path: Option<PathBuf>,
+    fingerprint: u64,
+}
+
+impl Program {
+    ///
+    /// 编译源代码
+    ///
+    /// # 参数列表
+    /// * source: dsl 源代码
+    ///
+    /// # 返回值
+    /// * 编译好的程序
+    /// * 语法错误，具体信息已输出到标准错误
+    ///
+    pub fn compile(source: &str) -> Result<Self, Error> {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens();
+        let fingerprint = fingerprint(tokens);
+        let statements = Parser::new(tokens).parse()?;
+        let steps = statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Stmt::Function { name, .. } => Some(name.lexeme.clone()),
+                _ => None,
+            })
+            .collect();
+        let names = tokens
+            .iter()
+            .filter(|token| token.tpe == TokenType::Identifier)
+            .map(|token| token.symbol)
+            .collect();
+        Ok(Program {
+            statements,
+            steps,
+            names,
+            path: None,
+            fingerprint,
+        })
+    }
+
+    ///
+    /// 读取并编译脚本文件，import 的相对路径以该文件所在目录为基准
+    ///
+    /// # 参数列表
+    /// * path: 脚本路径
+    ///
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let source = fs::read_to_string(path)?;
+        let mut program = Self::compile(&source)?;
+        program.path = Some(path.canonicalize()?);
+        Ok(program)
+    }
+
+    ///
+    /// 程序顶层的语句
+    ///
+    pub fn statements(&self) -> &[Stmt] {
+        &self.statements
+    }
+
+    ///
+    /// 程序顶层声明的 step 名称，按声明顺序排列
+    ///
+    pub fn steps(&self) -> &[String] {
+        &self.steps
+    }
+
+    ///
+    /// 程序中是否出现过该标识符，变量、常量、参数和 step 的名字都在其中
+    ///
+    /// # 参数列表
+    /// * name: 标识符的符号
+    ///
+    pub fn mentions(&self, name: Symbol) -> bool {
+        self.names.contains(&name)
+    }
+
+    ///
+    /// 脚本文件路径，由源代码直接编译时为 None
+    ///
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    ///
+    /// 程序的指纹，由词法单元序列决定，用于检查快照是否属于该程序
+    ///
+    /// 只改动注释、空白或换行时指纹不变，改动任何代码时指纹都会变化。
+    ///
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+}
+
+///
+/// 计算词法单元序列的 FNV-1a 散列值，与编译器版本和运行平台无关
+///
+fn fingerprint(tokens: &[Token]) -> u64 {
+    tokens
+        .iter()
+        // 每个词素后加一个 0 字节作为分隔，避免相邻词素拼接后相同
+        .flat_map(|token| token.lexeme.bytes().chain(std::iter::once(0)))
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
@@ -0,0 +1,273 @@
+use crate::error::Error;
+use crate::function::Function;
+use crate::interpreter::Interpreter;
+use crate::object::Object;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::TokenType;
+
+use std::fs;
+use std::path::Path;
+
+const HELP: &str = "\
+Enter statements or expressions; blocks and strings may span several lines.
+Expression values are printed, nil results are not.
+
+:help          show this message
+:env           list the variables defined so far
+:load <file>   run a script in the current session
+:history       list the inputs of this session
+:reset         forget all variables and steps
+:quit          leave the REPL (Ctrl-D works too)";
+
+///
+/// 处理一次输入后 REPL 的回应
+///
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    /// 输入还不完整，需要继续读入下一行
+    More,
+    /// 需要显示给用户的文本，没有内容时为空字符串
+    Print(String),
+    /// 出错，之后可以继续输入。语法错误时不执行任何语句，
+    /// 运行时错误之前已经执行的语句的效果会保留
+    Error(String),
+    /// 用户要求退出，或脚本执行了 exit 语句
+    Quit,
+}
+
+///
+/// 交互式解释器
+///
+/// 逐行接收输入，语句块或字符串没有结束时继续读入下一行，
+/// 出错后继续工作，并记录每次完整的输入。
+/// REPL 本身不读写终端，由命令行负责行编辑和历史文件。
+///
+/// # 使用示例
+/// let mut repl = Repl::new(Interpreter::new);
+/// assert_eq!(repl.feed("var x = 1;"), Reply::Print(String::new()));
+/// assert_eq!(repl.feed("x + 1"), Reply::Print("2".to_string()));
+///
+pub struct Repl {
+    make: Box<dyn Fn() -> Interpreter>,
+    interpreter: Interpreter,
+    /// 尚未完整的多行输入
+    buffer: String,
+    history: Vec<String>,
+}
+
+impl Repl {
+    ///
+    /// 创建 REPL
+    ///
+    /// # 参数列表
+    /// * make: 创建解释器的函数，`:reset` 时再次调用，可以带有宿主的配置
+    ///
+    pub fn new<F: Fn() -> Interpreter + 'static>(make: F) -> Self {
+        Repl {
+            interpreter: make(),
+            make: Box::new(make),
+            buffer: String::new(),
+            history: Vec::new(),
+        }
+    }
+
+    ///
+    /// 当前应显示的提示符，多行输入的后续行使用不同的提示符
+    ///
+    pub fn prompt(&self) -> &'static str {
+        if self.buffer.is_empty() {
+            "> "
+        } else {
+            ".. "
+        }
+    }
+
+    ///
+    /// 本次会话中完整的输入，按输入顺序排列
+    ///
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    ///
+    /// 丢弃尚未完整的多行输入，例如用户按下 Ctrl-C 时
+    ///
+    pub fn discard(&mut self) {
+        self.buffer.clear();
+    }
+
+    ///
+    /// 处理一行输入
+    ///
+    /// # 参数列表
+    /// * line: 用户输入的一行，不含换行符
+    ///
+    pub fn feed(&mut self, line: &str) -> Reply {
+        if self.buffer.is_empty() && line.trim_start().starts_with(':') {
+            let command = line.trim().to_string();
+            self.history.push(command.clone());
+            return self.command(&command);
+        }
+
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        if !is_complete(&self.buffer) {
+            return Reply::More;
+        }
+        let source = std::mem::take(&mut self.buffer);
+        if source.trim().is_empty() {
+            return Reply::Print(String::new());
+        }
+        self.history.push(source.trim_end().to_string());
+        self.evaluate(source)
+    }
+
+    fn command(&mut self, command: &str) -> Reply {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+        match (name, argument) {
+            (":help", _) => Reply::Print(HELP.to_string()),
+            (":env", _) => Reply::Print(self.environment()),
+            (":history", _) => Reply::Print(
+                self.history
+                    .iter()
+                    .enumerate()
+                    .map(|(number, input)| format!("{:>4}  {}", number + 1, input))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            (":load", "") => Reply::Error("Usage: :load <file>".to_string()),
+            (":load", path) => self.load(path),
+            (":reset", _) => {
+                self.interpreter = (self.make)();
+                Reply::Print("Session reset.".to_string())
+            }
+            (":quit", _) | (":q", _) => Reply::Quit,
+            _ => Reply::Error(format!("Unknown command '{}'. Type :help for help.", name)),
+        }
+    }
+
+    fn load(&mut self, path: &str) -> Reply {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => return Reply::Error(format!("Cannot read '{}': {}", path, e)),
+        };
+        if let Err(error) = self.interpreter.set_script_path(Path::new(path)) {
+            return Reply::Error(describe(&error));
+        }
+        self.run(source)
+    }
+
+    fn evaluate(&mut self, source: String) -> Reply {
+        let mut scanner = Scanner::new(source.clone());
+        let tokens = scanner.scan_tokens();
+        let statement_start = matches!(
+            tokens.first().map(|token| &token.tpe),
+            Some(
+                TokenType::Speak
+                    | TokenType::Listen
+                    | TokenType::Inputn
+                    | TokenType::Branch
+                    | TokenType::Loop
+                    | TokenType::Step
+                    | TokenType::Exit
+                    | TokenType::Input
+                    | TokenType::Var
+                    | TokenType::Const
+                    | TokenType::Import
+                    | TokenType::Match
+                    | TokenType::Menu
+                    | TokenType::State
+                    | TokenType::Goto
+                    | TokenType::LeftBrace
+            )
+        );
+        if !statement_start {
+            match Parser::new(tokens).single_expression() {
+                Ok(Some(expression)) => {
+                    return match self.interpreter.evaluate_expression(&expression) {
+                        Ok(Object::Null) => Reply::Print(String::new()),
+                        Ok(Object::String(text)) => Reply::Print(text),
+                        Ok(value) => Reply::Print(value.to_string()),
+                        Err(Error::Exit) => Reply::Quit,
+                        Err(error) => Reply::Error(describe(&error)),
+                    };
+                }
+                // 语法错误已由解析器报告
+                Err(_) => return Reply::Error(String::new()),
+                Ok(None) => (),
+            }
+        }
+        self.run(source)
+    }
+
+    fn run(&mut self, source: String) -> Reply {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+        let statements = match Parser::new(tokens).parse() {
+            Ok(statements) => statements,
+            Err(_) => return Reply::Error(String::new()),
+        };
+        match self.interpreter.interpret(&statements) {
+            Ok(()) if self.interpreter.has_exited() => Reply::Quit,
+            Ok(()) => Reply::Print(String::new()),
+            Err(error) => Reply::Error(describe(&error)),
+        }
+    }
+
+    fn environment(&self) -> String {
+        let globals = self.interpreter.globals.borrow();
+        let mut entries: Vec<String> = globals
+            .entries()
+            .filter(|(_, value)| !matches!(value, Object::Callable(Function::Native { .. })))
+            .map(|(name, value)| match value {
+                Object::String(s) => format!("{} = {:?}", name, s),
+                other => format!("{} = {}", name, other),
+            })
+            .collect();
+        entries.sort();
+        entries.join("\n")
+    }
+}
+
+fn describe(error: &Error) -> String {
+    match error {
+        Error::Runtime { token, message } => format!("[line {}] {}", token.line, message),
+        other => other.to_string(),
+    }
+}
+
+///
+/// 输入是否完整：括号都已闭合，字符串和块注释都已结束
+///
+fn is_complete(source: &str) -> bool {
+    let mut depth: i32 = 0;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if !chars.by_ref().any(|c| c == '"') => return false,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                let closed = chars.by_ref().any(|c| {
+                    let end = previous == '*' && c == '/';
+                    previous = c;
+                    end
+                });
+                if !closed {
+                    return false;
+                }
+            }
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            _ => (),
+        }
+    }
+    depth <= 0
+}
@@ -5,8 +5,8 @@ use crate::token::{Token, TokenType, KEYWORDS};
 /// scanner 类型，对源代码进行扫描和初步词法分析处理
 ///
 pub struct Scanner {
-    /// 源代码
-    source: String,
+    /// 源代码，按字符存储以支持中文等多字节字符
+    source: Vec<char>,
     /// 扫描后结果存储的位置
     tokens: Vec<Token>,
     /// 扫描的起始位置
@@ -23,7 +23,7 @@ impl Scanner {
     ///
     pub fn new(source: String) -> Self {
         Scanner {
-            source,
+            source: source.chars().collect(),
             tokens: Vec::new(),
             start: 0,
             current: 0,
@@ -59,6 +59,9 @@ impl Scanner {
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            ',' => self.add_token(TokenType::Comma),
+            '|' => self.add_token(TokenType::Pipe),
+            '.' => self.add_token(TokenType::Dot),
             '-' => self.add_token(TokenType::Minus),
             '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::SemiColon),
@@ -72,6 +75,8 @@ impl Scanner {
             '=' => {
                 if self.match_char('=') {
                     self.add_token(TokenType::EqualEqual);
+                } else if self.match_char('>') {
+                    self.add_token(TokenType::Arrow);
                 } else {
                     self.add_token(TokenType::Equal);
                 }
@@ -90,9 +95,9 @@ impl Scanner {
             '\n' => self.line += 1,
             '"' => self.string(),
             c => {
-                if c.is_digit(10) {
+                if c.is_ascii_digit() {
                     self.number();
-                } else if c.is_alphabetic() {
+                } else if c.is_alphabetic() || c == '_' {
                     self.identifier();
                 } else {
                     error(self.line, "Unexpected character.");
@@ -107,34 +112,32 @@ impl Scanner {
         }
 
         // See if the identifier is a reserved word.
-        let text = self
-            .source
-            .get(self.start..self.current)
-            .expect("Unexpected end.");
+        let text = self.text(self.start, self.current);
 
-        let tpe: TokenType = KEYWORDS.get(text).cloned().unwrap_or(TokenType::Identifier);
+        let tpe: TokenType = KEYWORDS
+            .get(text.as_str())
+            .cloned()
+            .unwrap_or(TokenType::Identifier);
         self.add_token(tpe);
     }
 
     fn number(&mut self) {
-        while self.peek().is_digit(10) {
+        while self.peek().is_ascii_digit() {
             self.advance();
         }
 
         // Look for a fractional part.
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             // Consumer the ".".
             self.advance();
 
-            while self.peek().is_digit(10) {
+            while self.peek().is_ascii_digit() {
                 self.advance();
             }
         }
 
         let n: f64 = self
-            .source
-            .get(self.start..self.current)
-            .expect("Unexpected end.")
+            .text(self.start, self.current)
             .parse()
             .expect("Scanned number could not be parsed.");
         self.add_token(TokenType::Number { literal: n })
@@ -151,17 +154,14 @@ impl Scanner {
         // Unterminated string.
         if self.is_at_end() {
             error(self.line, "Unterminated string.");
+            return;
         }
 
         // The closing ".
         self.advance();
 
         // Trim the surrounding quotes.
-        let literal = self
-            .source
-            .get((self.start + 1)..(self.current - 1))
-            .expect("Unexpected end.")
-            .to_string();
+        let literal = self.text(self.start + 1, self.current - 1);
         self.add_token(TokenType::String { literal });
     }
 
@@ -193,13 +193,7 @@ impl Scanner {
         if self.is_at_end() {
             return false;
         }
-        if self
-            .source
-            .chars()
-            .nth(self.current)
-            .expect("Unexpected end of source.")
-            != expected
-        {
+        if self.source[self.current] != expected {
             return false;
         }
 
@@ -208,17 +202,16 @@ impl Scanner {
     }
 
     fn peek(&self) -> char {
-        self.source.chars().nth(self.current).unwrap_or('\0')
+        self.source.get(self.current).copied().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        self.source.chars().nth(self.current + 1).unwrap_or('\0')
+        self.source.get(self.current + 1).copied().unwrap_or('\0')
     }
 
     fn advance(&mut self) -> char {
         self.current += 1;
-        let char_vec: Vec<char> = self.source.chars().collect();
-        char_vec[self.current - 1]
+        self.source[self.current - 1]
     }
 
     fn is_at_end(&self) -> bool {
@@ -226,11 +219,12 @@ impl Scanner {
     }
 
     fn add_token(&mut self, tpe: TokenType) {
-        let text = self
-            .source
-            .get(self.start..self.current)
-            .expect("Source token is empty.");
-        self.tokens.push(Token::new(tpe, text, self.line))
+        let text = self.text(self.start, self.current);
+        self.tokens.push(Token::new(tpe, &text, self.line))
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        self.source[start..end].iter().collect()
     }
 }
 
@@ -258,16 +252,17 @@ mod test {
 
     #[test]
     fn test_scan_tokens_operators() {
-        let source = "!= == = + -".to_string();
+        let source = "!= == = + - ,".to_string();
         let mut scanner = Scanner::new(source);
         let tokens = scanner.scan_tokens();
-        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens.len(), 7);
         assert_eq!(tokens[0].tpe, TokenType::BangEqual);
         assert_eq!(tokens[1].tpe, TokenType::EqualEqual);
         assert_eq!(tokens[2].tpe, TokenType::Equal);
         assert_eq!(tokens[3].tpe, TokenType::Plus);
         assert_eq!(tokens[4].tpe, TokenType::Minus);
-        assert_eq!(tokens[5].tpe, TokenType::EOF);
+        assert_eq!(tokens[5].tpe, TokenType::Comma);
+        assert_eq!(tokens[6].tpe, TokenType::EOF);
     }
 
     #[test]
@@ -393,6 +388,33 @@ mod test {
         assert_eq!(tokens[48].tpe, TokenType::EOF);
     }
 
+    #[test]
+    fn test_scan_intent_tokens() {
+        let source =
+            "match intent (str) { \"查询余额\" | \"ｂａｌａｎｃｅ\" => Billing(); _ => Help(); }";
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens();
+        assert_eq!(tokens.len(), 22);
+        assert_eq!(tokens[0].tpe, TokenType::Match);
+        assert_eq!(tokens[1].tpe, TokenType::Intent);
+        assert_eq!(
+            tokens[6].tpe,
+            TokenType::String {
+                literal: "查询余额".to_string()
+            }
+        );
+        assert_eq!(tokens[7].tpe, TokenType::Pipe);
+        assert_eq!(
+            tokens[8].tpe,
+            TokenType::String {
+                literal: "ｂａｌａｎｃｅ".to_string()
+            }
+        );
+        assert_eq!(tokens[9].tpe, TokenType::Arrow);
+        assert_eq!(tokens[14].tpe, TokenType::Identifier);
+        assert_eq!(tokens[14].lexeme, "_");
+    }
+
     /*
     #[test]
     fn test_scan_chinese() {
@@ -0,0 +1,703 @@
+use crate::cancel::CancelToken;
+use crate::channel::Channel;
+use crate::error::Error;
+use crate::http::{self, Request};
+use crate::interpreter::{Interpreter, STACK_SIZE};
+use crate::limits::Limits;
+use crate::program::Program;
+use crate::session::{Session, Status, Turn};
+use crate::storage::{MemoryStorage, Storage};
+
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message as Frame, WebSocket};
+
+/// 新连接在这段时间内发来 HTTP 请求行时按 HTTP 处理，否则按行协议处理
+const SNIFF_TIMEOUT: Duration = Duration::from_millis(200);
+/// 会话在这段时间内没有收到消息时被丢弃
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// 读取一个 HTTP 请求的最长时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// 行协议中一行输入的最大长度
+const MAX_LINE: usize = 64 * 1024;
+/// 拒绝格式错误的请求后最多再读取的字节数
+const MAX_DRAIN: u64 = 1024 * 1024;
+/// 连接上有未读数据时，再次检查连接是否关闭前等待的时间
+const WATCH_INTERVAL: Duration = Duration::from_millis(50);
+/// 默认的并发会话上限
+pub const DEFAULT_MAX_SESSIONS: usize = 64;
+/// 会话数达到上限时给客户端的答复
+const BUSY: &str = "Too many sessions, try again later.";
+/// 识别 HTTP 请求时检查的请求方法
+const METHODS: [&str; 6] = ["GET ", "POST ", "PUT ", "DELETE ", "HEAD ", "OPTIONS "];
+
+///
+/// 服务模式，把一个 dsl 程序开放给多个并发的客户端
+///
+/// 同一个端口同时支持三种协议：
+/// - 行协议：每个 TCP 连接是一个会话，speak 的输出逐行写回，客户端发来的每一行作为一次输入
+/// - HTTP：`POST /sessions` 创建会话，`POST /sessions/{id}` 发送 `{"text": ...}`，
+///   `DELETE /sessions/{id}` 结束会话，响应为带有本轮输出的 JSON
+/// - WebSocket：连接 `/ws`，每个连接是一个会话。speak 的输出以
+///   `{"type": "speak", "text": ..., "timestamp": ...}` 发送，timestamp 为毫秒级 Unix 时间；
+///   程序结束时发送 type 为 `end` 的消息，出错时发送 type 为 `error` 的消息。
+///   客户端发来的文本消息可以是纯文本，也可以是 `{"type": "message", "text": ...}`
+///
+/// 解释器不能跨线程共享，因此每个会话在自己的线程中运行，所有会话共享同一份程序。
+/// 会话线程的栈很大，同时存在的会话数不超过 `set_max_sessions` 设置的上限，
+/// 达到上限时新的 HTTP 和 WebSocket 会话得到 503，行协议的连接收到一行说明后被关闭。
+/// 连接在 `IDLE_TIMEOUT` 内没有发来输入时被关闭；
+/// 行协议和 WebSocket 的客户端断开连接时，会话立即被取消，正在执行的循环也会停止。
+///
+/// store 和 load 按用户区分数据，用户只由服务端配置的访问令牌确定（见 `add_user`）：
+/// HTTP 会话在创建时以 `Authorization: Bearer <token>` 携带令牌，
+/// WebSocket 会话还可以用 `/ws?token=...`，令牌未知时返回 401。
+/// 客户端直接给出的用户标识（请求体中的 `user` 或 `?user=`）会被拒绝。
+/// 没有携带令牌的会话以及行协议的会话是匿名的，各自使用私有的内存存储。
+///
+/// # 使用示例
+/// let program = Program::from_file(Path::new("example/charge.txt"))?;
+/// let server = Server::bind("127.0.0.1:7878", program)?;
+/// server.run()?;
+///
+pub struct Server {
+    listener: TcpListener,
+    shared: Arc<Shared>,
+}
+
+/// 为新会话的解释器设置存储和用户标识
+type Provider = Arc<dyn Fn(&mut Interpreter, &str) + Send + Sync>;
+
+struct Shared {
+    program: Arc<Program>,
+    limits: Limits,
+    storage: Provider,
+    users: HashMap<String, String>,
+    max_sessions: usize,
+    active: Arc<AtomicUsize>,
+    next_id: AtomicU64,
+    sessions: Sessions,
+}
+
+///
+/// 一个会话名额，会话结束时随之释放
+///
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// HTTP 会话的消息通道，以及用于结束会话时中止执行的取消令牌
+type Sessions = Arc<Mutex<HashMap<String, (Sender<Message>, CancelToken)>>>;
+
+/// 发给 HTTP 会话线程的用户消息，以及接收本轮结果的通道
+type Message = (String, Sender<Result<Turn, String>>);
+
+impl Server {
+    ///
+    /// 监听指定地址
+    ///
+    /// # 参数列表
+    /// * address: 监听地址，端口为 0 时由系统分配
+    /// * program: 每个会话执行的程序
+    ///
+    pub fn bind<A: ToSocketAddrs>(address: A, program: Program) -> io::Result<Self> {
+        Ok(Server {
+            listener: TcpListener::bind(address)?,
+            shared: Arc::new(Shared {
+                program: Arc::new(program),
+                limits: Limits::default(),
+                storage: provider(MemoryStorage::new()),
+                users: HashMap::new(),
+                max_sessions: DEFAULT_MAX_SESSIONS,
+                active: Arc::new(AtomicUsize::new(0)),
+                next_id: AtomicU64::new(1),
+                sessions: Arc::new(Mutex::new(HashMap::new())),
+            }),
+        })
+    }
+
+    ///
+    /// 设置每个会话的资源限制，默认不做限制
+    ///
+    pub fn set_limits(&mut self, limits: Limits) {
+        Arc::get_mut(&mut self.shared)
+            .expect("limits are set before the server runs")
+            .limits = limits;
+    }
+
+    ///
+    /// 设置所有会话共用的存储，默认为进程内的内存存储
+    ///
+    /// # 参数列表
+    /// * storage: 存储，每个会话使用它的一个克隆，克隆之间需要共享数据
+    ///
+    pub fn set_storage<S: Storage + Clone + Send + Sync + 'static>(&mut self, storage: S) {
+        Arc::get_mut(&mut self.shared)
+            .expect("storage is set before the server runs")
+            .storage = provider(storage);
+    }
+
+    ///
+    /// 设置同时存在的会话数上限，默认为 `DEFAULT_MAX_SESSIONS`
+    ///
+    /// # 参数列表
+    /// * max: 会话数上限，包括三种协议的会话
+    ///
+    pub fn set_max_sessions(&mut self, max: usize) {
+        Arc::get_mut(&mut self.shared)
+            .expect("the session limit is set before the server runs")
+            .max_sessions = max;
+    }
+
+    ///
+    /// 登记一个访问令牌，携带该令牌创建的会话属于指定的用户
+    ///
+    /// # 参数列表
+    /// * token: 访问令牌，由服务端签发给客户端
+    /// * user: 用户标识，同一用户的会话共享存储中的数据
+    ///
+    pub fn add_user(&mut self, token: &str, user: &str) {
+        Arc::get_mut(&mut self.shared)
+            .expect("users are added before the server runs")
+            .users
+            .insert(token.to_string(), user.to_string());
+    }
+
+    ///
+    /// 实际监听的地址
+    ///
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    ///
+    /// 接受连接，每个连接在新线程中处理，本方法不会返回
+    ///
+    pub fn run(self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let shared = Arc::clone(&self.shared);
+            // 连接线程只负责协议，会话在占到名额后才进入大栈的会话线程
+            let _ = thread::Builder::new().spawn(move || {
+                let _ = shared.handle(stream);
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Shared {
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        if is_http(&stream)? {
+            self.serve_http(stream)
+        } else {
+            self.serve_lines(stream)
+        }
+    }
+
+    fn serve_lines(&self, mut stream: TcpStream) -> io::Result<()> {
+        let _slot = match self.reserve() {
+            Some(slot) => slot,
+            None => return writeln!(stream, "{}", BUSY),
+        };
+        on_session_thread(|| self.converse_lines(stream))
+    }
+
+    fn converse_lines(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+        let token = watch(&stream)?;
+        let connection = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        let interpreter = Interpreter::builder()
+            .channel(connection)
+            .limits(self.limits)
+            .cancel_token(token)
+            .build();
+        let mut session = Session::with_interpreter(Arc::clone(&self.program), interpreter);
+        let written = match session.run() {
+            Ok(()) | Err(Error::Io(_)) | Err(Error::Cancelled) => Ok(()),
+            Err(error) => writeln!(writer, "{}", error),
+        };
+        // 关闭连接，同时让后台的 watch 线程退出
+        let _ = writer.shutdown(Shutdown::Both);
+        written
+    }
+
+    fn serve_http(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        let (status, body) = match Request::read(&mut reader) {
+            Ok(Some(request)) if request.method == "GET" && request.path == "/ws" => {
+                return self.serve_websocket(writer, &request)
+            }
+            Ok(Some(request)) => self.route(&request),
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                http::respond(&mut writer, 400, &json!({ "error": e.to_string() }))?;
+                // 读掉客户端还在发送的请求，避免直接关闭时连接被重置、响应丢失
+                writer.shutdown(Shutdown::Write)?;
+                let _ = io::copy(&mut reader.take(MAX_DRAIN), &mut io::sink());
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        http::respond(&mut writer, status, &body)
+    }
+
+    fn serve_websocket(&self, mut stream: TcpStream, request: &Request) -> io::Result<()> {
+        let upgrade = request
+            .header("upgrade")
+            .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+        let key = match request.header("sec-websocket-key") {
+            Some(key) if upgrade => key,
+            _ => {
+                let error = json!({ "error": "Expected a WebSocket upgrade." });
+                return http::respond(&mut stream, 400, &error);
+            }
+        };
+        let access = credentials(request).or_else(|| request.param("token"));
+        let user = match self.identify(request.param("user").is_some(), access) {
+            Ok(user) => user,
+            Err((status, error)) => return http::respond(&mut stream, status, &error),
+        };
+        let _slot = match self.reserve() {
+            Some(slot) => slot,
+            None => return http::respond(&mut stream, 503, &json!({ "error": BUSY })),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        )?;
+        on_session_thread(|| self.converse_websocket(stream, user))
+    }
+
+    fn converse_websocket(&self, stream: TcpStream, user: Option<String>) -> io::Result<()> {
+        stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+        let token = watch(&stream)?;
+        let raw = stream.try_clone()?;
+
+        let socket = Rc::new(RefCell::new(WebSocket::from_raw_socket(
+            stream,
+            Role::Server,
+            None,
+        )));
+        let browser = Browser {
+            socket: Rc::clone(&socket),
+        };
+        let mut interpreter = Interpreter::builder()
+            .channel(browser)
+            .limits(self.limits)
+            .cancel_token(token)
+            .build();
+        if let Some(user) = user {
+            (self.storage)(&mut interpreter, &user);
+        }
+        let mut session = Session::with_interpreter(Arc::clone(&self.program), interpreter);
+        let last = match session.run() {
+            Ok(()) => Some(event("end", "")),
+            Err(Error::Io(_)) | Err(Error::Cancelled) => None,
+            Err(error) => Some(event("error", &error.to_string())),
+        };
+        if let Some(last) = last {
+            let mut socket = socket.borrow_mut();
+            let _ = socket.send(Frame::text(last.to_string()));
+            let _ = socket.close(None);
+            let _ = socket.flush();
+        }
+        let _ = raw.shutdown(Shutdown::Both);
+        Ok(())
+    }
+
+    fn route(&self, request: &Request) -> (u16, Value) {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["sessions"]) => {
+                let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+                let user = match self.identify(body.get("user").is_some(), credentials(request)) {
+                    Ok(user) => user,
+                    Err(error) => return error,
+                };
+                match self.reserve() {
+                    Some(slot) => {
+                        let (id, turn) = self.open(user, slot);
+                        (201, reply(&id, turn))
+                    }
+                    None => (503, json!({ "error": BUSY })),
+                }
+            }
+            ("POST", ["sessions", id]) => {
+                let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+                let text = match body.get("text").and_then(Value::as_str) {
+                    Some(text) => text,
+                    None => return (400, json!({ "error": "Expected {\"text\": ...}." })),
+                };
+                match self.send(id, text) {
+                    Some(turn) => (200, reply(id, turn)),
+                    None => not_found(id),
+                }
+            }
+            ("DELETE", ["sessions", id]) => match self.sessions.lock().unwrap().remove(*id) {
+                Some((_, token)) => {
+                    token.cancel();
+                    (204, Value::Null)
+                }
+                None => not_found(id),
+            },
+            (_, ["sessions"]) | (_, ["sessions", _]) => {
+                (405, json!({ "error": "Method not allowed." }))
+            }
+            _ => (404, json!({ "error": "Not found." })),
+        }
+    }
+
+    ///
+    /// 由访问令牌确定会话的用户
+    ///
+    /// # 参数列表
+    /// * claimed: 客户端是否直接给出了用户标识
+    /// * token: 请求携带的访问令牌
+    ///
+    /// # 返回值
+    /// * 用户标识，没有携带令牌时为 None，即匿名会话
+    /// * 客户端给出用户标识时为 400，令牌未知时为 401
+    ///
+    fn identify(&self, claimed: bool, token: Option<&str>) -> Result<Option<String>, (u16, Value)> {
+        if claimed {
+            let error = "User ids are assigned by the server, send an access token instead.";
+            return Err((400, json!({ "error": error })));
+        }
+        match token {
+            Some(token) => match self.users.get(token) {
+                Some(user) => Ok(Some(user.clone())),
+                None => Err((401, json!({ "error": "Unknown access token." }))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// 占用一个会话名额，会话数已达上限时返回 None
+    ///
+    fn reserve(&self) -> Option<Slot> {
+        self.active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                (active < self.max_sessions).then_some(active + 1)
+            })
+            .ok()
+            .map(|_| Slot(Arc::clone(&self.active)))
+    }
+
+    ///
+    /// 在新线程中创建会话并运行到第一次等待输入
+    ///
+    /// # 参数列表
+    /// * user: 用户标识，为 None 时是匿名会话，使用私有的内存存储
+    /// * slot: 会话占用的名额，会话线程结束时释放
+    ///
+    fn open(&self, user: Option<String>, slot: Slot) -> (String, Result<Turn, String>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let token = CancelToken::new();
+        let (sender, inbox) = mpsc::channel();
+        let (reply_to, first) = mpsc::channel();
+        let program = Arc::clone(&self.program);
+        let (limits, cancel) = (self.limits, token.clone());
+        let storage = Arc::clone(&self.storage);
+        let sessions = Arc::clone(&self.sessions);
+        let key = id.clone();
+        // 线程创建失败时 first 随之关闭，下面会报告会话意外停止
+        let _ = session_thread().spawn(move || {
+            let mut interpreter = Interpreter::builder()
+                .limits(limits)
+                .cancel_token(cancel)
+                .build();
+            if let Some(user) = user {
+                storage(&mut interpreter, &user);
+            }
+            converse(program, interpreter, inbox, reply_to);
+            sessions.lock().unwrap().remove(&key);
+            drop(slot);
+        });
+
+        let turn = first
+            .recv()
+            .unwrap_or_else(|_| Err("The session stopped unexpectedly.".to_string()));
+        if is_waiting(&turn) {
+            self.sessions
+                .lock()
+                .unwrap()
+                .insert(id.clone(), (sender, token));
+        }
+        (id, turn)
+    }
+
+    ///
+    /// 把消息交给会话线程并等待本轮结果，会话不存在时返回 None
+    ///
+    fn send(&self, id: &str, text: &str) -> Option<Result<Turn, String>> {
+        let sender = self.sessions.lock().unwrap().get(id)?.0.clone();
+        let (reply_to, reply) = mpsc::channel();
+        let turn = match sender.send((text.to_string(), reply_to)) {
+            Ok(()) => reply.recv().ok(),
+            Err(_) => None,
+        };
+        if !turn.as_ref().is_some_and(is_waiting) {
+            self.sessions.lock().unwrap().remove(id);
+        }
+        turn
+    }
+}
+
+///
+/// HTTP 会话线程，持有会话直到程序结束或长时间没有消息，
+/// 返回后由调用者把会话从表中移除
+///
+fn converse(
+    program: Arc<Program>,
+    interpreter: Interpreter,
+    inbox: Receiver<Message>,
+    first: Sender<Result<Turn, String>>,
+) {
+    let mut session = Session::with_interpreter(program, interpreter);
+    let turn = session.start().map_err(|e| e.to_string());
+    let waiting = is_waiting(&turn);
+    if first.send(turn).is_err() || !waiting {
+        return;
+    }
+    while let Ok((text, reply_to)) = inbox.recv_timeout(IDLE_TIMEOUT) {
+        let turn = session.resume(&text).map_err(|e| e.to_string());
+        let waiting = is_waiting(&turn);
+        if reply_to.send(turn).is_err() || !waiting {
+            return;
+        }
+    }
+}
+
+///
+/// 运行会话的线程，栈足够容纳默认的调用深度
+///
+fn session_thread() -> thread::Builder {
+    thread::Builder::new().stack_size(STACK_SIZE)
+}
+
+///
+/// 在会话线程中运行连接上的会话，等待它结束
+///
+fn on_session_thread(body: impl FnOnce() -> io::Result<()> + Send) -> io::Result<()> {
+    thread::scope(
+        |scope| match session_thread().spawn_scoped(scope, body)?.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        },
+    )
+}
+
+fn provider<S: Storage + Clone + Send + Sync + 'static>(storage: S) -> Provider {
+    Arc::new(move |interpreter: &mut Interpreter, user: &str| {
+        interpreter.set_storage(user, storage.clone())
+    })
+}
+
+fn is_waiting(turn: &Result<Turn, String>) -> bool {
+    matches!(turn, Ok(turn) if turn.status == Status::WaitingForInput)
+}
+
+fn reply(id: &str, turn: Result<Turn, String>) -> Value {
+    match turn {
+        Ok(turn) => json!({
+            "session": id,
+            "utterances": turn.utterances,
+            "status": match turn.status {
+                Status::WaitingForInput => "waiting",
+                Status::Finished => "finished",
+            },
+        }),
+        Err(error) => json!({
+            "session": id,
+            "utterances": [],
+            "status": "finished",
+            "error": error,
+        }),
+    }
+}
+
+///
+/// 请求头中的访问令牌，`Bearer` 前缀可以省略
+///
+fn credentials(request: &Request) -> Option<&str> {
+    let value = request.header("authorization")?;
+    Some(value.strip_prefix("Bearer ").unwrap_or(value).trim())
+}
+
+fn not_found(id: &str) -> (u16, Value) {
+    (404, json!({ "error": format!("No session '{}'.", id) }))
+}
+
+///
+/// 在后台线程中等待客户端关闭连接，关闭时取消返回的令牌
+///
+/// 会话结束后调用者需要关闭连接，后台线程随之退出。
+///
+fn watch(stream: &TcpStream) -> io::Result<CancelToken> {
+    let token = CancelToken::new();
+    let (stream, cancel) = (stream.try_clone()?, token.clone());
+    thread::spawn(move || {
+        let mut byte = [0; 1];
+        loop {
+            match stream.peek(&mut byte) {
+                Ok(0) => break,
+                // 数据留给会话读取，稍后再检查
+                Ok(_) => thread::sleep(WATCH_INTERVAL),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                            | io::ErrorKind::Interrupted
+                    ) => {}
+                Err(_) => break,
+            }
+        }
+        cancel.cancel();
+    });
+    Ok(token)
+}
+
+///
+/// 查看连接最先发来的数据是否为 HTTP 请求行
+///
+/// 请求行可能分几次到达，只要已收到的内容还是某个请求方法的前缀就继续等待。
+///
+fn is_http(stream: &TcpStream) -> io::Result<bool> {
+    let deadline = Instant::now() + SNIFF_TIMEOUT;
+    let mut head = [0; 8];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        let peeked = stream.peek(&mut head);
+        stream.set_read_timeout(None)?;
+        let head = match peeked {
+            Ok(0) => return Ok(false),
+            Ok(n) => &head[..n],
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(false)
+            }
+            Err(e) => return Err(e),
+        };
+        if METHODS
+            .iter()
+            .any(|method| head.starts_with(method.as_bytes()))
+        {
+            return Ok(true);
+        }
+        if !METHODS
+            .iter()
+            .any(|method| method.as_bytes().starts_with(head))
+        {
+            return Ok(false);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+///
+/// 发给 WebSocket 客户端的消息
+///
+fn event(kind: &str, text: &str) -> Value {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default();
+    json!({ "type": kind, "text": text, "timestamp": timestamp })
+}
+
+///
+/// WebSocket 的对话通道
+///
+struct Browser {
+    socket: Rc<RefCell<WebSocket<TcpStream>>>,
+}
+
+impl Channel for Browser {
+    fn speak(&mut self, text: &str) -> Result<(), Error> {
+        let message = Frame::text(event("speak", text).to_string());
+        self.socket.borrow_mut().send(message).map_err(socket_error)
+    }
+
+    fn listen(&mut self) -> Result<Option<String>, Error> {
+        loop {
+            let text = match self.socket.borrow_mut().read() {
+                Ok(Frame::Text(text)) => text,
+                Ok(Frame::Close(_))
+                | Err(tungstenite::Error::ConnectionClosed)
+                | Err(tungstenite::Error::AlreadyClosed) => return Ok(None),
+                Ok(_) => continue,
+                Err(error) => return Err(socket_error(error)),
+            };
+            let message: Option<Value> = serde_json::from_str(&text).ok();
+            return Ok(Some(
+                match message.as_ref().and_then(|message| message.get("text")) {
+                    Some(Value::String(inner)) => inner.clone(),
+                    _ => text.to_string(),
+                },
+            ));
+        }
+    }
+}
+
+fn socket_error(error: tungstenite::Error) -> Error {
+    match error {
+        tungstenite::Error::Io(e) => Error::Io(e),
+        other => Error::Io(io::Error::other(other)),
+    }
+}
+
+///
+/// 行协议的对话通道，直接读写 TCP 连接
+///
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Channel for Connection {
+    fn speak(&mut self, text: &str) -> Result<(), Error> {
+        writeln!(self.writer, "{}", text)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn listen(&mut self) -> Result<Option<String>, Error> {
+        Ok(http::read_line(&mut self.reader, MAX_LINE)?)
+    }
+}
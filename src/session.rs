@@ -0,0 +1,317 @@
+use crate::channel::Mailbox;
+use crate::error::Error;
+use crate::interpreter::Interpreter;
+use crate::object::Object;
+use crate::program::Program;
+use crate::snapshot::{self, SnapshotError};
+
+use std::sync::Arc;
+
+///
+/// 一次对话的会话，持有某个用户独立的解释器与环境
+///
+/// 多个会话共享同一个编译好的 `Program`，
+/// 创建会话不需要重新解析脚本，会话之间的变量互不影响。
+///
+/// # 使用示例
+/// let program = Arc::new(Program::compile(source)?);
+/// let mut session = Session::with_interpreter(
+///     Arc::clone(&program),
+///     Interpreter::builder().channel(transcript.clone()).build(),
+/// );
+/// session.run()?;
+///
+pub struct Session {
+    program: Arc<Program>,
+    interpreter: Interpreter,
+    mailbox: Option<Mailbox>,
+    status: Option<Status>,
+}
+
+///
+/// 可恢复执行的会话所处的状态
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// 程序暂停在 input、inputn、menu 或状态机处，等待用户的下一条消息
+    WaitingForInput,
+    /// 程序已经执行完毕、执行了 exit 或出错
+    Finished,
+}
+
+///
+/// 一轮对话的结果
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turn {
+    /// 本轮中 speak 输出的全部内容
+    pub utterances: Vec<String>,
+    /// 本轮结束后会话的状态
+    pub status: Status,
+}
+
+impl Session {
+    ///
+    /// 使用标准输入输出创建会话
+    ///
+    /// # 参数列表
+    /// * program: 共享的程序
+    ///
+    pub fn new(program: Arc<Program>) -> Self {
+        Self::with_interpreter(program, Interpreter::new())
+    }
+
+    ///
+    /// 使用宿主配置好的解释器创建会话，可以带有宿主函数、常量和对话通道
+    ///
+    /// # 参数列表
+    /// * program: 共享的程序
+    /// * interpreter: 该会话专用的解释器
+    ///
+    pub fn with_interpreter(program: Arc<Program>, mut interpreter: Interpreter) -> Self {
+        if let Some(path) = program.path() {
+            interpreter.set_entry_path(path);
+        }
+        Session {
+            program,
+            interpreter,
+            mailbox: None,
+            status: None,
+        }
+    }
+
+    ///
+    /// 执行程序，exit 语句视为正常结束
+    ///
+    pub fn run(&mut self) -> Result<(), Error> {
+        let program = Arc::clone(&self.program);
+        self.interpreter.interpret(program.statements())
+    }
+
+    ///
+    /// 以可恢复模式开始执行程序，运行到第一次等待输入为止
+    ///
+    /// 可恢复模式下会话使用自己的 `Mailbox` 通道，取代解释器原有的通道，
+    /// 因此解释器不会阻塞在输入上，适合由聊天 webhook、消息队列等事件循环驱动。
+    ///
+    /// # 返回值
+    /// * 本轮的输出与会话状态
+    /// * 执行出错时返回错误，会话随即结束
+    ///
+    /// # 使用示例
+    /// let mut session = Session::new(Arc::clone(&program));
+    /// let greeting = session.start()?;
+    /// let reply = session.resume("check balance")?;
+    /// if reply.status == Status::Finished { ... }
+    ///
+    pub fn start(&mut self) -> Result<Turn, Error> {
+        if self.status.is_some() {
+            return self.turn();
+        }
+        let mailbox = Mailbox::new();
+        self.interpreter.set_channel(mailbox.clone());
+        self.mailbox = Some(mailbox);
+
+        let program = Arc::clone(&self.program);
+        let result = self.interpreter.interpret(program.statements());
+        self.finish_turn(result)
+    }
+
+    ///
+    /// 以可恢复模式执行程序的顶层语句，然后从指定状态开始运行状态机，运行到第一次等待输入为止
+    ///
+    /// 顶层语句用于声明变量和状态，不能等待输入；之后的每条消息都交给状态机处理。
+    ///
+    /// # 参数列表
+    /// * start: 起始状态名
+    ///
+    /// # 返回值
+    /// * 本轮的输出与会话状态
+    /// * 顶层语句等待输入、起始状态不存在或执行出错时返回错误，会话随即结束
+    ///
+    /// # 使用示例
+    /// let mut session = Session::new(Arc::clone(&program));
+    /// let greeting = session.start_machine("Welcome")?;
+    /// let reply = session.resume("coffee")?;
+    ///
+    pub fn start_machine(&mut self, start: &str) -> Result<Turn, Error> {
+        if self.status.is_some() {
+            return self.turn();
+        }
+        let mailbox = Mailbox::new();
+        self.interpreter.set_channel(mailbox.clone());
+        self.mailbox = Some(mailbox);
+
+        let program = Arc::clone(&self.program);
+        let result = match self.interpreter.interpret(program.statements()) {
+            Ok(()) => self.interpreter.run_state_machine(start),
+            Err(Error::Suspend) => {
+                self.interpreter.clear_suspension();
+                Err(Error::Native(
+                    "Top-level statements cannot wait for input before the state machine starts."
+                        .to_string(),
+                ))
+            }
+            Err(error) => Err(error),
+        };
+        self.finish_turn(result)
+    }
+
+    ///
+    /// 把用户消息交给暂停中的程序，继续执行到下一次等待输入为止
+    ///
+    /// 会话尚未开始时先执行 `start`，再把消息交给程序；
+    /// 会话已经结束时忽略消息。
+    ///
+    /// # 参数列表
+    /// * message: 用户消息
+    ///
+    /// # 返回值
+    /// * 本轮的输出与会话状态
+    ///
+    pub fn resume(&mut self, message: &str) -> Result<Turn, Error> {
+        let mut utterances = match self.status {
+            None => self.start()?.utterances,
+            Some(_) => Vec::new(),
+        };
+        if self.status != Some(Status::WaitingForInput) {
+            return Ok(Turn {
+                utterances,
+                status: Status::Finished,
+            });
+        }
+
+        if let Some(mailbox) = &self.mailbox {
+            mailbox.post(message);
+        }
+        let program = Arc::clone(&self.program);
+        let result = self.interpreter.resume(program.statements());
+        let mut turn = self.finish_turn(result)?;
+        utterances.append(&mut turn.utterances);
+        turn.utterances = utterances;
+        Ok(turn)
+    }
+
+    ///
+    /// 会话当前的状态，尚未开始时为 None
+    ///
+    pub fn status(&self) -> Option<Status> {
+        self.status
+    }
+
+    ///
+    /// 把暂停中的会话保存为 JSON 快照，包括环境中的变量、当前执行位置和调用栈
+    ///
+    /// # 返回值
+    /// * 快照，可以存入数据库，之后在另一个进程中用 `Session::restore` 恢复
+    /// * 会话没有在等待输入，或环境中有无法保存的值（如导入的模块）时返回错误
+    ///
+    /// # 使用示例
+    /// session.resume("recharge")?;
+    /// let saved = session.snapshot()?;
+    /// ...
+    /// let mut session = Session::restore(program, Interpreter::new(), &saved)?;
+    /// session.resume("30")?;
+    ///
+    pub fn snapshot(&self) -> Result<String, SnapshotError> {
+        if self.status != Some(Status::WaitingForInput) {
+            return Err(SnapshotError::NotPaused);
+        }
+        snapshot::save(&self.program, &self.interpreter)
+    }
+
+    ///
+    /// 从快照恢复暂停中的会话，之后用 `resume` 把下一条用户消息交给它
+    ///
+    /// # 参数列表
+    /// * program: 与保存快照时源代码相同的程序
+    /// * interpreter: 新建的解释器，需要注册与保存时相同的宿主函数
+    /// * snapshot: `Session::snapshot` 得到的快照
+    ///
+    /// # 返回值
+    /// * 等待输入的会话
+    /// * 快照格式版本不同、脚本已被修改或快照损坏时返回错误
+    ///
+    pub fn restore(
+        program: Arc<Program>,
+        interpreter: Interpreter,
+        snapshot: &str,
+    ) -> Result<Self, SnapshotError> {
+        let mut session = Self::with_interpreter(program, interpreter);
+        snapshot::restore(&session.program, &mut session.interpreter, snapshot)?;
+        let mailbox = Mailbox::new();
+        session.interpreter.set_channel(mailbox.clone());
+        session.mailbox = Some(mailbox);
+        session.status = Some(Status::WaitingForInput);
+        Ok(session)
+    }
+
+    fn finish_turn(&mut self, result: Result<(), Error>) -> Result<Turn, Error> {
+        let status = match result {
+            Ok(()) => Status::Finished,
+            Err(Error::Suspend) => Status::WaitingForInput,
+            Err(error) => {
+                self.status = Some(Status::Finished);
+                return Err(error);
+            }
+        };
+        self.status = Some(status);
+        self.turn()
+    }
+
+    fn turn(&self) -> Result<Turn, Error> {
+        let utterances = match &self.mailbox {
+            Some(mailbox) => mailbox.take_outputs(),
+            None => Vec::new(),
+        };
+        Ok(Turn {
+            utterances,
+            status: self.status.unwrap_or(Status::WaitingForInput),
+        })
+    }
+
+    ///
+    /// 调用会话中的一个全局 step
+    ///
+    /// # 参数列表
+    /// * name: step 名
+    /// * arguments: 参数列表
+    ///
+    pub fn call(&mut self, name: &str, arguments: &[Object]) -> Result<Object, Error> {
+        let callee = self
+            .get(name)
+            .ok_or_else(|| Error::Native(format!("Undefined step '{}'.", name)))?;
+        match self.interpreter.call(&callee, arguments) {
+            Err(Error::Exit) => Ok(Object::Null),
+            Err(Error::Suspend) => {
+                self.interpreter.clear_suspension();
+                Err(Error::Native(format!(
+                    "Step '{}' cannot wait for input when called by the host.",
+                    name
+                )))
+            }
+            other => other,
+        }
+    }
+
+    ///
+    /// 读取会话中的全局变量
+    ///
+    pub fn get(&self, name: &str) -> Option<Object> {
+        self.interpreter.globals.borrow().lookup(name)
+    }
+
+    ///
+    /// 会话执行的程序
+    ///
+    pub fn program(&self) -> &Arc<Program> {
+        &self.program
+    }
+
+    ///
+    /// 会话专用的解释器
+    ///
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+}
@@ -0,0 +1,740 @@
+use crate::env::Environment;
+use crate::function::Function;
+use crate::interpreter::Interpreter;
+use crate::object::Object;
+use crate::program::Program;
+use crate::state::State;
+use crate::suspend::{Frame, MachinePhase, MenuPhase};
+use crate::symbol::Symbol;
+use crate::syntax::{Expr, Stmt};
+use crate::token::Token;
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+///
+/// 快照格式的版本号，格式发生不兼容的变化时递增
+///
+pub const VERSION: u32 = 1;
+
+///
+/// 保存或恢复快照时的错误
+///
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// 会话没有暂停在等待输入处，无法保存
+    NotPaused,
+    /// 会话中有无法保存的值，例如导入模块中定义的 step
+    Unsupported(String),
+    /// 快照不是合法的 JSON，或缺少必要的字段
+    Format(serde_json::Error),
+    /// 快照由不同版本的格式写出
+    Version { found: u32, expected: u32 },
+    /// 保存快照之后脚本被修改过
+    ScriptChanged { found: String, expected: String },
+    /// 快照与当前的程序或宿主函数对不上
+    Incompatible(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::NotPaused => {
+                write!(f, "Only a session waiting for input can be saved.")
+            }
+            SnapshotError::Unsupported(message) => write!(f, "Cannot save {}.", message),
+            SnapshotError::Format(underlying) => write!(f, "Malformed snapshot: {}", underlying),
+            SnapshotError::Version { found, expected } => write!(
+                f,
+                "Snapshot format version {} is not supported, expected {}.",
+                found, expected
+            ),
+            SnapshotError::ScriptChanged { found, expected } => write!(
+                f,
+                "The script has changed since the snapshot was taken (snapshot {}, script {}).",
+                found, expected
+            ),
+            SnapshotError::Incompatible(message) => {
+                write!(f, "Snapshot does not match the program: {}.", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(e: serde_json::Error) -> Self {
+        SnapshotError::Format(e)
+    }
+}
+
+///
+/// 暂停中会话的快照
+///
+/// 环境按编号保存，父环境的编号总是小于子环境，
+/// step 和状态以名字和它是程序中第几个同名声明引用，原生函数以名字引用，
+/// 因此只改动注释或空白的脚本仍然可以恢复快照。
+///
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    fingerprint: String,
+    globals: usize,
+    environments: Vec<Scope>,
+    frames: Vec<Position>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Scope {
+    enclosing: Option<usize>,
+    values: BTreeMap<String, Value>,
+    constants: BTreeMap<String, i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Value {
+    Null,
+    Boolean {
+        value: bool,
+    },
+    Number {
+        value: f64,
+    },
+    String {
+        value: String,
+    },
+    List {
+        items: Vec<Value>,
+    },
+    Map {
+        entries: BTreeMap<String, Value>,
+    },
+    Native {
+        name: String,
+    },
+    Step {
+        name: String,
+        index: usize,
+        closure: usize,
+    },
+    State {
+        name: String,
+        index: usize,
+        closure: usize,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "frame", rename_all = "snake_case")]
+enum Position {
+    Statement {
+        index: usize,
+    },
+    Scope {
+        environment: usize,
+    },
+    Branch,
+    Call {
+        function: Value,
+        environment: usize,
+    },
+    Arm {
+        arm: Option<usize>,
+    },
+    Menu {
+        prompt: Option<String>,
+        phase: MenuPhase,
+    },
+    Machine {
+        state: Value,
+        phase: MachinePhase,
+        environment: Option<usize>,
+    },
+}
+
+///
+/// 把暂停中的解释器保存为 JSON
+///
+/// # 参数列表
+/// * program: 解释器正在执行的程序
+/// * interpreter: 暂停中的解释器
+///
+pub(crate) fn save(program: &Program, interpreter: &Interpreter) -> Result<String, SnapshotError> {
+    let mut saver = Saver {
+        program,
+        modules: interpreter.module_environments().cloned().collect(),
+        ids: HashMap::new(),
+        environments: Vec::new(),
+        pending: Vec::new(),
+    };
+    let globals = saver.environment(&interpreter.globals)?;
+    let frames = interpreter
+        .suspension()
+        .iter()
+        .map(|frame| saver.frame(frame))
+        .collect::<Result<_, _>>()?;
+    while let Some((id, environment)) = saver.pending.pop() {
+        let values = environment
+            .borrow()
+            .entries()
+            .map(|(name, value)| {
+                // 恢复时只接受程序中出现过的名字，不带别名导入的变量无法恢复
+                if !program.mentions(name) && saver.imported(name) {
+                    return Err(SnapshotError::Unsupported(format!(
+                        "the variable '{}' imported from a module",
+                        name
+                    )));
+                }
+                Ok((name.to_string(), saver.value(value)?))
+            })
+            .collect::<Result<_, SnapshotError>>()?;
+        saver.environments[id].values = values;
+    }
+
+    let snapshot = Snapshot {
+        version: VERSION,
+        fingerprint: format!("{:016x}", program.fingerprint()),
+        globals,
+        environments: saver.environments,
+        frames,
+    };
+    Ok(serde_json::to_string(&snapshot)?)
+}
+
+///
+/// 把快照中的环境与暂停位置恢复到新建的解释器中
+///
+/// 快照中的全局变量会覆盖解释器原有的同名变量，
+/// 原生函数从解释器原有的全局环境中按名字查找，因此宿主需要注册同样的函数。
+///
+/// # 参数列表
+/// * program: 与保存时相同的程序
+/// * interpreter: 尚未执行过程序的解释器
+/// * json: save 得到的快照
+///
+pub(crate) fn restore(
+    program: &Program,
+    interpreter: &mut Interpreter,
+    json: &str,
+) -> Result<(), SnapshotError> {
+    let raw: serde_json::Value = serde_json::from_str(json)?;
+    let found = raw.get("version").and_then(|version| version.as_u64());
+    if found != Some(u64::from(VERSION)) {
+        return Err(SnapshotError::Version {
+            found: found.unwrap_or(0) as u32,
+            expected: VERSION,
+        });
+    }
+    let snapshot: Snapshot = serde_json::from_value(raw)?;
+    let expected = format!("{:016x}", program.fingerprint());
+    if snapshot.fingerprint != expected {
+        return Err(SnapshotError::ScriptChanged {
+            found: snapshot.fingerprint,
+            expected,
+        });
+    }
+
+    let natives = interpreter
+        .globals
+        .borrow()
+        .entries()
+        .filter_map(|(name, value)| match value {
+            Object::Callable(function @ Function::Native { .. }) => {
+                Some((name.to_string(), function.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    let mut restorer = Restorer {
+        program,
+        natives,
+        environments: Vec::new(),
+        states: HashMap::new(),
+    };
+    for (id, scope) in snapshot.environments.iter().enumerate() {
+        let environment = match scope.enclosing {
+            _ if id == snapshot.globals => Rc::clone(&interpreter.globals),
+            Some(parent) if parent < id => Rc::new(RefCell::new(Environment::from(
+                restorer.environment(parent)?,
+            ))),
+            Some(_) => return Err(corrupt()),
+            None => Rc::new(RefCell::new(Environment::new())),
+        };
+        restorer.environments.push(environment);
+    }
+    restorer.environment(snapshot.globals)?;
+    // 快照中的名字来自外部输入，只接受程序中出现过的标识符和宿主预先定义的名字，
+    // 既不驻留新的名字，也不会在环境中加入程序访问不到的变量
+    let predefined: HashSet<Symbol> = interpreter
+        .globals
+        .borrow()
+        .entries()
+        .map(|(name, _)| name)
+        .collect();
+    for (id, scope) in snapshot.environments.iter().enumerate() {
+        for (name, value) in &scope.values {
+            let symbol = Symbol::get(name)
+                .filter(|symbol| program.mentions(*symbol) || predefined.contains(symbol))
+                .ok_or_else(|| missing("variable", name))?;
+            let value = restorer.object(value)?;
+            let mut environment = restorer.environments[id].borrow_mut();
+            match scope.constants.get(name) {
+                Some(line) => environment.define_constant(symbol, value, *line),
+                None => environment.define(symbol, value),
+            }
+        }
+    }
+    let frames = snapshot
+        .frames
+        .iter()
+        .map(|position| restorer.frame(position))
+        .collect::<Result<Vec<_>, _>>()?;
+    validate(program.statements(), &frames)?;
+    interpreter.set_suspension(frames);
+    Ok(())
+}
+
+struct Saver<'a> {
+    program: &'a Program,
+    modules: Vec<Rc<RefCell<Environment>>>,
+    ids: HashMap<*const RefCell<Environment>, usize>,
+    environments: Vec<Scope>,
+    pending: Vec<(usize, Rc<RefCell<Environment>>)>,
+}
+
+impl Saver<'_> {
+    ///
+    /// 为环境分配编号，先为父环境分配，变量的值稍后再保存
+    ///
+    fn environment(
+        &mut self,
+        environment: &Rc<RefCell<Environment>>,
+    ) -> Result<usize, SnapshotError> {
+        if let Some(id) = self.ids.get(&Rc::as_ptr(environment)) {
+            return Ok(*id);
+        }
+        if self
+            .modules
+            .iter()
+            .any(|module| Rc::ptr_eq(module, environment))
+        {
+            return Err(SnapshotError::Unsupported(
+                "steps or states defined in an imported module".to_string(),
+            ));
+        }
+        let enclosing = match environment.borrow().enclosing() {
+            Some(parent) => Some(self.environment(parent)?),
+            None => None,
+        };
+        let constants = environment
+            .borrow()
+            .entries()
+            .filter_map(|(name, _)| {
+                let line = environment.borrow().constant_line(name)?;
+                Some((name.to_string(), line))
+            })
+            .collect();
+
+        let id = self.environments.len();
+        self.ids.insert(Rc::as_ptr(environment), id);
+        self.environments.push(Scope {
+            enclosing,
+            values: BTreeMap::new(),
+            constants,
+        });
+        self.pending.push((id, Rc::clone(environment)));
+        Ok(id)
+    }
+
+    fn value(&mut self, value: &Object) -> Result<Value, SnapshotError> {
+        Ok(match value {
+            Object::Null => Value::Null,
+            Object::Boolean(value) => Value::Boolean { value: *value },
+            Object::Number(value) => Value::Number { value: *value },
+            Object::String(value) => Value::String {
+                value: value.clone(),
+            },
+            Object::List(items) => Value::List {
+                items: items
+                    .iter()
+                    .map(|item| self.value(item))
+                    .collect::<Result<_, _>>()?,
+            },
+            Object::Map(entries) => Value::Map {
+                entries: entries
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), self.value(value)?)))
+                    .collect::<Result<_, SnapshotError>>()?,
+            },
+            Object::Callable(function) => self.function(function)?,
+            Object::State(state) => Value::State {
+                name: state.name.lexeme.clone(),
+                index: self.index(&state.name, true, &state.enter)?,
+                closure: self.environment(&state.closure)?,
+            },
+            Object::Module(module) => {
+                return Err(SnapshotError::Unsupported(format!(
+                    "the imported module '{}'",
+                    module.name
+                )))
+            }
+        })
+    }
+
+    fn function(&mut self, function: &Function) -> Result<Value, SnapshotError> {
+        Ok(match function {
+            Function::Native { name, .. } => Value::Native { name: name.clone() },
+            Function::User {
+                name,
+                body,
+                closure,
+                ..
+            } => Value::Step {
+                name: name.lexeme.clone(),
+                index: self.index(name, false, body)?,
+                closure: self.environment(closure)?,
+            },
+        })
+    }
+
+    ///
+    /// 名字是否由导入的模块定义
+    ///
+    fn imported(&self, name: Symbol) -> bool {
+        self.modules.iter().any(|module| {
+            module
+                .borrow()
+                .entries()
+                .any(|(defined, _)| defined == name)
+        })
+    }
+
+    ///
+    /// step 或状态是程序中第几个同名声明
+    ///
+    /// 函数体和进入动作与声明语句共享，按它们的地址找到声明，
+    /// 同一行中的多个同名声明也不会混淆。
+    ///
+    fn index(&self, name: &Token, state: bool, body: &[Stmt]) -> Result<usize, SnapshotError> {
+        declarations(self.program, &name.lexeme, state)
+            .iter()
+            .position(|declared| match declared {
+                Stmt::Function { body: declared, .. }
+                | Stmt::State {
+                    enter: declared, ..
+                } => std::ptr::eq(declared.as_ref(), body),
+                _ => false,
+            })
+            .ok_or_else(|| {
+                SnapshotError::Unsupported(format!(
+                    "'{}' declared outside the program",
+                    name.lexeme
+                ))
+            })
+    }
+
+    fn frame(&mut self, frame: &Frame) -> Result<Position, SnapshotError> {
+        Ok(match frame {
+            Frame::Statement(index) => Position::Statement { index: *index },
+            Frame::Scope(environment) => Position::Scope {
+                environment: self.environment(environment)?,
+            },
+            Frame::Branch => Position::Branch,
+            Frame::Call {
+                function,
+                environment,
+            } => Position::Call {
+                function: self.function(function)?,
+                environment: self.environment(environment)?,
+            },
+            Frame::Arm(arm) => Position::Arm { arm: *arm },
+            Frame::Menu { prompt, phase } => Position::Menu {
+                prompt: prompt.clone(),
+                phase: *phase,
+            },
+            Frame::Machine {
+                state,
+                phase,
+                environment,
+            } => Position::Machine {
+                state: self.value(&Object::State(Rc::clone(state)))?,
+                phase: *phase,
+                environment: match environment {
+                    Some(environment) => Some(self.environment(environment)?),
+                    None => None,
+                },
+            },
+        })
+    }
+}
+
+struct Restorer<'a> {
+    program: &'a Program,
+    natives: HashMap<String, Function>,
+    environments: Vec<Rc<RefCell<Environment>>>,
+    states: HashMap<(String, usize, usize), Rc<State>>,
+}
+
+impl Restorer<'_> {
+    fn environment(&self, id: usize) -> Result<&Rc<RefCell<Environment>>, SnapshotError> {
+        self.environments.get(id).ok_or_else(corrupt)
+    }
+
+    fn object(&mut self, value: &Value) -> Result<Object, SnapshotError> {
+        Ok(match value {
+            Value::Null => Object::Null,
+            Value::Boolean { value } => Object::Boolean(*value),
+            Value::Number { value } => Object::Number(*value),
+            Value::String { value } => Object::String(value.clone()),
+            Value::List { items } => Object::List(Rc::new(
+                items
+                    .iter()
+                    .map(|item| self.object(item))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Value::Map { entries } => Object::Map(Rc::new(
+                entries
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), self.object(value)?)))
+                    .collect::<Result<_, SnapshotError>>()?,
+            )),
+            Value::Native { .. } | Value::Step { .. } => Object::Callable(self.function(value)?),
+            Value::State {
+                name,
+                index,
+                closure,
+            } => Object::State(self.state(name, *index, *closure)?),
+        })
+    }
+
+    fn function(&self, value: &Value) -> Result<Function, SnapshotError> {
+        match value {
+            Value::Native { name } => self.natives.get(name).cloned().ok_or_else(|| {
+                SnapshotError::Incompatible(format!("native function '{}' is not registered", name))
+            }),
+            Value::Step {
+                name,
+                index,
+                closure,
+            } => match declarations(self.program, name, false).get(*index) {
+                Some(Stmt::Function { name, params, body }) => Ok(Function::User {
+                    name: name.clone(),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: Rc::clone(self.environment(*closure)?),
+                }),
+                _ => Err(missing("step", name)),
+            },
+            _ => Err(corrupt()),
+        }
+    }
+
+    ///
+    /// 恢复状态，同一个状态只创建一次，以保持状态之间的比较结果
+    ///
+    fn state(
+        &mut self,
+        name: &str,
+        index: usize,
+        closure: usize,
+    ) -> Result<Rc<State>, SnapshotError> {
+        let key = (name.to_string(), index, closure);
+        if let Some(state) = self.states.get(&key) {
+            return Ok(Rc::clone(state));
+        }
+        let state = match declarations(self.program, name, true).get(index) {
+            Some(Stmt::State {
+                name,
+                enter,
+                handlers,
+                fallback,
+            }) => Rc::new(State {
+                name: name.clone(),
+                enter: Arc::clone(enter),
+                handlers: Arc::clone(handlers),
+                fallback: fallback.clone(),
+                closure: Rc::clone(self.environment(closure)?),
+            }),
+            _ => return Err(missing("state", name)),
+        };
+        self.states.insert(key, Rc::clone(&state));
+        Ok(state)
+    }
+
+    fn frame(&mut self, position: &Position) -> Result<Frame, SnapshotError> {
+        Ok(match position {
+            Position::Statement { index } => Frame::Statement(*index),
+            Position::Scope { environment } => {
+                Frame::Scope(Rc::clone(self.environment(*environment)?))
+            }
+            Position::Branch => Frame::Branch,
+            Position::Call {
+                function,
+                environment,
+            } => Frame::Call {
+                function: self.function(function)?,
+                environment: Rc::clone(self.environment(*environment)?),
+            },
+            Position::Arm { arm } => Frame::Arm(*arm),
+            Position::Menu { prompt, phase } => Frame::Menu {
+                prompt: prompt.clone(),
+                phase: *phase,
+            },
+            Position::Machine {
+                state,
+                phase,
+                environment,
+            } => Frame::Machine {
+                state: match self.object(state)? {
+                    Object::State(state) => state,
+                    _ => return Err(corrupt()),
+                },
+                phase: *phase,
+                environment: match environment {
+                    Some(id) => Some(Rc::clone(self.environment(*id)?)),
+                    None => None,
+                },
+            },
+        })
+    }
+}
+
+///
+/// 程序中同名的 step 或状态声明，按它们在源代码中出现的顺序排列
+///
+fn declarations<'a>(program: &'a Program, name: &str, state: bool) -> Vec<&'a Stmt> {
+    let mut found = Vec::new();
+    for statement in program.statements() {
+        collect(
+            statement,
+            &mut |statement| match statement {
+                Stmt::Function { name: declared, .. } => !state && declared.lexeme == name,
+                Stmt::State { name: declared, .. } => state && declared.lexeme == name,
+                _ => false,
+            },
+            &mut found,
+        );
+    }
+    found
+}
+
+fn collect<'a>(
+    statement: &'a Stmt,
+    wanted: &mut impl FnMut(&Stmt) -> bool,
+    found: &mut Vec<&'a Stmt>,
+) {
+    if wanted(statement) {
+        found.push(statement);
+    }
+    for child in statement.children() {
+        collect(child, wanted, found);
+    }
+}
+
+///
+/// 检查暂停位置与程序的结构是否一致
+///
+/// 按恢复执行的顺序从外到内逐层对照语句，
+/// 下标越界或位置与所在的语句对不上时返回错误，避免恢复执行时出错。
+///
+fn validate(statements: &[Stmt], frames: &[Frame]) -> Result<(), SnapshotError> {
+    let mut place = Place::Program(statements);
+    for frame in frames.iter().rev() {
+        // 循环语句不记录位置，直接进入循环体
+        while let Place::Statement(Stmt::Loop { body, .. }) = place {
+            place = Place::Statement(body);
+        }
+        place = match (place, frame) {
+            (Place::Program(statements) | Place::List(statements), Frame::Statement(index)) => {
+                Place::Statement(statements.get(*index).ok_or_else(mismatch)?)
+            }
+            (Place::Statement(Stmt::Block { statements }), Frame::Scope(_)) => {
+                Place::List(statements)
+            }
+            (Place::Statement(Stmt::Branch { then, .. }), Frame::Branch) => Place::Statement(then),
+            (
+                Place::Statement(Stmt::Expression {
+                    expression: Expr::Call { .. },
+                }),
+                Frame::Call {
+                    function: Function::User { body, .. },
+                    ..
+                },
+            ) => Place::List(body),
+            (Place::Statement(Stmt::Match { arms, fallback, .. }), Frame::Arm(arm)) => {
+                Place::Statement(match arm {
+                    Some(index) => &arms.get(*index).ok_or_else(mismatch)?.body,
+                    None => fallback.as_deref().ok_or_else(mismatch)?,
+                })
+            }
+            (Place::Statement(Stmt::Menu { options, .. }), Frame::Menu { phase, .. }) => {
+                match phase {
+                    MenuPhase::Choosing => Place::Waiting,
+                    MenuPhase::Confirming(index) => {
+                        options.get(*index).ok_or_else(mismatch)?;
+                        Place::Waiting
+                    }
+                    MenuPhase::Running(index) => {
+                        Place::Statement(&options.get(*index).ok_or_else(mismatch)?.body)
+                    }
+                }
+            }
+            // 状态机由 goto 语句启动，或在顶层语句执行完毕后由 run_state_machine 启动
+            (
+                Place::Program(_) | Place::Statement(Stmt::Goto { .. }),
+                Frame::Machine { state, phase, .. },
+            ) => machine(state, phase)?,
+            _ => return Err(mismatch()),
+        };
+    }
+    Ok(())
+}
+
+///
+/// 状态机中暂停的位置
+///
+/// 状态由快照中的声明下标从程序里找到，处理器下标按该 Stmt::State 的声明检查。
+///
+fn machine<'a>(state: &'a State, phase: &MachinePhase) -> Result<Place<'a>, SnapshotError> {
+    Ok(match phase {
+        MachinePhase::Enter => Place::List(&state.enter),
+        MachinePhase::Waiting => Place::Waiting,
+        MachinePhase::Handler(Some(index)) => Place::List(std::slice::from_ref(
+            &state.handlers.get(*index).ok_or_else(mismatch)?.body,
+        )),
+        MachinePhase::Handler(None) => Place::List(std::slice::from_ref(
+            state.fallback.as_deref().ok_or_else(mismatch)?,
+        )),
+    })
+}
+
+///
+/// 检查暂停位置时正在对照的语句
+///
+#[derive(Clone, Copy)]
+enum Place<'a> {
+    /// 程序的顶层，只会是最外层的位置
+    Program(&'a [Stmt]),
+    List(&'a [Stmt]),
+    Statement(&'a Stmt),
+    /// 已到达等待输入的位置，后面不应再有更内层的位置
+    Waiting,
+}
+
+fn missing(kind: &str, name: &str) -> SnapshotError {
+    SnapshotError::Incompatible(format!("{} '{}' is not declared", kind, name))
+}
+
+fn mismatch() -> SnapshotError {
+    SnapshotError::Incompatible("the saved position does not fit the program".to_string())
+}
+
+fn corrupt() -> SnapshotError {
+    SnapshotError::Incompatible("the snapshot refers to unknown values".to_string())
+}
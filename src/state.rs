@@ -0,0 +1,48 @@
+use crate::env::Environment;
+use crate::syntax::{IntentArm, Stmt};
+use crate::token::Token;
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+///
+/// 对话状态机中的一个状态
+///
+/// 进入状态时执行 enter 块，之后等待用户输入，
+/// 根据输入匹配转移分支。没有任何输入分支的状态是终止状态。
+///
+pub struct State {
+    /// 状态名
+    pub name: Token,
+    /// 进入状态时执行的语句
+    pub enter: Arc<[Stmt]>,
+    /// 根据用户输入匹配的分支
+    pub handlers: Arc<[IntentArm]>,
+    /// 没有分支匹配时执行的语句
+    pub fallback: Option<Arc<Stmt>>,
+    /// 声明状态时的环境
+    pub closure: Rc<RefCell<Environment>>,
+}
+
+impl State {
+    ///
+    /// 是否为终止状态，即执行完 enter 块后状态机停止运行
+    ///
+    pub fn is_terminal(&self) -> bool {
+        self.handlers.is_empty() && self.fallback.is_none()
+    }
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<state {}>", self.name.lexeme)
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<state {}>", self.name.lexeme)
+    }
+}
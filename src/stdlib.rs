@@ -0,0 +1,414 @@
+use crate::env::Environment;
+use crate::error::Error;
+use crate::function::Function;
+use crate::intent;
+use crate::interpreter::Interpreter;
+use crate::object::Object;
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 不需要状态和解释器的标准库函数
+type Builtin = fn(&[Object]) -> Result<Object, Error>;
+
+/// 标准库函数表：函数名、参数个数、函数体
+const NATIVES: &[(&str, usize, Builtin)] = &[
+    // 时间
+    ("clock", 0, clock),
+    ("now", 0, now),
+    ("format_time", 2, format_time),
+    // 字符串
+    ("len", 1, len),
+    ("upper", 1, upper),
+    ("lower", 1, lower),
+    ("trim", 1, trim),
+    ("contains", 2, contains),
+    ("split", 2, split),
+    ("replace", 3, replace),
+    // 数字
+    ("floor", 1, floor),
+    ("round", 1, round),
+    ("parse", 1, parse),
+    ("to_string", 1, to_string),
+    // 列表与映射
+    ("get", 2, get),
+    // 模糊匹配
+    ("closest", 2, closest),
+    // 类型判断
+    ("type_of", 1, type_of),
+    ("is_bool", 1, is_bool),
+    ("is_list", 1, is_list),
+    ("is_nil", 1, is_nil),
+    ("is_number", 1, is_number),
+    ("is_step", 1, is_step),
+    ("is_string", 1, is_string),
+];
+
+///
+/// 将标准库中的原生函数注册到环境中
+///
+/// # 参数列表
+/// * environment: 注册的目标环境，一般为全局环境
+///
+pub fn register(environment: &mut Environment) {
+    for &(name, arity, body) in NATIVES {
+        define(environment, name, arity, move |_, args| body(args));
+    }
+
+    // 列表的高阶函数，需要回调解释器
+    define(environment, "map", 2, map);
+    define(environment, "filter", 2, filter);
+
+    // 正则表达式，编译结果缓存在解释器中
+    define(environment, "matches", 2, matches);
+    define(environment, "find_all", 2, find_all);
+
+    // 按用户持久保存的键值数据
+    define(environment, "store", 2, store);
+    define(environment, "load", 1, load);
+
+    // 随机数，seed 与 random 共享同一个状态
+    let state = Rc::new(Cell::new(((unix_now() * 1e6) as u64) | 1));
+    let seed_state = Rc::clone(&state);
+    define(environment, "seed", 1, move |_, args| {
+        seed(&seed_state, args)
+    });
+    define(environment, "random", 0, move |_, _| Ok(random(&state)));
+}
+
+fn define<F>(environment: &mut Environment, name: &str, arity: usize, body: F)
+where
+    F: Fn(&mut Interpreter, &[Object]) -> Result<Object, Error> + 'static,
+{
+    environment.define(
+        name.to_string(),
+        Object::Callable(Function::native(name, arity, body)),
+    );
+}
+
+fn type_error<R>(name: &str, expected: &str, got: &Object) -> Result<R, Error> {
+    Err(Error::Native(format!(
+        "{}: expected {} but got {}.",
+        name,
+        expected,
+        got.type_name()
+    )))
+}
+
+fn string_arg<'a>(name: &str, args: &'a [Object], index: usize) -> Result<&'a str, Error> {
+    match &args[index] {
+        Object::String(s) => Ok(s),
+        other => type_error(name, "string", other),
+    }
+}
+
+fn number_arg(name: &str, args: &[Object], index: usize) -> Result<f64, Error> {
+    match &args[index] {
+        Object::Number(n) => Ok(*n),
+        other => type_error(name, "number", other),
+    }
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Could not retrieve time.")
+        .as_secs_f64()
+}
+
+fn clock(_args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Number((unix_now() * 1000.0).floor()))
+}
+
+fn now(_args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Number(unix_now().floor()))
+}
+
+// Days since 1970-01-01 to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn format_time(args: &[Object]) -> Result<Object, Error> {
+    let timestamp = number_arg("format_time", args, 0)?.floor() as i64;
+    let pattern = string_arg("format_time", args, 1)?;
+
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86_400));
+    let seconds = timestamp.rem_euclid(86_400);
+    let (hour, minute, second) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+
+    let mut result = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => result.push_str(&format!("{:04}", year)),
+            Some('m') => result.push_str(&format!("{:02}", month)),
+            Some('d') => result.push_str(&format!("{:02}", day)),
+            Some('H') => result.push_str(&format!("{:02}", hour)),
+            Some('M') => result.push_str(&format!("{:02}", minute)),
+            Some('S') => result.push_str(&format!("{:02}", second)),
+            Some('%') => result.push('%'),
+            Some(other) => {
+                return Err(Error::Native(format!(
+                    "format_time: unknown format specifier '%{}'.",
+                    other
+                )))
+            }
+            None => {
+                return Err(Error::Native(
+                    "format_time: dangling '%' at end of format.".to_string(),
+                ))
+            }
+        }
+    }
+    Ok(Object::String(result))
+}
+
+fn len(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::String(s) => Ok(Object::Number(s.chars().count() as f64)),
+        Object::List(items) => Ok(Object::Number(items.len() as f64)),
+        other => type_error("len", "string or list", other),
+    }
+}
+
+fn upper(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(string_arg("upper", args, 0)?.to_uppercase()))
+}
+
+fn lower(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(string_arg("lower", args, 0)?.to_lowercase()))
+}
+
+fn trim(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(
+        string_arg("trim", args, 0)?.trim().to_string(),
+    ))
+}
+
+fn contains(args: &[Object]) -> Result<Object, Error> {
+    match &args[0] {
+        Object::String(s) => {
+            let needle = string_arg("contains", args, 1)?;
+            Ok(Object::Boolean(s.contains(needle)))
+        }
+        Object::List(items) => Ok(Object::Boolean(
+            items.iter().any(|item| item.equals(&args[1])),
+        )),
+        other => type_error("contains", "string or list", other),
+    }
+}
+
+fn split(args: &[Object]) -> Result<Object, Error> {
+    let s = string_arg("split", args, 0)?;
+    let separator = string_arg("split", args, 1)?;
+    let parts: Vec<Object> = if separator.is_empty() {
+        s.chars().map(|c| Object::String(c.to_string())).collect()
+    } else {
+        s.split(separator)
+            .map(|part| Object::String(part.to_string()))
+            .collect()
+    };
+    Ok(Object::List(Rc::new(parts)))
+}
+
+fn replace(args: &[Object]) -> Result<Object, Error> {
+    let s = string_arg("replace", args, 0)?;
+    let from = string_arg("replace", args, 1)?;
+    let to = string_arg("replace", args, 2)?;
+    if from.is_empty() {
+        return Err(Error::Native(
+            "replace: pattern must not be empty.".to_string(),
+        ));
+    }
+    Ok(Object::String(s.replace(from, to)))
+}
+
+fn floor(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Number(number_arg("floor", args, 0)?.floor()))
+}
+
+fn round(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Number(number_arg("round", args, 0)?.round()))
+}
+
+fn parse(args: &[Object]) -> Result<Object, Error> {
+    let s = string_arg("parse", args, 0)?;
+    s.trim()
+        .parse::<f64>()
+        .map(Object::Number)
+        .map_err(|_| Error::Native(format!("parse: '{}' is not a number.", s)))
+}
+
+fn to_string(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(args[0].to_string()))
+}
+
+fn get(args: &[Object]) -> Result<Object, Error> {
+    match (&args[0], &args[1]) {
+        (Object::List(items), Object::Number(n)) => {
+            if n.fract() != 0.0 || *n < 0.0 || *n as usize >= items.len() {
+                return Err(Error::Native(format!(
+                    "get: index {} out of range for list of length {}.",
+                    n,
+                    items.len()
+                )));
+            }
+            Ok(items[*n as usize].clone())
+        }
+        (Object::Map(entries), key @ (Object::String(_) | Object::Number(_))) => Ok(entries
+            .get(&key.to_string())
+            .cloned()
+            .unwrap_or(Object::Null)),
+        (Object::List(_), other) => type_error("get", "number index", other),
+        (Object::Map(_), other) => type_error("get", "string or number key", other),
+        (other, _) => type_error("get", "list or map", other),
+    }
+}
+
+fn closest(args: &[Object]) -> Result<Object, Error> {
+    let input = string_arg("closest", args, 0)?;
+    let options = list_arg("closest", args, 1)?;
+    let mut candidates: Vec<&str> = Vec::with_capacity(options.len());
+    for option in options {
+        match option {
+            Object::String(s) => candidates.push(s),
+            other => return type_error("closest", "list of strings", other),
+        }
+    }
+    Ok(intent::closest(input, candidates)
+        .map(|index| options[index].clone())
+        .unwrap_or(Object::Null))
+}
+
+fn type_of(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(args[0].type_name().to_string()))
+}
+
+fn is_bool(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Boolean(matches!(args[0], Object::Boolean(_))))
+}
+
+fn is_list(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Boolean(matches!(args[0], Object::List(_))))
+}
+
+fn is_nil(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Boolean(matches!(args[0], Object::Null)))
+}
+
+fn is_number(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Boolean(matches!(args[0], Object::Number(_))))
+}
+
+fn is_step(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Boolean(matches!(args[0], Object::Callable(_))))
+}
+
+fn is_string(args: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Boolean(matches!(args[0], Object::String(_))))
+}
+
+fn list_arg<'a>(name: &str, args: &'a [Object], index: usize) -> Result<&'a [Object], Error> {
+    match &args[index] {
+        Object::List(items) => Ok(items),
+        other => type_error(name, "list", other),
+    }
+}
+
+fn map(interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, Error> {
+    let items = list_arg("map", args, 0)?;
+    let mapped: Result<Vec<Object>, Error> = items
+        .iter()
+        .map(|item| interpreter.call(&args[1], std::slice::from_ref(item)))
+        .collect();
+    Ok(Object::List(Rc::new(mapped?)))
+}
+
+fn filter(interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, Error> {
+    let items = list_arg("filter", args, 0)?;
+    let mut kept = Vec::new();
+    for item in items {
+        let keep = interpreter.call(&args[1], std::slice::from_ref(item))?;
+        if !matches!(keep, Object::Null | Object::Boolean(false)) {
+            kept.push(item.clone());
+        }
+    }
+    Ok(Object::List(Rc::new(kept)))
+}
+
+fn matches(interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, Error> {
+    let s = string_arg("matches", args, 0)?;
+    let regex = interpreter.regex(string_arg("matches", args, 1)?)?;
+    let captures = match regex.captures(s) {
+        Some(captures) => captures,
+        None => return Ok(Object::Null),
+    };
+
+    let group = |m: Option<regex::Match>| {
+        m.map(|m| Object::String(m.as_str().to_string()))
+            .unwrap_or(Object::Null)
+    };
+    let mut groups: BTreeMap<String, Object> = BTreeMap::new();
+    for (index, m) in captures.iter().enumerate() {
+        groups.insert(index.to_string(), group(m));
+    }
+    for name in regex.capture_names().flatten() {
+        groups.insert(name.to_string(), group(captures.name(name)));
+    }
+    Ok(Object::Map(Rc::new(groups)))
+}
+
+fn find_all(interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, Error> {
+    let s = string_arg("find_all", args, 0)?;
+    let regex = interpreter.regex(string_arg("find_all", args, 1)?)?;
+    let found: Vec<Object> = regex
+        .find_iter(s)
+        .map(|m| Object::String(m.as_str().to_string()))
+        .collect();
+    Ok(Object::List(Rc::new(found)))
+}
+
+fn store(interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, Error> {
+    let key = string_arg("store", args, 0)?;
+    interpreter.store(key, &args[1])?;
+    Ok(Object::Null)
+}
+
+fn load(interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, Error> {
+    interpreter.load(string_arg("load", args, 0)?)
+}
+
+fn seed(state: &Cell<u64>, args: &[Object]) -> Result<Object, Error> {
+    let n = number_arg("seed", args, 0)?;
+    // xorshift64* gets stuck on a zero state, so map it away.
+    let mixed = (n as i64 as u64) ^ 0x9E37_79B9_7F4A_7C15;
+    state.set(if mixed == 0 { 1 } else { mixed });
+    Ok(Object::Null)
+}
+
+fn random(state: &Cell<u64>) -> Object {
+    let mut x = state.get();
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    state.set(x);
+    let next = x.wrapping_mul(0x2545_F491_4F6C_DD1D);
+    // Use the top 53 bits to build a float in [0, 1).
+    Object::Number((next >> 11) as f64 / (1u64 << 53) as f64)
+}
@@ -0,0 +1,234 @@
+use crate::error::Error;
+use crate::object::Object;
+
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 临时文件名中的序号，保证同一进程中的临时文件互不相同
+static TEMPORARY: AtomicU64 = AtomicU64::new(0);
+
+///
+/// 按用户保存键值数据的存储，脚本中的 store 和 load 经由它读写
+///
+/// 值以 JSON 表示，只能保存 nil、布尔值、数字、字符串以及由它们组成的列表和映射。
+///
+pub trait Storage {
+    ///
+    /// 读取某个用户保存的值
+    ///
+    /// # 参数列表
+    /// * user: 宿主程序提供的用户标识
+    /// * key: 键
+    ///
+    /// # 返回值
+    /// * 保存过的值，没有保存过时为 None
+    ///
+    fn load(&self, user: &str, key: &str) -> Result<Option<Value>, Error>;
+
+    ///
+    /// 为某个用户保存一个值，覆盖原有的值
+    ///
+    /// # 参数列表
+    /// * user: 宿主程序提供的用户标识
+    /// * key: 键
+    /// * value: 值
+    ///
+    fn store(&mut self, user: &str, key: &str, value: Value) -> Result<(), Error>;
+}
+
+type Records = BTreeMap<String, BTreeMap<String, Value>>;
+
+///
+/// 保存在内存中的存储，进程退出后数据丢失，解释器默认使用它
+///
+/// 克隆得到的存储共享同一份数据，因此多个会话可以共用一个存储，
+/// 不同线程里的会话也可以共用。
+///
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    records: Arc<Mutex<Records>>,
+}
+
+impl MemoryStorage {
+    ///
+    /// 创建空的存储
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self, user: &str, key: &str) -> Result<Option<Value>, Error> {
+        Ok(self
+            .records
+            .lock()
+            .unwrap()
+            .get(user)
+            .and_then(|values| values.get(key))
+            .cloned())
+    }
+
+    fn store(&mut self, user: &str, key: &str, value: Value) -> Result<(), Error> {
+        self.records
+            .lock()
+            .unwrap()
+            .entry(user.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+///
+/// 保存在本地 JSON 文件中的存储
+///
+/// 文件内容形如 `{"adam": {"bill": 30}}`，每次保存都先写入临时文件再替换原文件，
+/// 进程中途退出时不会留下写了一半的文件。
+/// 保存时持有进程内的锁和 `<path>.lock` 文件锁，
+/// 多个线程或多个进程同时保存时不会丢失彼此的修改。
+///
+/// # 使用示例
+/// let interpreter = Interpreter::builder()
+///     .storage("adam", FileStorage::new("balances.json"))
+///     .build();
+///
+#[derive(Clone)]
+pub struct FileStorage {
+    path: PathBuf,
+    /// 克隆得到的存储共享同一把锁
+    lock: Arc<Mutex<()>>,
+}
+
+impl FileStorage {
+    ///
+    /// 创建存储，文件不存在时在第一次保存时创建
+    ///
+    /// # 参数列表
+    /// * path: JSON 文件路径
+    ///
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FileStorage {
+            path: path.as_ref().to_path_buf(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    ///
+    /// 与存储文件在同一目录下、文件名加上后缀的路径
+    ///
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    fn read(&self) -> Result<Records, Error> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Records::new()),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&text).map_err(|e| {
+            Error::Native(format!(
+                "Storage file '{}' is corrupted: {}.",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self, user: &str, key: &str) -> Result<Option<Value>, Error> {
+        let mut records = self.read()?;
+        Ok(records.get_mut(user).and_then(|values| values.remove(key)))
+    }
+
+    fn store(&mut self, user: &str, key: &str, value: Value) -> Result<(), Error> {
+        // 先取进程内的锁，再取文件锁，读取、修改和写回之间不会有其他写入者
+        let _guard = self
+            .lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let lock = File::create(self.sibling(".lock"))?;
+        lock.lock()?;
+
+        let mut records = self.read()?;
+        records
+            .entry(user.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+        let text = serde_json::to_string_pretty(&records)
+            .map_err(|e| Error::Native(format!("Cannot encode storage: {}.", e)))?;
+        let temporary = self.sibling(&format!(
+            ".{}-{}.tmp",
+            process::id(),
+            TEMPORARY.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temporary, text)?;
+        if let Err(e) = fs::rename(&temporary, &self.path) {
+            let _ = fs::remove_file(&temporary);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+///
+/// 把 dsl 对象转换为可以保存的 JSON 值
+///
+/// # 返回值
+/// * JSON 值
+/// * 对象是 step、模块或状态，或数字不是有限值时返回 Error::Native
+///
+pub fn to_json(value: &Object) -> Result<Value, Error> {
+    Ok(match value {
+        Object::Null => Value::Null,
+        Object::Boolean(b) => Value::Bool(*b),
+        Object::Number(n) => match Number::from_f64(*n) {
+            Some(number) => Value::Number(number),
+            None => return Err(Error::Native(format!("Cannot store the number {}.", n))),
+        },
+        Object::String(s) => Value::String(s.clone()),
+        Object::List(items) => Value::Array(items.iter().map(to_json).collect::<Result<_, _>>()?),
+        Object::Map(entries) => Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), to_json(value)?)))
+                .collect::<Result<Map<_, _>, Error>>()?,
+        ),
+        other => {
+            return Err(Error::Native(format!(
+                "Cannot store a value of type {}.",
+                other.type_name()
+            )))
+        }
+    })
+}
+
+///
+/// 把保存的 JSON 值转换为 dsl 对象
+///
+pub fn from_json(value: Value) -> Object {
+    match value {
+        Value::Null => Object::Null,
+        Value::Bool(b) => Object::Boolean(b),
+        Value::Number(n) => Object::Number(n.as_f64().unwrap_or(f64::NAN)),
+        Value::String(s) => Object::String(s),
+        Value::Array(items) => Object::List(Rc::new(items.into_iter().map(from_json).collect())),
+        Value::Object(entries) => Object::Map(Rc::new(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key, from_json(value)))
+                .collect(),
+        )),
+    }
+}
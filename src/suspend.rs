@@ -0,0 +1,76 @@
+use crate::env::Environment;
+use crate::function::Function;
+use crate::state::State;
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+///
+/// 暂停位置中的一层
+///
+/// 执行因等待输入而暂停时，错误沿调用链向外传播，
+/// 途经的每个语句记录下自己执行到了哪里，从内到外依次压入。
+/// 恢复执行时从外到内依次取出，每个语句跳过已经执行过的部分，
+/// 回到暂停处重新读取输入。
+///
+#[derive(Clone)]
+pub enum Frame {
+    /// 语句列表中正在执行的语句下标
+    Statement(usize),
+    /// 块语句新建的环境
+    Scope(Rc<RefCell<Environment>>),
+    /// 条件成立、正在执行的分支语句
+    Branch,
+    /// 作为语句调用的 step 及其参数环境
+    Call {
+        /// 被调用的 step
+        function: Function,
+        /// 绑定了参数的环境
+        environment: Rc<RefCell<Environment>>,
+    },
+    /// match 语句中正在执行的分支，None 为默认分支
+    Arm(Option<usize>),
+    /// 菜单语句
+    Menu {
+        /// 已经求值的提示语
+        prompt: Option<String>,
+        /// 菜单所处的阶段
+        phase: MenuPhase,
+    },
+    /// 状态机
+    Machine {
+        /// 当前状态
+        state: Rc<State>,
+        /// 当前状态所处的阶段
+        phase: MachinePhase,
+        /// enter 块或输入分支的环境，等待输入时为 None
+        environment: Option<Rc<RefCell<Environment>>>,
+    },
+}
+
+///
+/// 菜单语句的阶段
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MenuPhase {
+    /// 已列出选项，等待用户选择
+    Choosing,
+    /// 已询问“你是不是想说”，等待用户确认该选项
+    Confirming(usize),
+    /// 正在执行选中的选项
+    Running(usize),
+}
+
+///
+/// 状态机中当前状态的阶段
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MachinePhase {
+    /// 正在执行 enter 块
+    Enter,
+    /// 等待用户输入
+    Waiting,
+    /// 正在执行输入分支，None 为默认分支
+    Handler(Option<usize>),
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+///
+/// 驻留后的标识符
+///
+/// 相同的名字总是得到相同的符号，比较和哈希只涉及一个整数，
+/// 环境以符号为键，查找变量时不必再对字符串求哈希或分配内存。
+/// 驻留表是进程级的，服务模式下不同线程得到的符号可以互相比较。
+///
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    names: Vec<&'static str>,
+}
+
+fn interner() -> &'static Mutex<Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Symbol {
+    ///
+    /// 驻留一个名字，得到它的符号
+    ///
+    /// # 参数列表
+    /// * name: 名字
+    ///
+    /// # 使用示例
+    /// assert_eq!(Symbol::intern("balance"), Symbol::intern("balance"));
+    ///
+    pub fn intern(name: &str) -> Self {
+        let mut interner = interner().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(&symbol) = interner.symbols.get(name) {
+            return symbol;
+        }
+        // 驻留的名字在进程结束前一直有效，标识符的数量有限
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        let symbol = Symbol(interner.names.len() as u32);
+        interner.names.push(name);
+        interner.symbols.insert(name, symbol);
+        symbol
+    }
+
+    ///
+    /// 查找已经驻留的名字，不会驻留新的名字
+    ///
+    /// 用于按宿主程序或用户给出的名字查找变量：没有驻留过的名字一定不是变量名，
+    /// 不必为它分配一个永远不会释放的符号。
+    ///
+    /// # 参数列表
+    /// * name: 名字
+    ///
+    /// # 返回值
+    /// * 名字的符号，名字没有驻留过时为 None
+    ///
+    pub fn get(name: &str) -> Option<Self> {
+        let interner = interner().lock().unwrap_or_else(|e| e.into_inner());
+        interner.symbols.get(name).copied()
+    }
+
+    ///
+    /// 得到符号对应的名字
+    ///
+    pub fn as_str(self) -> &'static str {
+        let interner = interner().lock().unwrap_or_else(|e| e.into_inner());
+        interner.names[self.0 as usize]
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::intern(&name)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
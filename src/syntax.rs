@@ -1,6 +1,7 @@
 use crate::error::Error;
 use crate::token::Token;
 use std::fmt;
+use std::sync::Arc;
 
 ///
 /// 语法树中表达式的枚举类型，方便递归下降分析
@@ -12,6 +13,7 @@ use std::fmt;
 /// - 函数调用
 /// - 字面量
 /// - 赋值语句
+/// - 模块成员访问
 ///
 #[derive(Debug, Clone)]
 pub enum Expr {
@@ -31,6 +33,13 @@ pub enum Expr {
         /// 参数列表
         arguments: Vec<Expr>,
     },
+    /// 模块成员访问表达式
+    Get {
+        /// 模块表达式
+        object: Box<Expr>,
+        /// 成员名
+        name: Token,
+    },
     /// 二元表达式
     Binary {
         /// 左操作数
@@ -107,11 +116,29 @@ impl Expr {
                 operator,
                 right,
             } => visitor.visit_binary_expr(left, operator, right),
+            Expr::Get { object, name } => visitor.visit_get_expr(object, name),
             Expr::Literal { value } => visitor.visit_literal_expr(value),
             Expr::Unary { operator, right } => visitor.visit_unary_expr(operator, right),
             Expr::Variable { name } => visitor.visit_variable_expr(name),
         }
     }
+
+    ///
+    /// 表达式开始所在的行
+    ///
+    /// # 返回值
+    /// * 行号，字面量没有记录位置，返回 None
+    ///
+    pub fn line(&self) -> Option<i32> {
+        match self {
+            Expr::Assign { name, .. } | Expr::Variable { name } => Some(name.line),
+            Expr::Call { callee, paren, .. } => callee.line().or(Some(paren.line)),
+            Expr::Get { object, name } => object.line().or(Some(name.line)),
+            Expr::Binary { left, operator, .. } => left.line().or(Some(operator.line)),
+            Expr::Unary { operator, .. } => Some(operator.line),
+            Expr::Literal { .. } => None,
+        }
+    }
 }
 
 ///
@@ -131,6 +158,7 @@ pub mod expr {
             right: &Expr,
         ) -> Result<R, Error>;
 
+        fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<R, Error>;
         fn visit_literal_expr(&self, value: &LiteralValue) -> Result<R, Error>;
         fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Result<R, Error>;
         fn visit_variable_expr(&mut self, name: &Token) -> Result<R, Error>;
@@ -138,7 +166,7 @@ pub mod expr {
             &mut self,
             callee: &Expr,
             paren: &Token,
-            arguments: &Vec<Expr>,
+            arguments: &[Expr],
         ) -> Result<R, Error>;
     }
 }
@@ -150,6 +178,7 @@ pub mod expr {
 /// - 表达式语句
 /// - 打印语句
 /// - 变量声明语句
+/// - 常量声明语句
 /// - 块语句
 /// - 输入字符串语句
 /// - 输入数字语句
@@ -158,6 +187,11 @@ pub mod expr {
 /// - 函数声明语句
 /// - 等待语句
 /// - 退出语句
+/// - 导入语句
+/// - 意图匹配语句
+/// - 菜单语句
+/// - 状态声明语句
+/// - 状态转移语句
 ///
 #[derive(Clone)]
 pub enum Stmt {
@@ -173,6 +207,8 @@ pub enum Stmt {
     },
     /// 分支语句
     Branch {
+        /// branch 关键字，用于定位语句所在行
+        keyword: Token,
         /// 分支语句中的条件表达式
         condition: Expr,
         /// 分支语句中的执行语句
@@ -180,6 +216,8 @@ pub enum Stmt {
     },
     /// 循环语句，无限循环
     Loop {
+        /// loop 关键字，用于定位语句所在行
+        keyword: Token,
         /// 循环语句中的执行语句
         body: Box<Stmt>,
     },
@@ -187,13 +225,16 @@ pub enum Stmt {
     Function {
         /// 函数声明语句中的函数名
         name: Token,
-        /// 函数声明语句中的参数列表
-        params: Vec<Token>,
-        /// 函数声明语句中的函数体
-        body: Vec<Stmt>,
+        /// 函数声明语句中的参数列表，与声明出的函数共享；
+        /// 使用 Arc 是因为服务模式会把语法树交给多个线程
+        params: Arc<[Token]>,
+        /// 函数声明语句中的函数体，与声明出的函数共享
+        body: Arc<[Stmt]>,
     },
     /// 打印语句
     Speak {
+        /// speak 关键字，用于定位语句所在行
+        keyword: Token,
         /// 打印语句中的表达式
         expression: Expr,
     },
@@ -209,6 +250,8 @@ pub enum Stmt {
     },
     /// 停止语句
     Listen {
+        /// listen 关键字，用于定位语句所在行
+        keyword: Token,
         /// 停止时间表达式
         time: Expr,
     },
@@ -219,12 +262,109 @@ pub enum Stmt {
         /// 变量声明语句中的变量值
         initializer: Option<Expr>,
     },
+    /// 常量声明语句
+    Const {
+        /// 常量名
+        name: Token,
+        /// 常量值
+        initializer: Expr,
+    },
     /// 退出语句
-    Exit,
+    Exit {
+        /// exit 关键字，用于定位语句所在行
+        keyword: Token,
+    },
+    /// 意图匹配语句
+    Match {
+        /// match 关键字，用于报错定位
+        keyword: Token,
+        /// 被匹配的用户输入
+        subject: Expr,
+        /// 各个意图分支
+        arms: Vec<IntentArm>,
+        /// 没有意图匹配时执行的语句
+        fallback: Option<Box<Stmt>>,
+    },
+    /// 菜单语句，列出带编号的选项并读取用户的选择
+    Menu {
+        /// menu 关键字，用于报错定位
+        keyword: Token,
+        /// 列出选项前输出的提示语
+        prompt: Option<Expr>,
+        /// 各个选项
+        options: Vec<MenuOption>,
+    },
+    /// 状态声明语句
+    State {
+        /// 状态名
+        name: Token,
+        /// 进入状态时执行的语句，声明状态时与运行时的状态共享
+        enter: Arc<[Stmt]>,
+        /// 根据用户输入匹配的分支
+        handlers: Arc<[IntentArm]>,
+        /// 没有分支匹配时执行的语句
+        fallback: Option<Arc<Stmt>>,
+    },
+    /// 状态转移语句
+    Goto {
+        /// goto 关键字，用于报错定位
+        keyword: Token,
+        /// 目标状态名
+        target: Token,
+    },
+    /// 导入语句
+    Import {
+        /// import 关键字，用于报错定位
+        keyword: Token,
+        /// 被导入文件的路径
+        path: String,
+        /// 别名，为空时将模块的定义直接导入当前环境
+        alias: Option<Token>,
+    },
     /// 空语句
     Null,
 }
 
+///
+/// 意图匹配语句的一个分支
+///
+#[derive(Clone)]
+pub struct IntentArm {
+    /// 该意图的关键词及同义词
+    pub patterns: Vec<String>,
+    /// 匹配成功时执行的语句
+    pub body: Stmt,
+}
+
+///
+/// 菜单语句的一个选项
+///
+#[derive(Clone)]
+pub struct MenuOption {
+    /// 选择该选项时可以输入的按键及同义词
+    pub keys: Vec<String>,
+    /// 展示给用户的说明
+    pub label: Option<String>,
+    /// 选中时执行的语句
+    pub body: Stmt,
+}
+
+impl MenuOption {
+    ///
+    /// 选项在菜单中展示的名称，没有说明时使用第一个按键
+    ///
+    pub fn title(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.keys[0])
+    }
+
+    ///
+    /// 可以识别为该选项的全部文本，包括按键和说明
+    ///
+    pub fn synonyms(&self) -> Vec<String> {
+        self.keys.iter().chain(self.label.iter()).cloned().collect()
+    }
+}
+
 impl Stmt {
     pub fn accept<R>(&self, visitor: &mut dyn stmt::Visitor<R>) -> Result<R, Error> {
         match self {
@@ -233,42 +373,153 @@ impl Stmt {
             Stmt::Function { name, params, body } => {
                 visitor.visit_function_stmt(name, params, body)
             }
-            Stmt::Branch { condition, then } => visitor.visit_branch_stmt(condition, then),
-            Stmt::Loop { body } => visitor.visit_loop_stmt(body),
-            Stmt::Speak { expression } => visitor.visit_speak_stmt(expression),
+            Stmt::Branch {
+                condition, then, ..
+            } => visitor.visit_branch_stmt(condition, then),
+            Stmt::Loop { body, .. } => visitor.visit_loop_stmt(body),
+            Stmt::Speak { expression, .. } => visitor.visit_speak_stmt(expression),
             Stmt::Input { input } => visitor.visit_input_stmt(input),
             Stmt::Inputn { input } => visitor.visit_inputn_stmt(input),
-            Stmt::Listen { time } => visitor.visit_listen_stmt(time),
+            Stmt::Listen { keyword, time } => visitor.visit_listen_stmt(keyword, time),
             Stmt::Var { name, initializer } => visitor.visit_var_stmt(name, initializer),
-            Stmt::Exit => visitor.visit_exit_stmt(),
+            Stmt::Const { name, initializer } => visitor.visit_const_stmt(name, initializer),
+            Stmt::Exit { .. } => visitor.visit_exit_stmt(),
+            Stmt::Import {
+                keyword,
+                path,
+                alias,
+            } => visitor.visit_import_stmt(keyword, path, alias),
+            Stmt::Match {
+                keyword,
+                subject,
+                arms,
+                fallback,
+            } => visitor.visit_match_stmt(keyword, subject, arms, fallback),
+            Stmt::Menu {
+                keyword,
+                prompt,
+                options,
+            } => visitor.visit_menu_stmt(keyword, prompt, options),
+            Stmt::State {
+                name,
+                enter,
+                handlers,
+                fallback,
+            } => visitor.visit_state_stmt(name, enter, handlers, fallback),
+            Stmt::Goto { keyword, target } => visitor.visit_goto_stmt(keyword, target),
             Stmt::Null => unimplemented!(),
         }
     }
+
+    ///
+    /// 直接嵌套在语句中的语句，包括 step 函数体和状态的各个处理分支
+    ///
+    pub fn children(&self) -> Vec<&Stmt> {
+        match self {
+            Stmt::Block { statements } => statements.iter().collect(),
+            Stmt::Function { body, .. } => body.iter().collect(),
+            Stmt::Branch { then, .. } => vec![then],
+            Stmt::Loop { body, .. } => vec![body],
+            Stmt::Match { arms, fallback, .. } => arms
+                .iter()
+                .map(|arm| &arm.body)
+                .chain(fallback.as_deref())
+                .collect(),
+            Stmt::Menu { options, .. } => options.iter().map(|option| &option.body).collect(),
+            Stmt::State {
+                enter,
+                handlers,
+                fallback,
+                ..
+            } => enter
+                .iter()
+                .chain(handlers.iter().map(|arm| &arm.body))
+                .chain(fallback.as_deref())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    ///
+    /// 语句开始所在的行，调试器按它设置断点
+    ///
+    /// # 返回值
+    /// * 行号，语句块和只含字面量的表达式语句返回 None
+    ///
+    pub fn line(&self) -> Option<i32> {
+        match self {
+            Stmt::Block { .. } | Stmt::Null => None,
+            Stmt::Expression { expression } => expression.line(),
+            Stmt::Branch { keyword, .. }
+            | Stmt::Loop { keyword, .. }
+            | Stmt::Speak { keyword, .. }
+            | Stmt::Listen { keyword, .. }
+            | Stmt::Exit { keyword }
+            | Stmt::Match { keyword, .. }
+            | Stmt::Menu { keyword, .. }
+            | Stmt::Goto { keyword, .. }
+            | Stmt::Import { keyword, .. } => Some(keyword.line),
+            Stmt::Function { name, .. }
+            | Stmt::Var { name, .. }
+            | Stmt::Const { name, .. }
+            | Stmt::State { name, .. } => Some(name.line),
+            Stmt::Input { input } | Stmt::Inputn { input } => Some(input.line),
+        }
+    }
 }
 
 ///
 /// 语句模块的访问者接口
 ///
 pub mod stmt {
-    use super::{Expr, Stmt};
+    use super::{Expr, IntentArm, MenuOption, Stmt};
     use crate::{error::Error, token::Token};
+    use std::sync::Arc;
 
     pub trait Visitor<R> {
-        fn visit_block_stmt(&mut self, statements: &Vec<Stmt>) -> Result<R, Error>;
+        fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<R, Error>;
         fn visit_expression_stmt(&mut self, expression: &Expr) -> Result<R, Error>;
         fn visit_function_stmt(
             &mut self,
             name: &Token,
-            params: &Vec<Token>,
-            body: &Vec<Stmt>,
+            params: &Arc<[Token]>,
+            body: &Arc<[Stmt]>,
         ) -> Result<R, Error>;
         fn visit_branch_stmt(&mut self, condition: &Expr, then: &Stmt) -> Result<R, Error>;
         fn visit_loop_stmt(&mut self, body: &Stmt) -> Result<R, Error>;
         fn visit_speak_stmt(&mut self, expression: &Expr) -> Result<R, Error>;
         fn visit_input_stmt(&mut self, name: &Token) -> Result<R, Error>;
         fn visit_inputn_stmt(&mut self, name: &Token) -> Result<R, Error>;
-        fn visit_listen_stmt(&mut self, time: &Expr) -> Result<R, Error>;
+        fn visit_listen_stmt(&mut self, keyword: &Token, time: &Expr) -> Result<R, Error>;
         fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) -> Result<R, Error>;
+        fn visit_const_stmt(&mut self, name: &Token, initializer: &Expr) -> Result<R, Error>;
         fn visit_exit_stmt(&mut self) -> Result<R, Error>;
+        fn visit_import_stmt(
+            &mut self,
+            keyword: &Token,
+            path: &str,
+            alias: &Option<Token>,
+        ) -> Result<R, Error>;
+        fn visit_match_stmt(
+            &mut self,
+            keyword: &Token,
+            subject: &Expr,
+            arms: &[IntentArm],
+            fallback: &Option<Box<Stmt>>,
+        ) -> Result<R, Error>;
+        fn visit_menu_stmt(
+            &mut self,
+            keyword: &Token,
+            prompt: &Option<Expr>,
+            options: &[MenuOption],
+        ) -> Result<R, Error>;
+        fn visit_state_stmt(
+            &mut self,
+            name: &Token,
+            enter: &Arc<[Stmt]>,
+            handlers: &Arc<[IntentArm]>,
+            fallback: &Option<Arc<Stmt>>,
+        ) -> Result<R, Error>;
+        fn visit_goto_stmt(&mut self, keyword: &Token, target: &Token) -> Result<R, Error>;
     }
 }
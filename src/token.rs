@@ -1,3 +1,5 @@
+use crate::symbol::Symbol;
+
 use std::fmt;
 extern crate phf;
 
@@ -13,7 +15,10 @@ extern crate phf;
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
+    Comma,
+    Dot,
     Minus,
+    Pipe,
     Plus,
     SemiColon,
     LeftParen,
@@ -22,6 +27,7 @@ pub enum TokenType {
     RightBrace,
 
     // One or two character tokens.
+    Arrow,
     Bang,
     BangEqual,
     Equal,
@@ -42,9 +48,18 @@ pub enum TokenType {
     Exit,
     Input,
     Var,
+    Const,
     Nil,
     True,
     False,
+    Import,
+    As,
+    Match,
+    Intent,
+    Menu,
+    State,
+    On,
+    Goto,
 
     EOF,
 }
@@ -61,6 +76,8 @@ pub struct Token {
     pub tpe: TokenType,
     /// 词素，储存具体词素内容
     pub lexeme: String,
+    /// 驻留后的标识符，作为环境中的变量名；其他种类的词素不驻留，统一为空名字
+    pub symbol: Symbol,
     /// 该词素所在行，方便定位错误位置
     pub line: i32,
 }
@@ -70,9 +87,15 @@ impl Token {
     /// 生成一个新的 Token
     ///
     pub fn new(tpe: TokenType, lexeme: &str, line: i32) -> Self {
+        // 驻留的名字不会释放，只驻留标识符，符号和字面量的数量不受限制
+        let symbol = match tpe {
+            TokenType::Identifier => Symbol::intern(lexeme),
+            _ => Symbol::intern(""),
+        };
         Self {
             tpe,
             lexeme: lexeme.to_string(),
+            symbol,
             line,
         }
     }
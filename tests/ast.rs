@@ -15,13 +15,13 @@ impl AstPrinter {
 
     fn parenthesize(&mut self, name: String, exprs: Vec<&Expr>) -> Result<String, Error> {
         let mut r = String::new();
-        r.push_str("(");
+        r.push('(');
         r.push_str(&name);
         for e in &exprs {
-            r.push_str(" ");
+            r.push(' ');
             r.push_str(&e.accept(self)?);
         }
-        r.push_str(")");
+        r.push(')');
         Ok(r)
     }
 }
@@ -36,6 +36,10 @@ impl expr::Visitor<String> for AstPrinter {
         self.parenthesize(operator.lexeme.clone(), vec![left, right])
     }
 
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<String, Error> {
+        self.parenthesize(format!(".{}", name.lexeme), vec![object])
+    }
+
     fn visit_literal_expr(&self, value: &LiteralValue) -> Result<String, Error> {
         Ok(value.to_string())
     }
@@ -56,7 +60,7 @@ impl expr::Visitor<String> for AstPrinter {
         &mut self,
         _callee: &Expr,
         _paren: &Token,
-        _arguments: &Vec<Expr>,
+        _arguments: &[Expr],
     ) -> Result<String, Error> {
         unimplemented!()
     }
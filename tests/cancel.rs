@@ -0,0 +1,48 @@
+use robot_dsl::{cancel::CancelToken, channel::Transcript, error::Error, interpreter::Interpreter};
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod common;
+
+use common::parse;
+
+fn cancel_later(token: &CancelToken) {
+    let token = token.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        token.cancel();
+    });
+}
+
+#[test]
+fn test_cancel_stops_loops_and_listen() {
+    for source in ["loop {}", "speak \"waiting\"; listen 60;"] {
+        let token = CancelToken::new();
+        let mut interpreter = Interpreter::builder()
+            .channel(Transcript::new(&[]))
+            .cancel_token(token.clone())
+            .build();
+        let started = Instant::now();
+        cancel_later(&token);
+        assert!(matches!(
+            interpreter.interpret(&parse(source)),
+            Err(Error::Cancelled)
+        ));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
+
+#[test]
+fn test_cancel_is_shared_by_clones() {
+    let mut interpreter = Interpreter::new();
+    let token = interpreter.cancel_token();
+    assert!(!token.is_cancelled());
+    token.cancel();
+    assert!(interpreter.cancel_token().is_cancelled());
+
+    assert!(matches!(
+        interpreter.interpret(&parse("loop {}")),
+        Err(Error::Cancelled)
+    ));
+}
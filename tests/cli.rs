@@ -0,0 +1,34 @@
+use std::process::{Command, Output};
+
+mod common;
+
+use common::Workspace;
+
+fn run_script(name: &str, source: &str) -> Output {
+    let workspace = Workspace::new(name, &[("script.dsl", source)]);
+    Command::new(env!("CARGO_BIN_EXE_robot-dsl"))
+        .arg(workspace.path("script.dsl"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_cli_reports_runtime_errors() {
+    let output = run_script("cli-undefined", "speak \"hi\";\nspeak missing;\n");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "[line 2] Undefined variable 'missing'.\n"
+    );
+}
+
+#[test]
+fn test_cli_reports_call_depth_trace() {
+    let output = run_script("cli-depth", "step f() {\n    var x = f();\n}\nf();\n");
+    assert_eq!(output.status.code(), Some(70));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("[line 2] Maximum call depth of"));
+    assert!(stderr.contains("in f called on line 2"));
+    assert!(stderr.contains("in f called on line 4"));
+}
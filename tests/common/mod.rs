@@ -0,0 +1,60 @@
+//! 各个集成测试共用的辅助函数，每个测试只用到其中一部分
+#![allow(dead_code)]
+
+use robot_dsl::{
+    error::Error, interpreter::Interpreter, parser::Parser, scanner::Scanner, syntax::Stmt,
+};
+
+use std::fs;
+use std::path::PathBuf;
+
+pub fn parse(source: &str) -> Vec<Stmt> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    Parser::new(tokens).parse().expect("Failed to parse")
+}
+
+pub fn run(interpreter: &mut Interpreter, source: &str) -> Result<(), Error> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let statements = Parser::new(tokens).parse()?;
+    interpreter.interpret(&statements)
+}
+
+pub fn calculate(interpreter: &mut Interpreter, source: &str) -> Result<String, Error> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let expression = Parser::new(tokens)
+        .calculate()
+        .expect("Failed to calculate");
+    interpreter.interpret_cal(&expression)
+}
+
+/// 临时目录中的一组脚本文件，离开作用域时删除整个目录
+pub struct Workspace {
+    dir: PathBuf,
+}
+
+impl Workspace {
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("robot-dsl-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, source) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        Workspace { dir }
+    }
+
+    pub fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
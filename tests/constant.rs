@@ -0,0 +1,70 @@
+use robot_dsl::{error::Error, interpreter::Interpreter};
+
+mod common;
+
+use common::run;
+
+fn runtime_error(result: Result<(), Error>) -> (i32, String) {
+    match result {
+        Err(Error::Runtime { token, message }) => (token.line, message),
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_const_can_be_read() {
+    let mut interpreter = Interpreter::new();
+    assert!(run(
+        &mut interpreter,
+        "const NAME = \"adam\"; speak \"Hello, \" + NAME;"
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_const_reassignment_is_rejected() {
+    let mut interpreter = Interpreter::new();
+    let source = "const name = \"adam\";\nstep Rename() {\n  name = \"eve\";\n}\nRename();";
+    let (line, message) = runtime_error(run(&mut interpreter, source));
+    assert_eq!(line, 3);
+    assert_eq!(
+        message,
+        "Cannot assign to constant 'name' declared on line 1."
+    );
+}
+
+#[test]
+fn test_const_redeclaration_is_rejected() {
+    let mut interpreter = Interpreter::new();
+    let (_, message) = runtime_error(run(&mut interpreter, "const a = 1;\nvar a = 2;"));
+    assert_eq!(message, "Cannot assign to constant 'a' declared on line 1.");
+
+    let mut interpreter = Interpreter::new();
+    let (_, message) = runtime_error(run(&mut interpreter, "const a = 1;\ninput a;"));
+    assert_eq!(message, "Cannot assign to constant 'a' declared on line 1.");
+}
+
+#[test]
+fn test_const_can_be_shadowed_in_inner_scope() {
+    let mut interpreter = Interpreter::new();
+    assert!(run(&mut interpreter, "const a = 1; { var a = 2; a = 3; }").is_ok());
+}
+
+#[test]
+fn test_host_constant_is_immutable() {
+    let mut interpreter = Interpreter::builder().constant("company", "BUPT").build();
+    let (_, message) = runtime_error(run(&mut interpreter, "company = \"other\";"));
+    assert_eq!(
+        message,
+        "Cannot assign to constant 'company' defined by the host."
+    );
+}
+
+#[test]
+fn test_const_is_reserved() {
+    let mut interpreter = Interpreter::new();
+    assert!(matches!(
+        run(&mut interpreter, "var const = 1;"),
+        Err(Error::Parse)
+    ));
+}
@@ -0,0 +1,90 @@
+use robot_dsl::dap;
+
+use serde_json::Value;
+
+use std::cell::RefCell;
+use std::io::{self, Cursor, Write};
+use std::rc::Rc;
+
+mod common;
+
+use common::Workspace;
+
+const SCRIPT: &str = r#"var name = "world";
+step Greet(who) {
+    speak "Hello, " + who;
+}
+input answer;
+Greet(answer);
+speak "bye";
+"#;
+
+/// 录制下来的调试会话，格式见文件开头的注释
+const SESSION: &str = include_str!("fixtures/dap-session.txt");
+
+/// 客户端输出的缓冲区，运行结束后再取出
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+///
+/// 读取录制的会话，得到客户端的请求和适配器应当发出的消息
+///
+fn transcript(program: &str) -> (Vec<Value>, Vec<Value>) {
+    // 路径写在 JSON 字符串中，替换前先转义
+    let escaped = serde_json::to_string(program).unwrap();
+    let escaped = &escaped[1..escaped.len() - 1];
+    let (mut requests, mut messages) = (Vec::new(), Vec::new());
+    for line in SESSION.lines() {
+        let line = line.replace("${program}", escaped);
+        match line.split_at_checked(3) {
+            Some(("-> ", json)) => requests.push(serde_json::from_str(json).unwrap()),
+            Some(("<- ", json)) => messages.push(serde_json::from_str(json).unwrap()),
+            _ if line.is_empty() || line.starts_with('#') => (),
+            _ => panic!("unexpected line in the transcript: {}", line),
+        }
+    }
+    (requests, messages)
+}
+
+///
+/// 按顺序回放客户端请求，返回适配器发出的全部消息
+///
+fn replay(requests: &[Value]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for request in requests {
+        dap::write_message(&mut input, request).unwrap();
+    }
+    let output = Output::default();
+    dap::run(Cursor::new(input), output.clone()).unwrap();
+
+    let bytes = output.0.borrow().clone();
+    let mut reader = Cursor::new(bytes);
+    let mut messages = Vec::new();
+    while let Some(message) = dap::read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+    messages
+}
+
+#[test]
+fn test_dap_session() {
+    let workspace = Workspace::new("dap", &[("greet.dsl", SCRIPT)]);
+    let program = workspace.path("greet.dsl").to_string_lossy().to_string();
+    let (requests, expected) = transcript(&program);
+
+    let messages = replay(&requests);
+    assert_eq!(messages.len(), expected.len());
+    for (message, expected) in messages.iter().zip(&expected) {
+        assert_eq!(message, expected);
+    }
+}
@@ -0,0 +1,213 @@
+use robot_dsl::{
+    channel::Transcript,
+    debug::{Breakpoints, CommandLine, Context, Debugger, Frontend, Reason, Resume, StackFrame},
+    error::Error,
+    interpreter::Interpreter,
+};
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+mod common;
+
+use common::parse;
+
+const SCRIPT: &str = r#"var total = 0;
+step Add(amount) {
+    var doubled = amount + amount;
+    total = total + doubled;
+}
+Add(1);
+Add(2);
+speak "total " + total;
+"#;
+
+const TAIL: &str = r#"step Main(n) {
+    speak "main " + n;
+    Billing(n);
+}
+step Billing(n) {
+    speak "billing " + n;
+}
+Main(1);
+speak "done";
+"#;
+
+type Stops = Rc<RefCell<Vec<(Reason, i32, usize)>>>;
+type Stacks = Rc<RefCell<Vec<Vec<StackFrame>>>>;
+
+/// 按预先给定的方式继续执行，并记录每次暂停
+struct Recorder {
+    resumes: VecDeque<Resume>,
+    stops: Stops,
+    stacks: Stacks,
+}
+
+impl Frontend for Recorder {
+    fn stopped(
+        &mut self,
+        reason: Reason,
+        context: &Context<'_>,
+        _breakpoints: &mut Breakpoints,
+    ) -> Result<Resume, Error> {
+        self.stops
+            .borrow_mut()
+            .push((reason, context.line, context.depth()));
+        self.stacks.borrow_mut().push(context.stack());
+        Ok(self.resumes.pop_front().unwrap_or(Resume::Continue))
+    }
+}
+
+fn debug(resumes: &[Resume], breakpoints: Option<Breakpoints>) -> (Stops, Stacks) {
+    debug_source(SCRIPT, resumes, breakpoints)
+}
+
+fn debug_source(
+    source: &str,
+    resumes: &[Resume],
+    breakpoints: Option<Breakpoints>,
+) -> (Stops, Stacks) {
+    let stops = Rc::default();
+    let stacks = Rc::default();
+    let recorder = Recorder {
+        resumes: resumes.iter().copied().collect(),
+        stops: Rc::clone(&stops),
+        stacks: Rc::clone(&stacks),
+    };
+    let debugger = match breakpoints {
+        Some(breakpoints) => Debugger::new(recorder)
+            .breakpoints(breakpoints)
+            .stop_on_entry(false),
+        None => Debugger::new(recorder),
+    };
+    let mut interpreter = Interpreter::builder()
+        .channel(Transcript::new(&[]))
+        .debug_hook(debugger)
+        .build();
+    interpreter.interpret(&parse(source)).unwrap();
+    (stops, stacks)
+}
+
+#[test]
+fn test_debugger_steps_in_over_and_out() {
+    let (stops, _) = debug(
+        &[
+            Resume::StepOver,
+            Resume::StepOver,
+            Resume::StepIn,
+            Resume::StepOver,
+            Resume::StepOut,
+            Resume::StepOver,
+        ],
+        None,
+    );
+    assert_eq!(
+        *stops.borrow(),
+        vec![
+            (Reason::Entry, 1, 0),
+            (Reason::Stepped, 2, 0),
+            (Reason::Stepped, 6, 0),
+            (Reason::Stepped, 3, 1),
+            (Reason::Stepped, 4, 1),
+            (Reason::Stepped, 7, 0),
+            (Reason::Stepped, 8, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_debugger_stops_at_line_and_step_breakpoints() {
+    let mut breakpoints = Breakpoints::default();
+    breakpoints.add_step("Add");
+    breakpoints.add_line(8);
+    let (stops, stacks) = debug(&[], Some(breakpoints));
+    assert_eq!(
+        *stops.borrow(),
+        vec![
+            (Reason::Step("Add".to_string()), 3, 1),
+            (Reason::Step("Add".to_string()), 3, 1),
+            (Reason::Breakpoint, 8, 0),
+        ]
+    );
+    let frame = |name: &str, line| StackFrame {
+        name: name.to_string(),
+        line,
+    };
+    assert_eq!(
+        stacks.borrow()[1],
+        vec![frame("Add", 3), frame("<script>", 7)]
+    );
+}
+
+#[test]
+fn test_debugger_follows_tail_calls() {
+    let mut breakpoints = Breakpoints::default();
+    breakpoints.add_step("Billing");
+    let (stops, stacks) = debug_source(TAIL, &[], Some(breakpoints));
+    assert_eq!(
+        *stops.borrow(),
+        vec![(Reason::Step("Billing".to_string()), 6, 1)]
+    );
+    assert_eq!(stacks.borrow()[0][0].name, "Billing");
+
+    // 越过尾调用要等被调用的 step 返回，而不是停在它的第一条语句
+    let mut breakpoints = Breakpoints::default();
+    breakpoints.add_line(3);
+    let (stops, _) = debug_source(TAIL, &[Resume::StepOver], Some(breakpoints));
+    assert_eq!(
+        *stops.borrow(),
+        vec![(Reason::Breakpoint, 3, 1), (Reason::Stepped, 9, 0)]
+    );
+
+    let steps = [
+        Resume::StepOver,
+        Resume::StepOver,
+        Resume::StepIn,
+        Resume::StepIn,
+        Resume::StepIn,
+    ];
+    let (stops, _) = debug_source(TAIL, &steps, None);
+    assert_eq!(
+        *stops.borrow(),
+        vec![
+            (Reason::Entry, 1, 0),
+            (Reason::Stepped, 5, 0),
+            (Reason::Stepped, 8, 0),
+            (Reason::Stepped, 2, 1),
+            (Reason::Stepped, 3, 1),
+            (Reason::Stepped, 6, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_command_line_frontend() {
+    let commands = Transcript::new(&["break 4", "c", "p doubled", "locals", "bt", "q"]);
+    let frontend = CommandLine::new(commands.clone()).source(SCRIPT);
+    let mut interpreter = Interpreter::builder()
+        .channel(Transcript::new(&[]))
+        .debug_hook(Debugger::new(frontend))
+        .build();
+    interpreter.interpret(&parse(SCRIPT)).unwrap();
+    assert!(interpreter.has_exited());
+    assert_eq!(
+        commands.outputs(),
+        vec![
+            "Paused at entry at line 1",
+            "   1  var total = 0;",
+            "Breakpoint at line 4",
+            "Breakpoint in Add at line 4",
+            "   4      total = total + doubled;",
+            "doubled = 2",
+            "locals:",
+            "  amount = 1",
+            "  doubled = 2",
+            "globals:",
+            "  Add = <fn Add>",
+            "  total = 0",
+            "#0 Add line 4",
+            "#1 <script> line 6",
+        ]
+    );
+}
@@ -4,7 +4,7 @@ use robot_dsl::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
 fn test_interpreter_cal_binary() {
     let mut scanner = Scanner::new("1 + 2 + 3".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -17,7 +17,7 @@ fn test_interpreter_cal_binary() {
 fn test_interpreter_cal_unary_true() {
     let mut scanner = Scanner::new("!1".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -30,7 +30,7 @@ fn test_interpreter_cal_unary_true() {
 fn test_interpreter_cal_unary_false() {
     let mut scanner = Scanner::new("!0".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -43,7 +43,7 @@ fn test_interpreter_cal_unary_false() {
 fn test_interpreter_binary_equal() {
     let mut scanner = Scanner::new("1 == 1".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -56,7 +56,7 @@ fn test_interpreter_binary_equal() {
 fn test_interpreter_binary_not_equal() {
     let mut scanner = Scanner::new("1 != 1".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -69,7 +69,7 @@ fn test_interpreter_binary_not_equal() {
 fn test_interpreter_binary_plus1() {
     let mut scanner = Scanner::new("1 + 1".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -82,7 +82,7 @@ fn test_interpreter_binary_plus1() {
 fn test_interpreter_binary_plus2() {
     let mut scanner = Scanner::new("1 + 1 + 1".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -95,7 +95,7 @@ fn test_interpreter_binary_plus2() {
 fn test_interpreter_binary_minus1() {
     let mut scanner = Scanner::new("1 - 1".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -108,7 +108,7 @@ fn test_interpreter_binary_minus1() {
 fn test_interpreter_binary_minus2() {
     let mut scanner = Scanner::new("1 - 1 - 1".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -121,7 +121,7 @@ fn test_interpreter_binary_minus2() {
 fn test_interperter_call_primary_number() {
     let mut scanner = Scanner::new("1".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -134,7 +134,7 @@ fn test_interperter_call_primary_number() {
 fn test_interpreter_call_primary_string() {
     let mut scanner = Scanner::new("\"hello\"".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -147,7 +147,7 @@ fn test_interpreter_call_primary_string() {
 fn test_interpreter_call_primary_true() {
     let mut scanner = Scanner::new("true".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -160,7 +160,7 @@ fn test_interpreter_call_primary_true() {
 fn test_interpreter_call_primary_false() {
     let mut scanner = Scanner::new("false".to_string());
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     if let Some(expression) = parser.calculate() {
         let mut interpreter = Interpreter::new();
         assert!(interpreter.interpret_cal(&expression).is_ok());
@@ -168,4 +168,3 @@ fn test_interpreter_call_primary_false() {
         assert_eq!(res, "false");
     }
 }
-
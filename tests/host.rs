@@ -0,0 +1,83 @@
+use robot_dsl::{error::Error, interpreter::Interpreter};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+mod common;
+
+use common::{calculate, run};
+
+#[test]
+fn test_host_function_typed_arguments() {
+    let mut interpreter = Interpreter::builder()
+        .function("lookup_account", |id: String| format!("account {}", id))
+        .function("add", |a: f64, b: f64| a + b)
+        .function("tickets", || vec!["T-1", "T-2"])
+        .build();
+
+    assert_eq!(
+        calculate(&mut interpreter, "lookup_account(\"42\")").unwrap(),
+        "account 42"
+    );
+    assert_eq!(calculate(&mut interpreter, "add(1, 2)").unwrap(), "3");
+    assert_eq!(
+        calculate(&mut interpreter, "tickets()").unwrap(),
+        "[T-1, T-2]"
+    );
+}
+
+#[test]
+fn test_host_function_captures_host_state() {
+    let created: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&created);
+    let mut interpreter = Interpreter::builder()
+        .function("create_ticket", move |subject: String, priority: i64| {
+            sink.borrow_mut().push(format!("{}:{}", priority, subject));
+        })
+        .build();
+
+    assert!(run(&mut interpreter, "create_ticket(\"no signal\", 2);").is_ok());
+    assert_eq!(*created.borrow(), vec!["2:no signal".to_string()]);
+}
+
+#[test]
+fn test_host_constant() {
+    let mut interpreter = Interpreter::builder()
+        .constant("company", "BUPT Telecom")
+        .constant("vip", true)
+        .constant("discount", Some(0.5))
+        .build();
+
+    assert_eq!(
+        calculate(&mut interpreter, "company").unwrap(),
+        "BUPT Telecom"
+    );
+    assert_eq!(calculate(&mut interpreter, "vip").unwrap(), "true");
+    assert_eq!(calculate(&mut interpreter, "discount").unwrap(), "0.5");
+}
+
+#[test]
+fn test_host_function_argument_error() {
+    let mut interpreter = Interpreter::builder()
+        .function("add", |a: f64, b: f64| a + b)
+        .function("check", |amount: f64| {
+            if amount > 0.0 {
+                Ok(amount)
+            } else {
+                Err("amount must be positive.".to_string())
+            }
+        })
+        .build();
+
+    match calculate(&mut interpreter, "add(1, \"2\")") {
+        Err(Error::Runtime { message, .. }) => {
+            assert_eq!(message, "add: argument 2 expected number but got string.")
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    match calculate(&mut interpreter, "check(0 - 1)") {
+        Err(Error::Runtime { message, .. }) => assert_eq!(message, "amount must be positive."),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(calculate(&mut interpreter, "add(1)").is_err());
+}
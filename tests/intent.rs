@@ -0,0 +1,71 @@
+use robot_dsl::{error::Error, host::IntoObject, interpreter::Interpreter};
+
+mod common;
+
+use common::{calculate, run};
+
+const BOT: &str = r#"
+var chosen = "none";
+match intent (reply) {
+    "check balance" | "balance" | "查询余额" => chosen = "billing";
+    "recharge" | "top up" => { chosen = "charging"; }
+    _ => chosen = "unknown";
+}
+"#;
+
+fn choose(interpreter: &mut Interpreter, reply: &str) -> String {
+    interpreter
+        .globals
+        .borrow_mut()
+        .define("reply".to_string(), reply.into_object());
+    run(interpreter, BOT).unwrap();
+    calculate(interpreter, "chosen").unwrap()
+}
+
+#[test]
+fn test_match_intent_natural_sentences() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        choose(&mut interpreter, "Can I check my BALANCE?"),
+        "billing"
+    );
+    assert_eq!(
+        choose(&mut interpreter, "I'd like to top-up, please"),
+        "charging"
+    );
+    assert_eq!(choose(&mut interpreter, "我想查询余额。"), "billing");
+    assert_eq!(choose(&mut interpreter, "ＲＥＣＨＡＲＧＥ"), "charging");
+}
+
+#[test]
+fn test_match_intent_fallback() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(choose(&mut interpreter, "tell me a joke"), "unknown");
+}
+
+#[test]
+fn test_match_intent_threshold() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(choose(&mut interpreter, "recharg"), "charging");
+
+    let mut strict = Interpreter::builder().intent_threshold(0.95).build();
+    assert_eq!(choose(&mut strict, "recharg"), "unknown");
+}
+
+#[test]
+fn test_match_intent_requires_string() {
+    let mut interpreter = Interpreter::new();
+    let source = "match intent (1) { \"a\" => speak 1; }";
+    assert!(run(&mut interpreter, source).is_err());
+}
+
+#[test]
+fn test_match_and_intent_are_reserved() {
+    for word in ["match", "intent"] {
+        let source = format!("var {} = 1;", word);
+        assert!(matches!(
+            run(&mut Interpreter::new(), &source),
+            Err(Error::Parse)
+        ));
+    }
+}
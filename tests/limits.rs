@@ -0,0 +1,137 @@
+use robot_dsl::{
+    channel::Transcript,
+    error::Error,
+    interpreter::Interpreter,
+    limits::{LimitExceeded, Limits},
+    program::Program,
+    session::{Session, Status},
+};
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+mod common;
+
+use common::parse;
+
+fn exceeded(limits: Limits, source: &str) -> LimitExceeded {
+    let mut interpreter = Interpreter::builder()
+        .channel(Transcript::new(&[]))
+        .limits(limits)
+        .build();
+    match interpreter.interpret(&parse(source)) {
+        Err(Error::Limit(exceeded)) => exceeded,
+        other => panic!("expected a limit error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_limits_stop_runaway_scripts() {
+    let statements = Limits {
+        statements: Some(1000),
+        ..Limits::default()
+    };
+    assert_eq!(
+        exceeded(statements, "loop {}"),
+        LimitExceeded::Statements(1000)
+    );
+
+    let time = Limits {
+        time: Some(Duration::from_millis(50)),
+        ..Limits::default()
+    };
+    assert_eq!(
+        exceeded(time, "var i = 0; loop { i = i + 1; }"),
+        LimitExceeded::Time(Duration::from_millis(50))
+    );
+
+    let output = Limits {
+        output: Some(100),
+        ..Limits::default()
+    };
+    assert_eq!(
+        exceeded(output, "loop speak \"hello\";"),
+        LimitExceeded::Output(100)
+    );
+}
+
+#[test]
+fn test_limits_bound_value_sizes() {
+    let limits = Limits {
+        string_length: Some(1000),
+        collection_length: Some(5),
+        ..Limits::default()
+    };
+    assert_eq!(
+        exceeded(limits, "var s = \"ab\"; loop { s = s + s; }"),
+        LimitExceeded::StringLength(1000)
+    );
+    assert_eq!(
+        exceeded(limits, "var items = split(\"a,b,c,d,e,f\", \",\");"),
+        LimitExceeded::CollectionLength(5)
+    );
+    assert!(Interpreter::builder()
+        .limits(limits)
+        .build()
+        .interpret(&parse("var items = split(\"a,b,c,d,e\", \",\");"))
+        .is_ok());
+}
+
+#[test]
+fn test_limits_apply_per_turn() {
+    let program = Arc::new(Program::compile("loop { input word; speak word; }").unwrap());
+    let interpreter = Interpreter::builder()
+        .limits(Limits {
+            statements: Some(10),
+            ..Limits::default()
+        })
+        .build();
+    let mut session = Session::with_interpreter(program, interpreter);
+    session.start().unwrap();
+    for _ in 0..20 {
+        let turn = session.resume("again").unwrap();
+        assert_eq!(turn.status, Status::WaitingForInput);
+    }
+}
+
+#[test]
+fn test_listen_is_capped_by_the_time_limit() {
+    let limits = Limits {
+        time: Some(Duration::from_millis(100)),
+        ..Limits::default()
+    };
+    let started = Instant::now();
+    assert_eq!(
+        exceeded(limits, "listen 60;"),
+        LimitExceeded::Time(Duration::from_millis(100))
+    );
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(Interpreter::builder()
+        .limits(limits)
+        .build()
+        .interpret(&parse("listen 0.01;"))
+        .is_ok());
+}
+
+#[test]
+fn test_listen_rejects_invalid_durations() {
+    for input in ["-1", "NaN", "inf"] {
+        let mut interpreter = Interpreter::builder()
+            .channel(Transcript::new(&[input]))
+            .build();
+        match interpreter.interpret(&parse("inputn seconds;\nlisten seconds;")) {
+            Err(Error::Runtime { token, message }) => {
+                assert_eq!(token.line, 2);
+                assert!(message.starts_with("Listen time must be a non-negative number"));
+            }
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+    }
+    let mut interpreter = Interpreter::new();
+    match interpreter.interpret(&parse("listen \"soon\";")) {
+        Err(Error::Runtime { message, .. }) => {
+            assert_eq!(message, "Listen time must be a number, got string.")
+        }
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
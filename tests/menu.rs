@@ -0,0 +1,91 @@
+use robot_dsl::{
+    channel::Transcript, error::Error, interpreter::Interpreter, parser::Parser, scanner::Scanner,
+};
+
+const BOT: &str = r#"
+var chosen = "none";
+menu "How can I help?" {
+    "b" "Check balance" => chosen = "billing";
+    "r" | "top up" "Recharge" => { chosen = "charging"; }
+    "quit" => chosen = "quit";
+}
+speak chosen;
+"#;
+
+fn run(inputs: &[&str]) -> (Result<(), Error>, Vec<String>) {
+    let transcript = Transcript::new(inputs);
+    let mut interpreter = Interpreter::builder().channel(transcript.clone()).build();
+    let mut scanner = Scanner::new(BOT.to_string());
+    let tokens = scanner.scan_tokens();
+    let statements = Parser::new(tokens).parse().expect("Failed to parse");
+    let result = interpreter.interpret(&statements);
+    (result, transcript.outputs())
+}
+
+fn chosen(inputs: &[&str]) -> String {
+    let (result, outputs) = run(inputs);
+    assert!(result.is_ok());
+    outputs.last().cloned().unwrap()
+}
+
+#[test]
+fn test_menu_renders_numbered_options() {
+    let (_, outputs) = run(&["1"]);
+    assert_eq!(
+        outputs,
+        vec![
+            "How can I help?",
+            "1. Check balance (b)",
+            "2. Recharge (r)",
+            "3. quit",
+            "billing",
+        ]
+    );
+}
+
+#[test]
+fn test_menu_accepts_number_key_and_label() {
+    assert_eq!(chosen(&["2"]), "charging");
+    assert_eq!(chosen(&["B"]), "billing");
+    assert_eq!(chosen(&["top up"]), "charging");
+    assert_eq!(chosen(&["check balance!"]), "billing");
+}
+
+#[test]
+fn test_menu_reprompts_on_invalid_input() {
+    let (result, outputs) = run(&["weather", "7", "3"]);
+    assert!(result.is_ok());
+    assert_eq!(
+        outputs[4],
+        "Sorry, I didn't understand \"weather\". Please choose 1-3."
+    );
+    assert_eq!(outputs[5], "How can I help?");
+    assert_eq!(
+        outputs[9],
+        "Sorry, I didn't understand \"7\". Please choose 1-3."
+    );
+    assert_eq!(outputs.last().unwrap(), "quit");
+}
+
+#[test]
+fn test_menu_suggests_close_option() {
+    let (_, outputs) = run(&["recharg", "yes"]);
+    assert_eq!(outputs[4], "Did you mean \"Recharge\"?");
+    assert_eq!(outputs.last().unwrap(), "charging");
+
+    let (_, outputs) = run(&["recharg", "no", "quit"]);
+    assert_eq!(outputs.last().unwrap(), "quit");
+}
+
+#[test]
+fn test_menu_errors_when_input_ends() {
+    let (result, _) = run(&["weather"]);
+    assert!(matches!(result, Err(Error::Io(_))));
+}
+
+#[test]
+fn test_menu_is_reserved() {
+    let mut scanner = Scanner::new("var menu = 1;".to_string());
+    let tokens = scanner.scan_tokens();
+    assert!(matches!(Parser::new(tokens).parse(), Err(Error::Parse)));
+}
@@ -0,0 +1,157 @@
+use robot_dsl::{error::Error, interpreter::Interpreter, parser::Parser, scanner::Scanner};
+
+use std::cell::Cell;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+mod common;
+
+use common::{calculate, run, Workspace};
+
+fn run_file(interpreter: &mut Interpreter, path: PathBuf) -> Result<(), Error> {
+    let source = fs::read_to_string(&path)?;
+    interpreter.set_script_path(&path)?;
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    let statements = Parser::new(tokens).parse()?;
+    interpreter.interpret(&statements)
+}
+
+fn counting_interpreter() -> (Interpreter, Rc<Cell<i64>>) {
+    let loads = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&loads);
+    let interpreter = Interpreter::builder()
+        .function("loaded", move || counter.set(counter.get() + 1))
+        .build();
+    (interpreter, loads)
+}
+
+#[test]
+fn test_import_into_current_scope() {
+    let dir = Workspace::new(
+        "plain",
+        &[
+            (
+                "common/greeting.dsl",
+                "var greeting = \"hello\"; step Greet() { speak greeting; }",
+            ),
+            (
+                "main.dsl",
+                "import \"common/greeting.dsl\"; Greet(); var said = greeting;",
+            ),
+        ],
+    );
+    let mut interpreter = Interpreter::new();
+    assert!(run_file(&mut interpreter, dir.path("main.dsl")).is_ok());
+    assert_eq!(calculate(&mut interpreter, "said").unwrap(), "hello");
+}
+
+#[test]
+fn test_import_with_alias_and_relative_paths() {
+    let dir = Workspace::new(
+        "alias",
+        &[
+            (
+                "lib/billing.dsl",
+                "import \"rates.dsl\"; var fee = rate + 1;",
+            ),
+            ("lib/rates.dsl", "var rate = 2;"),
+            (
+                "main.dsl",
+                "import \"lib/billing.dsl\" as billing; var total = billing.fee;",
+            ),
+        ],
+    );
+    let mut interpreter = Interpreter::new();
+    assert!(run_file(&mut interpreter, dir.path("main.dsl")).is_ok());
+    assert_eq!(calculate(&mut interpreter, "total").unwrap(), "3");
+    assert_eq!(
+        calculate(&mut interpreter, "billing").unwrap(),
+        "<module billing>"
+    );
+    assert!(calculate(&mut interpreter, "fee").is_err());
+}
+
+#[test]
+fn test_import_executes_module_once() {
+    let dir = Workspace::new(
+        "once",
+        &[
+            ("shared.dsl", "loaded();"),
+            ("a.dsl", "import \"shared.dsl\";"),
+            (
+                "main.dsl",
+                "import \"shared.dsl\"; import \"a.dsl\"; import \"./shared.dsl\" as s;",
+            ),
+        ],
+    );
+    let (mut interpreter, loads) = counting_interpreter();
+    assert!(run_file(&mut interpreter, dir.path("main.dsl")).is_ok());
+    assert_eq!(loads.get(), 1);
+}
+
+#[test]
+fn test_import_cycle_is_detected() {
+    let dir = Workspace::new(
+        "cycle",
+        &[
+            ("a.dsl", "import \"b.dsl\";"),
+            ("b.dsl", "import \"a.dsl\";"),
+        ],
+    );
+    let mut interpreter = Interpreter::new();
+    match run_file(&mut interpreter, dir.path("a.dsl")) {
+        Err(Error::Runtime { message, .. }) => {
+            assert_eq!(message, "Import cycle detected: a.dsl -> b.dsl -> a.dsl.")
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_import_missing_file() {
+    let dir = Workspace::new("missing", &[("main.dsl", "import \"nope.dsl\";")]);
+    let mut interpreter = Interpreter::new();
+    match run_file(&mut interpreter, dir.path("main.dsl")) {
+        Err(Error::Runtime { token, message }) => {
+            assert_eq!(token.lexeme, "import");
+            assert!(message.starts_with("Cannot import 'nope.dsl'"));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_import_names_file_with_syntax_error() {
+    let dir = Workspace::new(
+        "syntax",
+        &[
+            ("broken.dsl", "var = 1;"),
+            ("main.dsl", "import \"broken.dsl\";"),
+        ],
+    );
+    let mut interpreter = Interpreter::new();
+    match run_file(&mut interpreter, dir.path("main.dsl")) {
+        Err(Error::Runtime { token, message }) => {
+            assert_eq!(token.lexeme, "import");
+            let broken = dir.path("broken.dsl").canonicalize().unwrap();
+            assert_eq!(
+                message,
+                format!("Syntax error in imported file '{}'.", broken.display())
+            );
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_import_and_as_are_reserved() {
+    for word in ["import", "as"] {
+        let source = format!("var {} = 1;", word);
+        assert!(matches!(
+            run(&mut Interpreter::new(), &source),
+            Err(Error::Parse)
+        ));
+    }
+}
@@ -0,0 +1,91 @@
+use robot_dsl::{error::Error, function::Function, interpreter::Interpreter, object::Object};
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+mod common;
+
+use common::{calculate, run};
+
+#[test]
+fn test_native_closure_captures_state() {
+    let counter = Rc::new(Cell::new(0.0));
+    let captured = Rc::clone(&counter);
+    let next = Function::native("next", 0, move |_, _| {
+        captured.set(captured.get() + 1.0);
+        Ok(Object::Number(captured.get()))
+    });
+
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .globals
+        .borrow_mut()
+        .define("next".to_string(), Object::Callable(next));
+    assert!(run(&mut interpreter, "next(); next();").is_ok());
+    assert_eq!(calculate(&mut interpreter, "next()").unwrap(), "3");
+    assert_eq!(counter.get(), 3.0);
+}
+
+#[test]
+fn test_native_calls_back_into_step() {
+    let twice = Function::native("twice", 1, |interpreter, args| {
+        interpreter.call(&args[0], &[])?;
+        interpreter.call(&args[0], &[])
+    });
+
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .globals
+        .borrow_mut()
+        .define("twice".to_string(), Object::Callable(twice));
+    let source = "var count = 0; step Bump() { count = count + 1; } twice(Bump);";
+    assert!(run(&mut interpreter, source).is_ok());
+    assert_eq!(calculate(&mut interpreter, "count").unwrap(), "2");
+}
+
+#[test]
+fn test_native_map_and_filter() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        calculate(&mut interpreter, "map(split(\"a b\", \" \"), upper)").unwrap(),
+        "[A, B]"
+    );
+    assert_eq!(
+        calculate(
+            &mut interpreter,
+            "filter(split(\"1 x 2\", \" \"), is_number)"
+        )
+        .unwrap(),
+        "[]"
+    );
+    assert_eq!(
+        calculate(&mut interpreter, "len(filter(split(\"a  b\", \" \"), len))").unwrap(),
+        "3"
+    );
+}
+
+#[test]
+fn test_native_error_reports_call_site() {
+    let fail = Function::native("fail", 0, |_, _| {
+        Err(Error::Native("account service unavailable.".to_string()))
+    });
+
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .globals
+        .borrow_mut()
+        .define("fail".to_string(), Object::Callable(fail));
+    match run(&mut interpreter, "\n\nfail();") {
+        Err(Error::Runtime { token, message }) => {
+            assert_eq!(token.line, 3);
+            assert_eq!(message, "account service unavailable.");
+        }
+        _ => panic!("expected a runtime error"),
+    }
+}
+
+#[test]
+fn test_native_callback_arity_error() {
+    let mut interpreter = Interpreter::new();
+    assert!(calculate(&mut interpreter, "map(split(\"a\", \" \"), replace)").is_err());
+}
@@ -0,0 +1,113 @@
+use robot_dsl::{
+    channel::Transcript,
+    error::Error,
+    interpreter::{Interpreter, DEFAULT_MAX_CALL_DEPTH, STACK_SIZE},
+    program::Program,
+    session::{Session, Status},
+};
+
+use std::sync::Arc;
+use std::thread;
+
+mod common;
+
+use common::parse;
+
+const GO_BACK: &str = r#"
+var visits = 0;
+step Main() {
+    visits = visits + 1;
+    menu "Main" {
+        "b" "Billing" => Billing();
+        "q" "Quit" => speak "Visited " + visits;
+    }
+}
+step Billing() {
+    speak "Billing";
+    Main();
+}
+Main();
+"#;
+
+const RUNAWAY: &str = r#"
+step Ask(times) {
+    Spin(times + 1);
+    speak "unreachable";
+}
+step Spin(times) {
+    Ask(times);
+}
+Ask(0);
+"#;
+
+fn message(result: Result<(), Error>) -> String {
+    match result {
+        Err(Error::Runtime { message, .. }) => message,
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_tail_calls_do_not_deepen_the_stack() {
+    let mut inputs = vec!["b"; 1000];
+    inputs.push("q");
+    let transcript = Transcript::new(&inputs);
+    let mut interpreter = Interpreter::builder()
+        .channel(transcript.clone())
+        .max_call_depth(10)
+        .build();
+    assert!(interpreter.interpret(&parse(GO_BACK)).is_ok());
+    assert_eq!(transcript.outputs().last().unwrap(), "Visited 1001");
+}
+
+#[test]
+fn test_max_call_depth_reports_trace() {
+    let mut interpreter = Interpreter::builder()
+        .channel(Transcript::new(&[]))
+        .max_call_depth(20)
+        .build();
+    let message = message(interpreter.interpret(&parse(RUNAWAY)));
+    assert!(message.starts_with("Maximum call depth of 20 exceeded."));
+    // Spin 中对 Ask 的调用是尾调用，不会留下 Spin 的记录
+    assert!(message.contains("in Ask called on line 9"));
+    assert!(message.ends_with("in Ask called on line 7 (19 times)"));
+}
+
+#[test]
+fn test_default_call_depth_fits_the_interpreter_stack() {
+    let expected = format!("Maximum call depth of {} exceeded.", DEFAULT_MAX_CALL_DEPTH);
+    const { assert!(DEFAULT_MAX_CALL_DEPTH >= 1000) };
+    let deep = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            for source in [
+                "step f() { var x = f(); } f();",
+                "step f(n) { branch (true) { loop { branch (true) { var x = f(n + 1); } } } } f(0);",
+            ] {
+                let mut interpreter = Interpreter::new();
+                assert!(message_of(&mut interpreter, source).starts_with(&expected));
+            }
+        })
+        .unwrap();
+    deep.join().unwrap();
+}
+
+fn message_of(interpreter: &mut Interpreter, source: &str) -> String {
+    message(interpreter.interpret(&parse(source)))
+}
+
+#[test]
+fn test_tail_calls_resume_after_input() {
+    let program = Arc::new(Program::compile(GO_BACK).unwrap());
+    let mut interpreter = Interpreter::new();
+    interpreter.set_max_call_depth(3);
+    let mut session = Session::with_interpreter(program, interpreter);
+    session.start().unwrap();
+    for _ in 0..10 {
+        let turn = session.resume("b").unwrap();
+        assert_eq!(turn.utterances[0], "Billing");
+    }
+    let turn = session.resume("q").unwrap();
+    assert_eq!(turn.utterances, vec!["Visited 11"]);
+    assert_eq!(turn.status, Status::Finished);
+}
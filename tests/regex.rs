@@ -0,0 +1,89 @@
+use robot_dsl::{
+    error::Error,
+    host::IntoObject,
+    interpreter::{Interpreter, REGEX_CACHE_SIZE},
+};
+
+use std::rc::Rc;
+
+mod common;
+
+use common::{calculate, run};
+
+fn with_reply(reply: &str) -> Interpreter {
+    Interpreter::builder()
+        .constant("reply", reply.into_object())
+        .build()
+}
+
+#[test]
+fn test_matches_named_groups() {
+    let mut interpreter = with_reply("my order is A-1024, phone 13800138000");
+    let source = r#"
+        var m = matches(reply, "order is (?P<order>[A-Z]-\d+)");
+        var order = m.order;
+        var whole = get(m, 0);
+    "#;
+    assert!(run(&mut interpreter, source).is_ok());
+    assert_eq!(calculate(&mut interpreter, "order").unwrap(), "A-1024");
+    assert_eq!(
+        calculate(&mut interpreter, "whole").unwrap(),
+        "order is A-1024"
+    );
+}
+
+#[test]
+fn test_matches_numbered_and_optional_groups() {
+    let mut interpreter = with_reply("recharge 50");
+    assert_eq!(
+        calculate(&mut interpreter, r#"matches(reply, "(\w+) (\d+)( yuan)?")"#).unwrap(),
+        "{0: recharge 50, 1: recharge, 2: 50, 3: nil}"
+    );
+    assert_eq!(
+        calculate(&mut interpreter, r#"matches(reply, "^\d+$")"#).unwrap(),
+        "nil"
+    );
+}
+
+#[test]
+fn test_find_all() {
+    let mut interpreter = with_reply("call 13800138000 or 13900139000");
+    assert_eq!(
+        calculate(&mut interpreter, r#"find_all(reply, "1\d{10}")"#).unwrap(),
+        "[13800138000, 13900139000]"
+    );
+}
+
+#[test]
+fn test_regex_compiled_once() {
+    let mut interpreter = with_reply("b");
+    let source = r#"
+        var i = 0;
+        step Check() { matches(reply, "^[a-z]$"); find_all(reply, "^[a-z]$"); }
+        Check(); Check(); Check();
+    "#;
+    assert!(run(&mut interpreter, source).is_ok());
+    assert_eq!(interpreter.cached_regex_count(), 1);
+}
+
+#[test]
+fn test_regex_cache_is_bounded() {
+    let mut interpreter = with_reply("b");
+    for i in 0..REGEX_CACHE_SIZE * 2 {
+        interpreter.regex(&format!("^{}$", i)).unwrap();
+    }
+    assert_eq!(interpreter.cached_regex_count(), REGEX_CACHE_SIZE);
+    let recent = interpreter.regex("^0$").unwrap();
+    assert!(Rc::ptr_eq(&recent, &interpreter.regex("^0$").unwrap()));
+    assert_eq!(interpreter.cached_regex_count(), REGEX_CACHE_SIZE);
+}
+
+#[test]
+fn test_invalid_pattern() {
+    let mut interpreter = with_reply("b");
+    match calculate(&mut interpreter, r#"matches(reply, "(")"#) {
+        Err(Error::Runtime { message, .. }) => assert!(message.starts_with("Invalid pattern '('")),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(calculate(&mut interpreter, "matches(reply, \"x\").order").is_err());
+}
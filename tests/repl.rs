@@ -0,0 +1,79 @@
+use robot_dsl::{
+    channel::Transcript,
+    interpreter::Interpreter,
+    repl::{Repl, Reply},
+};
+
+use std::fs;
+
+fn repl(transcript: &Transcript) -> Repl {
+    let transcript = transcript.clone();
+    Repl::new(move || Interpreter::builder().channel(transcript.clone()).build())
+}
+
+fn print(text: &str) -> Reply {
+    Reply::Print(text.to_string())
+}
+
+#[test]
+fn test_repl_continues_incomplete_input() {
+    let transcript = Transcript::new(&[]);
+    let mut repl = repl(&transcript);
+    assert_eq!(repl.prompt(), "> ");
+    assert_eq!(repl.feed("step Greet(name) {"), Reply::More);
+    assert_eq!(repl.prompt(), ".. ");
+    assert_eq!(repl.feed("  speak \"Hello, \" + name; // {"), Reply::More);
+    assert_eq!(repl.feed("}"), print(""));
+    assert_eq!(repl.prompt(), "> ");
+    assert_eq!(repl.feed("Greet(\"Ada\")"), print(""));
+    assert_eq!(transcript.take_outputs(), vec!["Hello, Ada"]);
+    assert_eq!(repl.history().len(), 2);
+    assert!(repl.history()[0].starts_with("step Greet(name) {\n"));
+}
+
+#[test]
+fn test_repl_prints_expressions_and_recovers_from_errors() {
+    let transcript = Transcript::new(&[]);
+    let mut repl = repl(&transcript);
+    assert_eq!(repl.feed("var count = 1;"), print(""));
+    assert_eq!(repl.feed("count + 1"), print("2"));
+    assert_eq!(repl.feed("count + 1;"), print("2"));
+    assert_eq!(
+        repl.feed("missing + 1"),
+        Reply::Error("[line 1] Undefined variable 'missing'.".to_string())
+    );
+    assert_eq!(repl.feed("count = count + 1;"), print("2"));
+    assert_eq!(repl.feed("\"a\" + \"b\""), print("ab"));
+    assert_eq!(repl.feed("exit;"), Reply::Quit);
+}
+
+#[test]
+fn test_repl_prints_nil_strings_and_keeps_effects_before_errors() {
+    let transcript = Transcript::new(&[]);
+    let mut repl = repl(&transcript);
+    assert_eq!(repl.feed("nil"), print(""));
+    assert_eq!(repl.feed("\"nil\""), print("nil"));
+    assert!(matches!(
+        repl.feed("var kept = 1; missing;"),
+        Reply::Error(_)
+    ));
+    assert_eq!(repl.feed("kept"), print("1"));
+}
+
+#[test]
+fn test_repl_meta_commands() {
+    let transcript = Transcript::new(&[]);
+    let mut repl = repl(&transcript);
+    let path = std::env::temp_dir().join(format!("robot-dsl-repl-{}.dsl", std::process::id()));
+    fs::write(&path, "var greeting = \"hi\";\nconst limit = 3;\n").unwrap();
+
+    assert_eq!(repl.feed(&format!(":load {}", path.display())), print(""));
+    assert_eq!(repl.feed(":env"), print("greeting = \"hi\"\nlimit = 3"));
+    assert_eq!(repl.feed(":reset"), print("Session reset."));
+    assert_eq!(repl.feed(":env"), print(""));
+    assert!(matches!(repl.feed(":load"), Reply::Error(_)));
+    assert!(matches!(repl.feed(":unknown"), Reply::Error(_)));
+    assert!(matches!(repl.feed(":help"), Reply::Print(text) if text.contains(":load")));
+    assert_eq!(repl.feed(":quit"), Reply::Quit);
+    fs::remove_file(&path).unwrap();
+}
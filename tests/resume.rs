@@ -0,0 +1,142 @@
+use robot_dsl::{
+    error::Error,
+    program::Program,
+    session::{Session, Status, Turn},
+};
+
+use std::sync::Arc;
+
+const CHARGE: &str = r#"
+var bill = 0;
+
+step Charging(prefix) {
+    speak "Please enter your recharge amount";
+    inputn amount;
+    bill = bill + amount;
+    speak prefix + bill;
+}
+
+speak "Hello, who is there?";
+input name;
+speak "Hi " + name;
+loop {
+    menu "What can I do for you?" {
+        "b" "Check balance" => speak "Your balance is " + bill;
+        "r" "Recharge" => {
+            var greeting = "Thanks, " + name;
+            Charging("Your balance is ");
+            speak greeting;
+        }
+        "e" "Quit" => exit
+    }
+}
+"#;
+
+fn session(source: &str) -> Session {
+    Session::new(Arc::new(Program::compile(source).unwrap()))
+}
+
+fn waiting(utterances: &[&str]) -> Turn {
+    Turn {
+        utterances: utterances.iter().map(|s| s.to_string()).collect(),
+        status: Status::WaitingForInput,
+    }
+}
+
+const MENU: [&str; 4] = [
+    "What can I do for you?",
+    "1. Check balance (b)",
+    "2. Recharge (r)",
+    "3. Quit (e)",
+];
+
+#[test]
+fn test_resume_runs_until_next_input() {
+    let mut session = session(CHARGE);
+    assert_eq!(session.status(), None);
+    assert_eq!(session.start().unwrap(), waiting(&["Hello, who is there?"]));
+
+    let mut expected = vec!["Hi adam"];
+    expected.extend(MENU);
+    assert_eq!(session.resume("adam").unwrap(), waiting(&expected));
+
+    assert_eq!(
+        session.resume("recharge").unwrap(),
+        waiting(&["Please enter your recharge amount"])
+    );
+
+    let mut expected = vec!["Your balance is 30", "Thanks, adam"];
+    expected.extend(MENU);
+    assert_eq!(session.resume("30").unwrap(), waiting(&expected));
+
+    let turn = session.resume("qiut").unwrap();
+    assert_eq!(turn, waiting(&["Did you mean \"Quit\"?"]));
+
+    let turn = session.resume("yes").unwrap();
+    assert_eq!(turn.status, Status::Finished);
+    assert!(turn.utterances.is_empty());
+    assert_eq!(session.resume("hello?").unwrap().status, Status::Finished);
+}
+
+#[test]
+fn test_sessions_resume_independently() {
+    let program = Arc::new(Program::compile(CHARGE).unwrap());
+    let mut alice = Session::new(Arc::clone(&program));
+    let mut bob = Session::new(Arc::clone(&program));
+
+    alice.resume("alice").unwrap();
+    bob.resume("bob").unwrap();
+    alice.resume("r").unwrap();
+    bob.resume("r").unwrap();
+    let alice_turn = alice.resume("5").unwrap();
+    let bob_turn = bob.resume("7").unwrap();
+    assert_eq!(
+        alice_turn.utterances[..2],
+        ["Your balance is 5", "Thanks, alice"]
+    );
+    assert_eq!(
+        bob_turn.utterances[..2],
+        ["Your balance is 7", "Thanks, bob"]
+    );
+}
+
+#[test]
+fn test_resume_state_machine() {
+    let source = r#"
+        var count = 0;
+        state Ask {
+            on enter { speak "Say something"; }
+            on "again" => { count = count + 1; input extra; speak "Got " + extra; }
+            on "stop" => goto Done;
+        }
+        state Done {
+            on enter { speak "Counted " + count; }
+        }
+        branch (true) goto Ask;
+        speak "after";
+    "#;
+    let mut session = session(source);
+    assert_eq!(session.start().unwrap(), waiting(&["Say something"]));
+    assert_eq!(session.resume("again").unwrap(), waiting(&[]));
+    assert_eq!(
+        session.resume("more").unwrap(),
+        waiting(&["Got more", "Say something"])
+    );
+    let turn = session.resume("stop").unwrap();
+    assert_eq!(turn.utterances, vec!["Counted 1", "after"]);
+    assert_eq!(turn.status, Status::Finished);
+}
+
+#[test]
+fn test_cannot_wait_inside_expression() {
+    let source = "step Ask() { input x; }\nspeak \"before\";\nvar y = Ask();";
+    let mut session = session(source);
+    match session.start() {
+        Err(Error::Runtime { token, message }) => {
+            assert_eq!(token.line, 3);
+            assert_eq!(message, "Cannot wait for input inside an expression.");
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(session.status(), Some(Status::Finished));
+}
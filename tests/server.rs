@@ -0,0 +1,258 @@
+use robot_dsl::{program::Program, server::Server};
+
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+const GREETER: &str = r#"
+speak "Who is there?";
+input name;
+speak "Hi " + name;
+menu "Anything else?" {
+    "y" "Yes" => { input wish; speak "Noted: " + wish; }
+    "n" "No" => speak "Bye";
+}
+"#;
+
+fn start() -> SocketAddr {
+    serve(GREETER)
+}
+
+fn serve(source: &str) -> SocketAddr {
+    serve_with(source, |_| ())
+}
+
+fn serve_with(source: &str, configure: impl FnOnce(&mut Server)) -> SocketAddr {
+    let program = Program::compile(source).unwrap();
+    let mut server = Server::bind("127.0.0.1:0", program).unwrap();
+    configure(&mut server);
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    address
+}
+
+struct LineClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl LineClient {
+    fn connect(address: SocketAddr) -> Self {
+        let stream = TcpStream::connect(address).unwrap();
+        LineClient {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            writer: stream,
+        }
+    }
+
+    fn expect(&mut self, expected: &str) {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        assert_eq!(line.trim_end(), expected);
+    }
+
+    fn say(&mut self, text: &str) {
+        writeln!(self.writer, "{}", text).unwrap();
+    }
+}
+
+fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+    request_with(address, method, path, "", body)
+}
+
+fn request_with(
+    address: SocketAddr,
+    method: &str,
+    path: &str,
+    headers: &str,
+    body: &str,
+) -> (u16, Value) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        headers,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1;
+    (status, serde_json::from_str(body).unwrap_or(Value::Null))
+}
+
+#[test]
+fn test_server_line_clients_are_independent() {
+    let address = start();
+    let mut alice = LineClient::connect(address);
+    let mut bob = LineClient::connect(address);
+    alice.expect("Who is there?");
+    bob.expect("Who is there?");
+
+    bob.say("bob");
+    alice.say("alice");
+    alice.expect("Hi alice");
+    bob.expect("Hi bob");
+
+    alice.say("no");
+    for line in ["Anything else?", "1. Yes (y)", "2. No (n)", "Bye"] {
+        alice.expect(line);
+    }
+    let mut rest = String::new();
+    alice.reader.read_to_string(&mut rest).unwrap();
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn test_server_http_sessions() {
+    let address = start();
+    let (status, first) = request(address, "POST", "/sessions", "");
+    assert_eq!(status, 201);
+    assert_eq!(first["status"], "waiting");
+    assert_eq!(first["utterances"][0], "Who is there?");
+    let id = first["session"].as_str().unwrap().to_string();
+    let path = format!("/sessions/{}", id);
+
+    let (_, other) = request(address, "POST", "/sessions", "");
+    assert_ne!(other["session"], first["session"]);
+
+    let (status, turn) = request(address, "POST", &path, r#"{"text": "adam"}"#);
+    assert_eq!(status, 200);
+    assert_eq!(turn["utterances"][0], "Hi adam");
+
+    request(address, "POST", &path, r#"{"text": "yes"}"#);
+    let (_, turn) = request(address, "POST", &path, r#"{"text": "tea"}"#);
+    assert_eq!(turn["utterances"], serde_json::json!(["Noted: tea"]));
+    assert_eq!(turn["status"], "finished");
+
+    assert_eq!(request(address, "POST", &path, r#"{"text": "?"}"#).0, 404);
+    assert_eq!(request(address, "POST", "/sessions/1", "nonsense").0, 400);
+    assert_eq!(request(address, "GET", "/sessions", "").0, 405);
+}
+
+#[test]
+fn test_server_delete_cancels_running_session() {
+    let address = serve("input word; loop {}");
+    let (_, first) = request(address, "POST", "/sessions", "");
+    let path = format!("/sessions/{}", first["session"].as_str().unwrap());
+
+    let stuck = {
+        let path = path.clone();
+        thread::spawn(move || request(address, "POST", &path, r#"{"text": "spin"}"#))
+    };
+    thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(request(address, "DELETE", &path, "").0, 204);
+    let (_, turn) = stuck.join().unwrap();
+    assert_eq!(turn["error"], "Cancelled");
+}
+
+#[test]
+fn test_server_sessions_share_storage_per_user() {
+    let source = r#"
+        var visits = load("visits");
+        branch (visits == nil) visits = 0;
+        store("visits", visits + 1);
+        speak visits + 1;
+    "#;
+    let address = serve_with(source, |server| {
+        server.add_user("token-adam", "adam");
+        server.add_user("token-eve", "eve");
+    });
+    let visit = |headers: &str| {
+        request_with(address, "POST", "/sessions", headers, "").1["utterances"][0].clone()
+    };
+    assert_eq!(visit("Authorization: Bearer token-adam\r\n"), "1");
+    assert_eq!(visit("Authorization: Bearer token-adam\r\n"), "2");
+    assert_eq!(visit("Authorization: Bearer token-eve\r\n"), "1");
+    // 匿名会话各自使用私有的存储
+    assert_eq!(visit(""), "1");
+    assert_eq!(visit(""), "1");
+}
+
+#[test]
+fn test_server_rejects_client_supplied_users() {
+    let address = serve_with(GREETER, |server| server.add_user("token-adam", "adam"));
+    let (status, body) = request(address, "POST", "/sessions", r#"{"user": "adam"}"#);
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("access token"));
+
+    let forged = "Authorization: Bearer adam\r\n";
+    assert_eq!(
+        request_with(address, "POST", "/sessions", forged, "").0,
+        401
+    );
+    let valid = "Authorization: Bearer token-adam\r\n";
+    assert_eq!(request_with(address, "POST", "/sessions", valid, "").0, 201);
+}
+
+#[test]
+fn test_server_rejects_oversized_requests() {
+    let address = start();
+    let long = format!("/sessions?{}", "a".repeat(16 * 1024));
+    assert_eq!(request(address, "POST", &long, "").0, 400);
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "POST /sessions HTTP/1.1\r\n").unwrap();
+    for header in 0..100 {
+        write!(stream, "X-Header-{}: {}\r\n", header, header).unwrap();
+    }
+    write!(stream, "\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 400"));
+    assert!(response.contains("too many headers"));
+}
+
+#[test]
+fn test_server_cancels_line_session_when_client_disconnects() {
+    let address = serve_with(
+        "speak \"counting\"; var n = 0; loop { n = n + 1; }",
+        |server| server.set_max_sessions(1),
+    );
+    let mut client = LineClient::connect(address);
+    client.expect("counting");
+    drop(client);
+
+    // 取消的会话结束后才让出唯一的名额
+    for _ in 0..100 {
+        let mut next = LineClient::connect(address);
+        let mut line = String::new();
+        next.reader.read_line(&mut line).unwrap();
+        if line.trim_end() == "counting" {
+            return;
+        }
+        assert_eq!(line.trim_end(), "Too many sessions, try again later.");
+        thread::sleep(Duration::from_millis(20));
+    }
+    panic!("the disconnected session kept running");
+}
+
+#[test]
+fn test_server_limits_concurrent_sessions() {
+    let address = serve_with(GREETER, |server| server.set_max_sessions(1));
+    let (status, first) = request(address, "POST", "/sessions", "");
+    assert_eq!(status, 201);
+    let (status, body) = request(address, "POST", "/sessions", "");
+    assert_eq!(status, 503);
+    assert_eq!(body["error"], "Too many sessions, try again later.");
+    LineClient::connect(address).expect("Too many sessions, try again later.");
+
+    let path = format!("/sessions/{}", first["session"].as_str().unwrap());
+    assert_eq!(request(address, "DELETE", &path, "").0, 204);
+    // 会话线程退出后名额才释放
+    let mut status = 503;
+    for _ in 0..100 {
+        status = request(address, "POST", "/sessions", "").0;
+        if status != 503 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(status, 201);
+}
@@ -0,0 +1,100 @@
+use robot_dsl::{
+    channel::Transcript, error::Error, interpreter::Interpreter, object::Object, program::Program,
+    session::Session, symbol::Symbol,
+};
+
+use std::sync::Arc;
+
+const BOT: &str = r#"
+var bill = 0;
+
+step Charging(amount) {
+    bill = bill + amount;
+    speak "Your balance is " + bill;
+}
+
+input name;
+speak "Hello, " + name;
+Charging(10);
+"#;
+
+fn session(program: &Arc<Program>, inputs: &[&str]) -> (Session, Transcript) {
+    let transcript = Transcript::new(inputs);
+    let interpreter = Interpreter::builder().channel(transcript.clone()).build();
+    (
+        Session::with_interpreter(Arc::clone(program), interpreter),
+        transcript,
+    )
+}
+
+#[test]
+fn test_sessions_share_program_but_not_state() {
+    let program = Arc::new(Program::compile(BOT).unwrap());
+    assert_eq!(program.steps(), ["Charging"]);
+
+    let (mut alice, alice_transcript) = session(&program, &["alice"]);
+    let (mut bob, bob_transcript) = session(&program, &["bob"]);
+    assert!(alice.run().is_ok());
+    assert!(alice.call("Charging", &[Object::Number(5.0)]).is_ok());
+    assert!(bob.run().is_ok());
+
+    assert_eq!(
+        alice_transcript.outputs(),
+        vec!["Hello, alice", "Your balance is 10", "Your balance is 15"]
+    );
+    assert_eq!(
+        bob_transcript.outputs(),
+        vec!["Hello, bob", "Your balance is 10"]
+    );
+    assert!(alice.get("bill").unwrap().equals(&Object::Number(15.0)));
+    assert!(bob.get("bill").unwrap().equals(&Object::Number(10.0)));
+
+    drop(alice);
+    assert_eq!(Arc::strong_count(&program), 2);
+}
+
+#[test]
+fn test_exit_ends_only_the_session() {
+    let program =
+        Arc::new(Program::compile("speak \"bye\"; exit\nspeak \"unreachable\";").unwrap());
+    let (mut session, transcript) = session(&program, &[]);
+    assert!(session.run().is_ok());
+    assert_eq!(transcript.outputs(), vec!["bye"]);
+}
+
+#[test]
+fn test_compile_reports_parse_errors() {
+    assert!(matches!(
+        Program::compile("var = 1;\nspeak \"ok\";"),
+        Err(Error::Parse)
+    ));
+}
+
+#[test]
+fn test_call_undefined_step() {
+    let program = Arc::new(Program::compile("var x = 1;").unwrap());
+    let (mut session, _) = session(&program, &[]);
+    assert!(session.run().is_ok());
+    assert!(session.call("Missing", &[]).is_err());
+}
+
+#[test]
+fn test_get_does_not_intern_unknown_names() {
+    let program = Arc::new(Program::compile(BOT).unwrap());
+    let (mut session, _) = session(&program, &["adam"]);
+    session.run().unwrap();
+    assert!(session.get("bill").unwrap().equals(&Object::Number(10.0)));
+
+    let name = "never_declared_in_any_script_7f3a";
+    assert!(session.get(name).is_none());
+    assert_eq!(Symbol::get(name), None);
+    assert_eq!(Symbol::get("bill"), Some(Symbol::intern("bill")));
+}
+
+#[test]
+fn test_compile_interns_only_identifiers() {
+    let source = "var greeting = \"literal_never_interned_9c1d\";\nspeak greeting;\n";
+    Program::compile(source).unwrap();
+    assert_eq!(Symbol::get("literal_never_interned_9c1d"), None);
+    assert_eq!(Symbol::get("greeting"), Some(Symbol::intern("greeting")));
+}
@@ -0,0 +1,199 @@
+use robot_dsl::{
+    interpreter::Interpreter,
+    program::Program,
+    session::{Session, Status},
+    snapshot::SnapshotError,
+    symbol::Symbol,
+};
+
+use std::sync::Arc;
+
+const CHARGE: &str = r#"
+var bill = 0;
+
+step Charging(prefix) {
+    speak "Please enter your recharge amount";
+    inputn amount;
+    bill = bill + amount;
+    speak prefix + bill;
+}
+
+input name;
+loop {
+    menu "What can I do for you?" {
+        "b" "Check balance" => speak "Your balance is " + bill;
+        "r" "Recharge" => {
+            var greeting = "Thanks, " + upper(name);
+            Charging("Your balance is ");
+            speak greeting;
+        }
+        "e" "Quit" => exit
+    }
+}
+"#;
+
+fn program(source: &str) -> Arc<Program> {
+    Arc::new(Program::compile(source).unwrap())
+}
+
+fn paused_in_charging() -> String {
+    let mut session = Session::new(program(CHARGE));
+    session.start().unwrap();
+    session.resume("adam").unwrap();
+    session.resume("r").unwrap();
+    session.snapshot().unwrap()
+}
+
+#[test]
+fn test_snapshot_restores_in_new_session() {
+    let saved = paused_in_charging();
+    assert!(saved.contains("\"version\":1"));
+
+    let mut session = Session::restore(program(CHARGE), Interpreter::new(), &saved).unwrap();
+    assert_eq!(session.status(), Some(Status::WaitingForInput));
+    let turn = session.resume("12").unwrap();
+    assert_eq!(
+        &turn.utterances[..2],
+        ["Your balance is 12", "Thanks, ADAM"]
+    );
+
+    let saved = session.snapshot().unwrap();
+    let mut session = Session::restore(program(CHARGE), Interpreter::new(), &saved).unwrap();
+    session.resume("r").unwrap();
+    let turn = session.resume("8").unwrap();
+    assert_eq!(turn.utterances[0], "Your balance is 20");
+    assert_eq!(session.resume("e").unwrap().status, Status::Finished);
+}
+
+#[test]
+fn test_snapshot_state_machine() {
+    let source = r#"
+state Ask {
+    on enter { speak "Coffee or tea?"; }
+    on "coffee" => goto Done;
+    on _ => speak "Sorry?";
+}
+state Done {
+    on enter { speak "Enjoy!"; }
+}
+goto Ask;
+"#;
+    let mut session = Session::new(program(source));
+    session.start().unwrap();
+    session.resume("water").unwrap();
+    let saved = session.snapshot().unwrap();
+
+    let mut session = Session::restore(program(source), Interpreter::new(), &saved).unwrap();
+    let turn = session.resume("coffee").unwrap();
+    assert_eq!(turn.utterances, vec!["Enjoy!"]);
+    assert_eq!(turn.status, Status::Finished);
+}
+
+#[test]
+fn test_snapshot_rejects_incompatible_input() {
+    let saved = paused_in_charging();
+
+    let changed = CHARGE.replace("Thanks", "Thank you");
+    match Session::restore(program(&changed), Interpreter::new(), &saved) {
+        Err(SnapshotError::ScriptChanged { .. }) => (),
+        other => panic!("expected ScriptChanged, got {:?}", other.err()),
+    }
+
+    let future = saved.replace("\"version\":1", "\"version\":99");
+    match Session::restore(program(CHARGE), Interpreter::new(), &future) {
+        Err(SnapshotError::Version { found: 99, .. }) => (),
+        other => panic!("expected Version, got {:?}", other.err()),
+    }
+
+    match Session::restore(program(CHARGE), Interpreter::new(), "{\"version\":1}") {
+        Err(SnapshotError::Format(_)) => (),
+        other => panic!("expected Format, got {:?}", other.err()),
+    }
+
+    let session = Session::new(program(CHARGE));
+    assert!(matches!(session.snapshot(), Err(SnapshotError::NotPaused)));
+
+    let tampered = saved.replace("{\"Running\":1}", "{\"Running\":7}");
+    assert_ne!(tampered, saved);
+    match Session::restore(program(CHARGE), Interpreter::new(), &tampered) {
+        Err(SnapshotError::Incompatible(_)) => (),
+        other => panic!("expected Incompatible, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn test_snapshot_survives_comment_and_layout_edits() {
+    let saved = paused_in_charging();
+    let edited = format!(
+        "// charge bot\n\n{}",
+        CHARGE.replace(
+            "    inputn amount;",
+            "    // ask again\n\n    inputn amount;"
+        )
+    );
+    let mut session = Session::restore(program(&edited), Interpreter::new(), &saved).unwrap();
+    let turn = session.resume("5").unwrap();
+    assert_eq!(turn.utterances[0], "Your balance is 5");
+}
+
+#[test]
+fn test_snapshot_rejects_undeclared_names() {
+    let saved = paused_in_charging();
+    let name = "snapshot_injected_name_4e2a";
+    let tampered = saved.replace("\"bill\":{", &format!("\"{}\":{{", name));
+    assert_ne!(tampered, saved);
+    match Session::restore(program(CHARGE), Interpreter::new(), &tampered) {
+        Err(SnapshotError::Incompatible(message)) => assert!(message.contains(name)),
+        other => panic!("expected Incompatible, got {:?}", other.err()),
+    }
+    assert_eq!(Symbol::get(name), None);
+}
+
+#[test]
+fn test_snapshot_inside_state_machine_started_by_host() {
+    let source = r#"
+var cups = 0;
+state Ask {
+    on enter { speak "Coffee or tea?"; }
+    on "coffee" => {
+        cups = cups + 1;
+        speak "Cup " + cups;
+        goto Ask;
+    }
+    on "bye" => goto Done;
+}
+state Done {
+    on enter { speak "Enjoy!"; }
+}
+"#;
+    let mut session = Session::new(program(source));
+    let turn = session.start_machine("Ask").unwrap();
+    assert_eq!(turn.utterances, vec!["Coffee or tea?"]);
+    assert_eq!(turn.status, Status::WaitingForInput);
+    session.resume("coffee").unwrap();
+    let saved = session.snapshot().unwrap();
+
+    let mut session = Session::restore(program(source), Interpreter::new(), &saved).unwrap();
+    let turn = session.resume("coffee").unwrap();
+    assert_eq!(turn.utterances, vec!["Cup 2", "Coffee or tea?"]);
+    let saved = session.snapshot().unwrap();
+
+    let mut session = Session::restore(program(source), Interpreter::new(), &saved).unwrap();
+    let turn = session.resume("bye").unwrap();
+    assert_eq!(turn.utterances, vec!["Enjoy!"]);
+    assert_eq!(turn.status, Status::Finished);
+}
+
+#[test]
+fn test_snapshot_tells_apart_declarations_on_one_line() {
+    let source =
+        "step f() { speak \"first\"; } step f() { input x; speak \"second \" + x; }\nf();\n";
+    let mut session = Session::new(program(source));
+    session.start().unwrap();
+    let saved = session.snapshot().unwrap();
+    assert!(saved.contains("\"index\":1"));
+
+    let mut session = Session::restore(program(source), Interpreter::new(), &saved).unwrap();
+    let turn = session.resume("x").unwrap();
+    assert_eq!(turn.utterances, vec!["second x"]);
+}
@@ -0,0 +1,135 @@
+use robot_dsl::{
+    channel::Transcript, error::Error, interpreter::Interpreter, parser::Parser, scanner::Scanner,
+};
+
+mod common;
+
+use common::run;
+
+const BOT: &str = r#"
+var bill = 10;
+
+state Welcome {
+    on enter {
+        speak "Welcome, how can I help?";
+    }
+    on "balance" | "check balance" => goto Billing;
+    on "recharge" => goto Charging;
+    on "bye" => goto Goodbye;
+    on _ => speak "Sorry?";
+}
+
+state Billing {
+    on enter {
+        speak "Your balance is " + bill;
+        goto Welcome;
+    }
+}
+
+state Charging {
+    on enter {
+        speak "How much?";
+    }
+    on "ten" => {
+        bill = bill + 10;
+        goto Welcome;
+    }
+}
+
+state Goodbye {
+    on enter {
+        speak "Bye!";
+    }
+}
+"#;
+
+fn interpreter(inputs: &[&str]) -> (Interpreter, Transcript) {
+    let transcript = Transcript::new(inputs);
+    let mut interpreter = Interpreter::builder().channel(transcript.clone()).build();
+    let mut scanner = Scanner::new(BOT.to_string());
+    let tokens = scanner.scan_tokens();
+    let statements = Parser::new(tokens).parse().expect("Failed to parse");
+    interpreter.interpret(&statements).expect("Failed to run");
+    (interpreter, transcript)
+}
+
+#[test]
+fn test_state_machine_runs_until_terminal_state() {
+    let (mut interpreter, transcript) = interpreter(&[
+        "check my balance",
+        "weather",
+        "recharge",
+        "ten",
+        "balance",
+        "bye",
+    ]);
+    assert!(interpreter.run_state_machine("Welcome").is_ok());
+    assert_eq!(
+        transcript.outputs(),
+        vec![
+            "Welcome, how can I help?",
+            "Your balance is 10",
+            "Welcome, how can I help?",
+            "Sorry?",
+            "Welcome, how can I help?",
+            "How much?",
+            "Welcome, how can I help?",
+            "Your balance is 20",
+            "Welcome, how can I help?",
+            "Bye!",
+        ]
+    );
+}
+
+#[test]
+fn test_goto_statement_starts_machine() {
+    let (mut interpreter, transcript) = interpreter(&["bye"]);
+    assert!(run(&mut interpreter, "goto Welcome; speak \"after\";").is_ok());
+    assert_eq!(
+        transcript.outputs(),
+        vec!["Welcome, how can I help?", "Bye!", "after"]
+    );
+}
+
+#[test]
+fn test_goto_does_not_grow_the_stack() {
+    let mut interpreter = Interpreter::new();
+    let source = r#"
+        var n = 0;
+        state Ping { on enter { n = n + 1; branch (n != 100000) goto Pong; } }
+        state Pong { on enter { goto Ping; } }
+        goto Ping;
+    "#;
+    assert!(run(&mut interpreter, source).is_ok());
+    let mut scanner = Scanner::new("n".to_string());
+    let expression = Parser::new(scanner.scan_tokens()).calculate().unwrap();
+    assert_eq!(interpreter.interpret_cal(&expression).unwrap(), "100000");
+}
+
+#[test]
+fn test_goto_errors() {
+    let (mut interpreter, _) = interpreter(&[]);
+    match run(&mut interpreter, "var x = 1;\ngoto x;") {
+        Err(Error::Runtime { token, message }) => {
+            assert_eq!(token.line, 2);
+            assert_eq!(message, "Can only goto a state, got number.");
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(matches!(
+        interpreter.run_state_machine("Welcome"),
+        Err(Error::Io(_))
+    ));
+    assert!(interpreter.run_state_machine("Missing").is_err());
+}
+
+#[test]
+fn test_state_and_on_and_goto_are_reserved() {
+    for word in ["state", "on", "goto"] {
+        let source = format!("var {} = 1;", word);
+        assert!(matches!(
+            run(&mut Interpreter::new(), &source),
+            Err(Error::Parse)
+        ));
+    }
+}
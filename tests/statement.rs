@@ -5,7 +5,7 @@ fn test_interpreter_speak_string() {
     let speaking: String = "speak \"hello\";".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -16,7 +16,7 @@ fn test_interpreter_speak_number() {
     let speaking: String = "speak 1;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -27,7 +27,7 @@ fn test_interpreter_speak_true() {
     let speaking: String = "speak true;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -38,7 +38,7 @@ fn test_interpreter_speak_false() {
     let speaking: String = "speak false;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -49,7 +49,7 @@ fn test_interpreter_speak_expression() {
     let speaking: String = "speak 1 + 1;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -60,7 +60,7 @@ fn test_interpreter_var_expression() {
     let speaking: String = "var a = 1 + 1; speak a;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -71,7 +71,7 @@ fn test_interpreter_var_expression_with_assignment() {
     let speaking: String = "var a = 1 + 1; speak a; a = 2; speak a;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -82,7 +82,7 @@ fn test_interpreter_var_expression_with_assignment_and_reassignment() {
     let speaking: String = "var a = 1 + 1; speak a; a = 2; speak a; a = 3; speak a;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -93,7 +93,7 @@ fn test_interpreter_scope() {
     let speaking: String = "var a = 1 + 1; speak a; { var a = 2; speak a; } speak a;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -101,10 +101,11 @@ fn test_interpreter_scope() {
 
 #[test]
 fn test_interpreter_scope_with_reassignment() {
-    let speaking: String = "var a = 1 + 1; speak a; { var a = 2; speak a; a = 3; speak a; } speak a;".to_string();
+    let speaking: String =
+        "var a = 1 + 1; speak a; { var a = 2; speak a; a = 3; speak a; } speak a;".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -115,7 +116,7 @@ fn test_interpreter_branch_statement() {
     let speaking: String = "branch (true) { speak 1; }".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -126,7 +127,7 @@ fn test_interpreter_loop_exit_statement() {
     let speaking: String = "loop { speak 1; exit }".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -137,7 +138,7 @@ fn test_interpreter_function_statement() {
     let speaking: String = "step speak_one() { speak 1; } speak_one();".to_string();
     let mut scanner = Scanner::new(speaking);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(&tokens);
+    let mut parser = Parser::new(tokens);
     let statements = parser.parse().unwrap();
     let mut interpreter = Interpreter::new();
     assert!(interpreter.interpret(&statements).is_ok());
@@ -0,0 +1,113 @@
+use robot_dsl::{error::Error, interpreter::Interpreter, parser::Parser, scanner::Scanner};
+
+fn calculate(source: &str) -> Result<String, Error> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let expression = parser.calculate().expect("Failed to calculate");
+    let mut interpreter = Interpreter::new();
+    interpreter.interpret_cal(&expression)
+}
+
+#[test]
+fn test_stdlib_string_helpers() {
+    assert_eq!(calculate("len(\"hello\")").unwrap(), "5");
+    assert_eq!(calculate("upper(\"Hello\")").unwrap(), "HELLO");
+    assert_eq!(calculate("lower(\"Hello\")").unwrap(), "hello");
+    assert_eq!(calculate("trim(\"  hi  \")").unwrap(), "hi");
+    assert_eq!(calculate("contains(\"balance\", \"lan\")").unwrap(), "true");
+    assert_eq!(calculate("split(\"a,b,c\", \",\")").unwrap(), "[a, b, c]");
+    assert_eq!(calculate("len(split(\"a,b,c\", \",\"))").unwrap(), "3");
+    assert_eq!(
+        calculate("replace(\"a-b-c\", \"-\", \"+\")").unwrap(),
+        "a+b+c"
+    );
+}
+
+#[test]
+fn test_stdlib_number_helpers() {
+    assert_eq!(calculate("floor(parse(\"3.7\"))").unwrap(), "3");
+    assert_eq!(calculate("round(parse(\"3.5\"))").unwrap(), "4");
+    assert_eq!(calculate("to_string(12) + \"!\"").unwrap(), "12!");
+}
+
+#[test]
+fn test_stdlib_type_checks() {
+    assert_eq!(calculate("type_of(1)").unwrap(), "number");
+    assert_eq!(calculate("type_of(\"a\")").unwrap(), "string");
+    assert_eq!(calculate("type_of(nil)").unwrap(), "nil");
+    assert_eq!(calculate("is_string(\"a\")").unwrap(), "true");
+    assert_eq!(calculate("is_number(\"a\")").unwrap(), "false");
+    assert_eq!(calculate("is_step(len)").unwrap(), "true");
+}
+
+#[test]
+fn test_stdlib_random_with_seed() {
+    let mut scanner = Scanner::new("random()".to_string());
+    let tokens = scanner.scan_tokens();
+    let expression = Parser::new(tokens).calculate().unwrap();
+    let mut scanner = Scanner::new("seed(42)".to_string());
+    let tokens = scanner.scan_tokens();
+    let seed = Parser::new(tokens).calculate().unwrap();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.interpret_cal(&seed).unwrap();
+    let first = interpreter.interpret_cal(&expression).unwrap();
+    interpreter.interpret_cal(&seed).unwrap();
+    let second = interpreter.interpret_cal(&expression).unwrap();
+    assert_eq!(first, second);
+
+    let value: f64 = first.parse().unwrap();
+    assert!((0.0..1.0).contains(&value));
+}
+
+#[test]
+fn test_stdlib_format_time() {
+    assert_eq!(
+        calculate("format_time(0, \"%Y-%m-%d %H:%M:%S\")").unwrap(),
+        "1970-01-01 00:00:00"
+    );
+    assert_eq!(
+        calculate("format_time(1700000000, \"%Y/%m/%d %H:%M\")").unwrap(),
+        "2023/11/14 22:13"
+    );
+}
+
+#[test]
+fn test_stdlib_type_error() {
+    match calculate("upper(1)") {
+        Err(Error::Runtime { message, .. }) => {
+            assert_eq!(message, "upper: expected string but got number.")
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(calculate("parse(\"abc\")").is_err());
+}
+
+#[test]
+fn test_stdlib_arity_error() {
+    match calculate("len(\"a\", \"b\")") {
+        Err(Error::Runtime { message, .. }) => {
+            assert_eq!(message, "Expected 1 arguments but got 2.")
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_stdlib_closest() {
+    let options = "split(\"balance,recharge,exit\", \",\")";
+    assert_eq!(
+        calculate(&format!("closest(\"balanse\", {})", options)).unwrap(),
+        "balance"
+    );
+    assert_eq!(
+        calculate(&format!("closest(\"Recharge!\", {})", options)).unwrap(),
+        "recharge"
+    );
+    assert_eq!(
+        calculate(&format!("closest(\"weather\", {})", options)).unwrap(),
+        "nil"
+    );
+    assert!(calculate("closest(\"a\", \"a\")").is_err());
+}
@@ -0,0 +1,110 @@
+use robot_dsl::{
+    channel::Transcript,
+    error::Error,
+    interpreter::Interpreter,
+    object::Object,
+    program::Program,
+    session::Session,
+    storage::{FileStorage, MemoryStorage, Storage},
+};
+
+use std::fs;
+use std::sync::Arc;
+use std::thread;
+
+const COUNTER: &str = r#"
+var visits = load("visits");
+branch (visits == nil) visits = 0;
+visits = visits + 1;
+store("visits", visits);
+store("profile", matches("adam 30", "(?P<name>\w+) (?P<age>\d+)"));
+speak visits;
+"#;
+
+fn run(user: &str, storage: impl Storage + 'static) -> Vec<String> {
+    let transcript = Transcript::new(&[]);
+    let interpreter = Interpreter::builder()
+        .channel(transcript.clone())
+        .storage(user, storage)
+        .build();
+    let program = Arc::new(Program::compile(COUNTER).unwrap());
+    Session::with_interpreter(program, interpreter)
+        .run()
+        .unwrap();
+    transcript.outputs()
+}
+
+#[test]
+fn test_memory_storage_is_per_user() {
+    let storage = MemoryStorage::new();
+    assert_eq!(run("adam", storage.clone()), vec!["1"]);
+    assert_eq!(run("adam", storage.clone()), vec!["2"]);
+    assert_eq!(run("eve", storage.clone()), vec!["1"]);
+
+    let mut interpreter = Interpreter::builder().storage("adam", storage).build();
+    match interpreter.load("profile").unwrap() {
+        Object::Map(profile) => {
+            assert_eq!(profile["name"].to_string(), "adam");
+        }
+        other => panic!("expected a map, got {}", other),
+    }
+    let len = interpreter.globals.borrow().lookup("len").unwrap();
+    assert!(matches!(
+        interpreter.store("step", &len),
+        Err(Error::Native(_))
+    ));
+}
+
+#[test]
+fn test_file_storage_survives_restart() {
+    let path = std::env::temp_dir().join(format!("robot-dsl-storage-{}.json", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    assert_eq!(run("adam", FileStorage::new(&path)), vec!["1"]);
+    assert_eq!(run("adam", FileStorage::new(&path)), vec!["2"]);
+    assert_eq!(run("eve", FileStorage::new(&path)), vec!["1"]);
+    assert!(fs::read_to_string(&path)
+        .unwrap()
+        .contains("\"visits\": 2.0"));
+
+    fs::write(&path, "not json").unwrap();
+    let interpreter = Interpreter::builder()
+        .storage("adam", FileStorage::new(&path))
+        .build();
+    assert!(matches!(interpreter.load("visits"), Err(Error::Native(_))));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_file_storage_concurrent_writers_keep_every_store() {
+    let path =
+        std::env::temp_dir().join(format!("robot-dsl-concurrent-{}.json", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let writers: Vec<_> = (0..8)
+        .map(|writer| {
+            let path = path.clone();
+            thread::spawn(move || {
+                let mut storage = FileStorage::new(&path);
+                for key in 0..10 {
+                    let user = format!("user{}", writer);
+                    storage.store(&user, &key.to_string(), key.into()).unwrap();
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let storage = FileStorage::new(&path);
+    for writer in 0..8 {
+        for key in 0..10 {
+            assert!(storage
+                .load(&format!("user{}", writer), &key.to_string())
+                .unwrap()
+                .is_some());
+        }
+    }
+    fs::remove_file(&path).unwrap();
+}
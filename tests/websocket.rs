@@ -0,0 +1,128 @@
+use robot_dsl::{
+    program::Program,
+    server::Server,
+    storage::{MemoryStorage, Storage},
+};
+
+use serde_json::Value;
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+use tungstenite::{HandshakeError, Message, WebSocket};
+
+const GREETER: &str = r#"
+speak "How old are you?";
+inputn age;
+var next = age + 1;
+speak "Next year you will be " + next;
+"#;
+
+fn start() -> SocketAddr {
+    let program = Program::compile(GREETER).unwrap();
+    let server = Server::bind("127.0.0.1:0", program).unwrap();
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    address
+}
+
+fn connect(address: SocketAddr) -> WebSocket<TcpStream> {
+    let stream = TcpStream::connect(address).unwrap();
+    let url = format!("ws://{}/ws", address);
+    tungstenite::client(url.as_str(), stream).unwrap().0
+}
+
+fn receive(socket: &mut WebSocket<TcpStream>) -> Value {
+    match socket.read().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        other => panic!("expected a text message, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_websocket_session() {
+    let address = start();
+    let mut socket = connect(address);
+
+    let greeting = receive(&mut socket);
+    assert_eq!(greeting["type"], "speak");
+    assert_eq!(greeting["text"], "How old are you?");
+    assert!(greeting["timestamp"].as_u64().unwrap() > 0);
+
+    socket
+        .send(Message::text(r#"{"type": "message", "text": "41"}"#))
+        .unwrap();
+    assert_eq!(receive(&mut socket)["text"], "Next year you will be 42");
+    assert_eq!(receive(&mut socket)["type"], "end");
+    assert!(matches!(socket.read(), Ok(Message::Close(_))));
+}
+
+#[test]
+fn test_websocket_reports_errors() {
+    let address = start();
+    let mut socket = connect(address);
+    receive(&mut socket);
+
+    socket.send(Message::text("forty")).unwrap();
+    let error = receive(&mut socket);
+    assert_eq!(error["type"], "error");
+    assert!(error["text"]
+        .as_str()
+        .unwrap()
+        .contains("Expected a number"));
+}
+
+#[test]
+fn test_websocket_close_cancels_session() {
+    let program =
+        Program::compile("speak \"counting\"; var n = 0; loop { n = n + 1; store(\"n\", n); }")
+            .unwrap();
+    let mut server = Server::bind("127.0.0.1:0", program).unwrap();
+    let storage = MemoryStorage::new();
+    server.set_storage(storage.clone());
+    server.add_user("token-adam", "adam");
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let stream = TcpStream::connect(address).unwrap();
+    let url = format!("ws://{}/ws?token=token-adam", address);
+    let mut socket = tungstenite::client(url.as_str(), stream).unwrap().0;
+    assert_eq!(receive(&mut socket)["text"], "counting");
+    let count = || storage.load("adam", "n").unwrap();
+    while count().is_none() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    drop(socket);
+    thread::sleep(Duration::from_millis(300));
+    let stopped = count();
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(count(), stopped);
+}
+
+fn handshake(address: SocketAddr, query: &str) -> u16 {
+    let stream = TcpStream::connect(address).unwrap();
+    let url = format!("ws://{}/ws?{}", address, query);
+    match tungstenite::client(url.as_str(), stream) {
+        Err(HandshakeError::Failure(tungstenite::Error::Http(response))) => {
+            response.status().as_u16()
+        }
+        Ok(_) => 101,
+        Err(other) => panic!("unexpected handshake error {:?}", other),
+    }
+}
+
+#[test]
+fn test_websocket_rejects_client_supplied_users() {
+    let address = start();
+    assert_eq!(handshake(address, "user=adam"), 400);
+    assert_eq!(handshake(address, "token=unknown"), 401);
+}
+
+#[test]
+fn test_websocket_rejects_sessions_over_the_limit() {
+    let program = Program::compile(GREETER).unwrap();
+    let mut server = Server::bind("127.0.0.1:0", program).unwrap();
+    server.set_max_sessions(0);
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    assert_eq!(handshake(address, ""), 503);
+}